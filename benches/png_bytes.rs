@@ -0,0 +1,36 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::{Png, Rgba};
+use std::str::FromStr;
+
+/// A generated 64x64 PNG plus about 10 MB of extra IDAT chunks
+fn large_png_bytes() -> Vec<u8> {
+    let color = Rgba::from_str("336699FF").unwrap();
+    let mut png = Png::generate(64, 64, color).unwrap();
+    let idat = ChunkType::from_str("IDAT").unwrap();
+    let position = png.position_of(&ChunkType::IEND).unwrap();
+    for i in 0..10u8 {
+        let chunk = Chunk::new(idat, vec![i; 1024 * 1024]);
+        png.insert_chunk(position, chunk).unwrap();
+    }
+    png.into_bytes()
+}
+
+fn serialize(c: &mut Criterion) {
+    let bytes = large_png_bytes();
+    let parse = || Png::try_from(bytes.as_slice()).unwrap();
+
+    let mut group = c.benchmark_group("serialize 10 MB");
+    group.sample_size(20);
+    group.bench_function("as_bytes", |b| {
+        b.iter_batched(parse, |png| png.as_bytes(), BatchSize::LargeInput)
+    });
+    group.bench_function("into_bytes", |b| {
+        b.iter_batched(parse, Png::into_bytes, BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -1,11 +1,22 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Args, Subcommand};
+use clap::{Parser, Args, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
+use pngme::compress::Compression;
+use pngme::png::Rgba;
 
 #[derive(Debug, Parser)]
 pub struct PngMeArgs {
-    #[clap(subcommand)]
+    #[command(subcommand)]
     pub command: PngMeCommands,
+    /// Write the same bytes every time: made-up chunk types and encryption
+    /// salts and nonces come from --seed, and timestamps from
+    /// SOURCE_DATE_EPOCH, or 1970-01-01 when it is not set
+    #[arg(long, global = true)]
+    pub deterministic: bool,
+    /// Seed used by --deterministic
+    #[arg(long, global = true, requires = "deterministic", default_value_t = 0)]
+    pub seed: u64,
 }
 
 #[derive(Subcommand, Debug)]
@@ -14,39 +25,456 @@ pub enum PngMeCommands {
     Decode(DecodeArgs),
     Remove(RemoveArgs),
     Print(PrintArgs),
+    Extract(ExtractArgs),
+    Rename(RenameArgs),
+    Strip(StripArgs),
+    Reorder(ReorderArgs),
+    Verify(VerifyArgs),
+    Info(InfoArgs),
+    Merge(MergeArgs),
+    Doctor(DoctorArgs),
+    Steg(StegArgs),
+    Capacity(CapacityArgs),
+    Generate(GenerateArgs),
+    Completions(CompletionsArgs),
+    #[cfg(feature = "json")]
+    Export(ExportArgs),
+    #[cfg(feature = "json")]
+    Import(ImportArgs),
+}
+
+/// How listing commands such as `print` and `info` lay out their output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Plain text for reading in a terminal
+    Human,
+    /// JSON for scripts
+    Json,
+    /// CSV with a header row, for spreadsheets
+    Csv,
+}
+
+/// When to color the output of `print`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    /// Color when stdout is a terminal and `NO_COLOR` is not set
+    Auto,
+    /// Always color, even when writing to a file or pipe
+    Always,
+    /// Never color
+    Never,
 }
 
 #[derive(Debug, Args)]
 pub struct EncodeArgs {
-    #[clap(required = true, parse(from_os_str))]
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
     pub file_path: PathBuf,
-    #[clap(required = true)]
-    pub chunk_type: String,
-    #[clap(required = true)]
-    pub message: String,
-    #[clap(required = false, parse(from_os_str))]
-    pub output_file: Option<PathBuf>
+    /// The chunk type to encode into, or `random` for a new private one that
+    /// the file doesn't have yet
+    #[arg(required_unless_present_any = &["batch_file", "pair"])]
+    pub chunk_type: Option<String>,
+    #[arg(required_unless_present_any = &["batch_file", "pair"])]
+    pub message: Option<String>,
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output_file: Option<PathBuf>,
+    /// Fail instead of warning when the result is not a structurally valid PNG
+    #[arg(long)]
+    pub strict: bool,
+    /// Also record the current time in the tIME chunk
+    #[arg(long)]
+    pub timestamp: bool,
+    /// Allow encoding into a critical chunk type such as IDAT
+    #[arg(long)]
+    pub force_critical: bool,
+    /// CSV file of `chunk_type,message` rows to encode instead of a single
+    /// message. Quote messages that have commas in them.
+    #[arg(long, value_hint = ValueHint::FilePath, conflicts_with_all = &["chunk_type", "message"])]
+    pub batch_file: Option<PathBuf>,
+    /// A `TYPE=MESSAGE` pair to encode instead of a single message. Repeat it
+    /// to add several chunks, in the order given.
+    #[arg(
+        long,
+        value_name = "TYPE=MESSAGE",
+        conflicts_with_all = &["chunk_type", "message", "batch_file"]
+    )]
+    pub pair: Vec<String>,
+    /// Drop any bytes found after the IEND chunk instead of keeping them
+    #[arg(long)]
+    pub strip_trailing: bool,
+    /// Rewrite only the end of the file instead of parsing and rewriting all of
+    /// it. Chunk CRCs are not checked.
+    #[arg(long, conflicts_with_all = &["strict", "timestamp", "strip_trailing"])]
+    pub fast: bool,
+    /// Skip each chunk to encode that is already in the file with the same
+    /// data, instead of adding a duplicate, and leave the file alone if that
+    /// skips them all
+    #[arg(long, conflicts_with = "fast")]
+    pub idempotent: bool,
+    /// Compress each message with `zlib` or, with the `zstd` feature, `zstd`,
+    /// optionally at a given level such as `zstd:19`
+    #[arg(long, value_name = "METHOD[:LEVEL]")]
+    pub compress: Option<Compression>,
+    /// Encrypt each message with a key derived from this password. A fresh
+    /// salt and nonce make every encryption differ, so it can't be combined
+    /// with `--idempotent`.
+    #[cfg(feature = "encryption")]
+    #[arg(long, conflicts_with = "idempotent")]
+    pub password: Option<String>,
+    /// Add Reed-Solomon parity to each message so that `decode --ecc` can
+    /// repair damaged bytes
+    #[cfg(feature = "ecc")]
+    #[arg(long)]
+    pub ecc: bool,
+    /// Percentage of each error correction block given to parity
+    #[cfg(feature = "ecc")]
+    #[arg(
+        long,
+        requires = "ecc",
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u8).range(1..=90)
+    )]
+    pub parity: u8,
+    /// Read the written file back and check that every new chunk holds what
+    /// was encoded, deleting the file if one does not
+    #[arg(long)]
+    pub verify: bool,
+    /// Don't print a summary of the changes or show a progress bar
+    #[arg(short, long)]
+    pub quiet: bool,
+    #[command(flatten)]
+    pub download: DownloadArgs,
 }
 
 #[derive(Debug, Args)]
 pub struct DecodeArgs {
-    #[clap(required = true, parse(from_os_str))]
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
     pub file_path: PathBuf,
-    #[clap(required = true)]
+    #[arg(required = true)]
     pub chunk_type: String,
+    /// Fail instead of replacing invalid UTF-8 in the message
+    #[arg(long)]
+    pub strict: bool,
+    /// Write the message bytes as they are, with no trailing newline. This is
+    /// the default when stdout is not a terminal.
+    #[arg(long, conflicts_with_all = &["text", "strict"])]
+    pub raw: bool,
+    /// Print the message as text followed by a newline, even when stdout is
+    /// not a terminal
+    #[arg(long)]
+    pub text: bool,
+    /// Decode the Nth matching chunk (0-indexed) instead of the first
+    #[arg(long)]
+    pub nth: Option<usize>,
+    /// Decode every matching chunk, one message per line
+    #[arg(long, conflicts_with = "nth")]
+    pub all: bool,
+    /// Print how many chunks match instead of decoding them
+    #[arg(long, conflicts_with_all = &["quiet", "nth"])]
+    pub count: bool,
+    /// Print nothing, and only exit with an error if there is no matching chunk
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Parse the message as JSON and pretty-print it
+    #[cfg(feature = "json")]
+    #[arg(long)]
+    pub json: bool,
+    /// Skip checking chunk CRCs, which is faster and lets damaged files be read
+    #[arg(long)]
+    pub no_verify: bool,
+    /// Read large files normally instead of memory-mapping them
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    pub no_mmap: bool,
+    /// Decrypt messages written by `encode --password`
+    #[cfg(feature = "encryption")]
+    #[arg(long, requires = "password")]
+    pub decrypt: bool,
+    /// Password to decrypt the message with
+    #[cfg(feature = "encryption")]
+    #[arg(long, requires = "decrypt")]
+    pub password: Option<String>,
+    /// Decompress messages written by `encode --compress`, whatever the method
+    #[arg(long)]
+    pub decompress: bool,
+    /// Repair messages written by `encode --ecc`. Chunk CRCs are not checked,
+    /// as damaged chunks fail them.
+    #[cfg(feature = "ecc")]
+    #[arg(long)]
+    pub ecc: bool,
+    #[command(flatten)]
+    pub download: DownloadArgs,
 }
 
 
 #[derive(Debug, Args)]
 pub struct RemoveArgs {
-    #[clap(required = true, parse(from_os_str))]
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
     pub file_path: PathBuf,
-    #[clap(required = true)]
+    #[arg(required = true)]
     pub chunk_type: String,
+    /// Fail instead of warning when the result is not a structurally valid PNG
+    #[arg(long)]
+    pub strict: bool,
+    /// Allow removing a critical chunk type such as IHDR
+    #[arg(long)]
+    pub force_critical: bool,
+    /// Don't print a summary of the changes
+    #[arg(short, long)]
+    pub quiet: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct PrintArgs {
-    #[clap(required = true, parse(from_os_str))]
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    /// Print every field of each chunk along with a preview of its data
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// Output format, where `json` and `csv` give one record per chunk
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
+    /// Print the number and total size of the chunks of each type, largest
+    /// first, instead of every chunk
+    #[arg(long, conflicts_with_all = &["verbose", "data", "data_hex"])]
+    pub summary: bool,
+    /// Also print the data of each chunk as text, replacing invalid UTF-8
+    #[arg(long)]
+    pub data: bool,
+    /// Also print the data of each chunk as hex. Takes precedence over `--data`.
+    #[arg(long)]
+    pub data_hex: bool,
+    /// Color chunk types by kind, and chunks with a bad CRC in red
+    #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+    /// Skip checking chunk CRCs, which is faster and lets damaged files be read
+    #[arg(long)]
+    pub no_verify: bool,
+    /// Read large files normally instead of memory-mapping them
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    pub no_mmap: bool,
+    #[command(flatten)]
+    pub download: DownloadArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    #[arg(required = true)]
+    pub chunk_type: String,
+    /// File to write the raw chunk data to, or `-` for stdout
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub output_file: PathBuf,
+    /// Extract the Nth matching chunk (0-indexed) instead of the first
+    #[arg(long)]
+    pub nth: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    #[arg(required = true)]
+    pub old_type: String,
+    #[arg(required = true)]
+    pub new_type: String,
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output_file: Option<PathBuf>,
+    /// Rename every matching chunk instead of only the first
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct StripArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
     pub file_path: PathBuf,
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output_file: Option<PathBuf>,
+    /// Ancillary chunk type to keep, may be repeated
+    #[arg(long = "keep")]
+    pub keep: Vec<String>,
+    /// Also drop any bytes found after the IEND chunk
+    #[arg(long)]
+    pub strip_trailing: bool,
+}
+
+/// Moves chunks into an order the PNG spec allows
+#[derive(Debug, Args)]
+pub struct ReorderArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct VerifyArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_paths: Vec<PathBuf>,
+    /// Number of files to check at once, defaults to the number of CPUs
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    pub jobs: Option<usize>,
+    /// Don't show progress bars for large files
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Read large files normally instead of memory-mapping them
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    pub no_mmap: bool,
+    #[command(flatten)]
+    pub download: DownloadArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    /// Fail on warnings too, not only on errors
+    #[arg(long)]
+    pub strict: bool,
+    #[command(flatten)]
+    pub download: DownloadArgs,
+}
+
+/// Hides a message in the image samples instead of in a chunk
+#[derive(Debug, Args)]
+pub struct StegArgs {
+    #[command(subcommand)]
+    pub command: StegCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StegCommands {
+    Encode(StegEncodeArgs),
+    Decode(StegDecodeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct StegEncodeArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    #[arg(required = true)]
+    pub message: String,
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct StegDecodeArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+}
+
+/// Reports how much data a PNG file has room for
+#[derive(Debug, Args)]
+pub struct CapacityArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    /// Data bytes per chunk when a message is split over several chunks
+    #[arg(long, default_value_t = 65536)]
+    pub chunk_size: usize,
+    /// How many bytes the new chunks may add to the file in total
+    #[arg(long, default_value_t = 1 << 20)]
+    pub budget: usize,
+    /// Output format for the report
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
+}
+
+/// Writes a minimal valid PNG filled with a single color
+#[derive(Debug, Args)]
+pub struct GenerateArgs {
+    pub width: u32,
+    pub height: u32,
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub output_file: PathBuf,
+    /// The fill color as hex digits RRGGBBAA
+    #[arg(long, value_name = "RRGGBBAA", default_value = "FFFFFFFF")]
+    pub color: Rgba,
+}
+
+/// Prints a shell completion script to stdout
+#[derive(Debug, Args)]
+pub struct CompletionsArgs {
+    /// The shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+/// Options for input files given as HTTP(S) URLs, empty without the `http`
+/// feature
+#[derive(Debug, Args)]
+pub struct DownloadArgs {
+    /// Refuse to download input files larger than this many bytes
+    #[cfg(feature = "http")]
+    #[arg(long, default_value_t = pngme::http::DEFAULT_MAX_DOWNLOAD_SIZE)]
+    pub max_download_size: u64,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    /// Print the header fields as a JSON object, same as `--format json`
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+    /// Output format for the header fields
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// PNG file to copy chunks from
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub src: PathBuf,
+    /// PNG file to add the chunks to
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub dst: PathBuf,
+    /// Where to save the result, defaults to overwriting the destination
+    #[arg(required = false, value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+    /// Also merge critical chunks, except IHDR and IEND
+    #[arg(long)]
+    pub critical: bool,
+    /// Replace chunks of types the destination already has instead of skipping them
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub file_path: PathBuf,
+    /// JSON file to write the chunk list to
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub output_file: PathBuf,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// JSON file written by `export`
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub input_file: PathBuf,
+    #[arg(required = true, value_hint = ValueHint::FilePath)]
+    pub output_file: PathBuf,
+    /// Write the `crc` fields as they are instead of recomputing every CRC
+    #[arg(long)]
+    pub trust_crc: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_args_are_consistent() {
+        PngMeArgs::command().debug_assert();
+    }
 }
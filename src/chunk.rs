@@ -1,11 +1,12 @@
+use std::borrow::Cow;
 use std::fmt;
-use std::io::{BufReader, Read};
+use std::io::{self, Read, Write};
 
 use anyhow::{bail, Context};
-use crc::{Crc, CRC_32_ISO_HDLC};
 
-use crate::{Error, Result};
+use crate::Result;
 use crate::chunk_type::ChunkType;
+use crate::error::{PngError, PngResult};
 
 #[derive(Debug, Clone)]
 pub struct Chunk {
@@ -14,114 +15,720 @@ pub struct Chunk {
     crc: u32,
 }
 
+/// The CRC-32 used by PNG, built once instead of on every checksum
+#[cfg(feature = "pure-rust-crc")]
+static PNG_CRC: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Computes the CRC of a chunk, which covers its type bytes followed by its data
+#[cfg(feature = "pure-rust-crc")]
+pub(crate) fn png_crc(chunk_type: &[u8; 4], data: &[u8]) -> u32 {
+    let mut digest = PNG_CRC.digest();
+    digest.update(chunk_type);
+    digest.update(data);
+    digest.finalize()
+}
+
+/// Computes the CRC of a chunk, which covers its type bytes followed by its
+/// data. `crc32fast` uses SIMD where the CPU supports it, which matters on
+/// files that are mostly IDAT data.
+#[cfg(not(feature = "pure-rust-crc"))]
+pub(crate) fn png_crc(chunk_type: &[u8; 4], data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Computes the same CRC-32 over `data` alone, without a chunk type
+#[cfg(feature = "pure-rust-crc")]
+fn data_crc(data: &[u8]) -> u32 {
+    PNG_CRC.checksum(data)
+}
+
+/// Computes the same CRC-32 over `data` alone, without a chunk type
+#[cfg(not(feature = "pure-rust-crc"))]
+fn data_crc(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+/// Parses chunk data as JSON. Errors say what is wrong with the data rather
+/// than passing on serde's message.
+#[cfg(feature = "json")]
+pub fn json_from_data(data: &[u8]) -> Result<serde_json::Value> {
+    let text = std::str::from_utf8(data).context("Chunk: Data is not valid UTF-8")?;
+    serde_json::from_str(text).map_err(|e| {
+        anyhow::anyhow!(
+            "Chunk: Data is text but not valid JSON (problem at line {}, column {})",
+            e.line(),
+            e.column()
+        )
+    })
+}
+
+/// Fills `buf` from `r` as much as possible, returning how many bytes were read
+fn read_up_to<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Fills `buf` from `r`, failing if the input ends before the chunk `field`
+/// starting at `offset` is complete
+fn read_field<R: Read>(
+    r: &mut R,
+    buf: &mut [u8],
+    field: &'static str,
+    offset: usize,
+) -> PngResult<()> {
+    let found = read_up_to(r, buf)?;
+    if found != buf.len() {
+        return Err(PngError::TruncatedChunk {
+            offset,
+            field,
+            expected: buf.len(),
+            found,
+        });
+    }
+    Ok(())
+}
+
 impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
+    type Error = PngError;
+
+    /// Parses a buffer holding exactly one chunk. Use `Chunk::from_bytes_prefix`
+    /// to parse a chunk followed by other bytes.
+    fn try_from(value: &[u8]) -> PngResult<Self> {
+        let (chunk, consumed) = Chunk::from_bytes_prefix(value)?;
+        if consumed != value.len() {
+            return Err(PngError::TrailingBytes {
+                count: value.len() - consumed,
+            });
+        }
+        Ok(chunk)
+    }
+}
 
-    fn try_from(value: &[u8]) -> Result<Self> {
-        let mut reader = BufReader::new(value);
+impl From<Chunk> for Vec<u8> {
+    /// Same as `Chunk::as_bytes`
+    fn from(chunk: Chunk) -> Self {
+        chunk.as_bytes()
+    }
+}
 
-        let mut length_field: [u8; 4] = [0, 0, 0, 0]; 
-        reader.read_exact(&mut length_field).context("Chunk: Not enough bytes in the length field")?;
-        let length = u32::from_be_bytes(length_field);
+impl From<&Chunk> for Vec<u8> {
+    /// Same as `Chunk::as_bytes`
+    fn from(chunk: &Chunk) -> Self {
+        chunk.as_bytes()
+    }
+}
 
-        let mut chunk_type_field: [u8; 4] = [0, 0, 0, 0];
-        reader.read_exact(&mut chunk_type_field)?;
-        let chunk_type = ChunkType::try_from(chunk_type_field).context("Chunk: Not enough bytes in the chunck type field")?;
-
-        let mut data_handle = reader.take(length as u64);
-        let mut data = Vec::with_capacity(length as usize);
-        let read_len = data_handle.read_to_end(&mut data)?;
-        
-        if data.len() != length as usize && read_len != length as usize {
-            bail!("Chunk: Not enough bytes in the data field");
+/// Iterates over the chunks read from `R`, stopping after `IEND` or at the end
+/// of the input. Reading stops at the first error.
+pub struct ChunkReader<R> {
+    reader: R,
+    verify_crc: bool,
+    done: bool,
+}
+
+impl<R: Read> ChunkReader<R> {
+    pub fn new(reader: R) -> Self {
+        ChunkReader {
+            reader,
+            verify_crc: true,
+            done: false,
         }
+    }
 
-        // This should never happen (famous last words)
-        if data.len() != read_len {
-            panic!("OH NOES");
+    /// Like `new`, but yields chunks without checking their CRC
+    pub fn unchecked(reader: R) -> Self {
+        ChunkReader {
+            reader,
+            verify_crc: false,
+            done: false,
         }
+    }
 
-        let mut reader = data_handle.into_inner();
+    /// Returns the underlying reader, positioned after the last chunk read
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
 
-        let mut crc_field: [u8; 4] = [0, 0, 0, 0];
-        reader.read_exact(&mut crc_field).context("Chunk: Not enough bytes in the CRC field")?;
-        let crc = u32::from_be_bytes(crc_field);
+impl<R: Read> Iterator for ChunkReader<R> {
+    type Item = PngResult<Chunk>;
 
-        let combined_collection: Vec<u8> = chunk_type_field
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect();
-        if crc != Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&combined_collection) {
-            bail!("Chunk: Crc check failed");
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
         }
 
-        Ok(Chunk {
+        let result = match Chunk::read_header(&mut self.reader) {
+            Ok(Some((length, chunk_type))) if self.verify_crc => {
+                Chunk::read_body(length, chunk_type, &mut self.reader)
+            }
+            Ok(Some((length, chunk_type))) => {
+                Chunk::read_body_unchecked(length, chunk_type, &mut self.reader)
+            }
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(chunk) => self.done = chunk.chunk_type().bytes() == *b"IEND",
+            Err(_) => self.done = true,
+        }
+        Some(result)
+    }
+}
+
+/// A chunk borrowed from a byte slice, for scanning many chunks without
+/// copying their data
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkView<'a> {
+    chunk_type: ChunkType,
+    bytes: &'a [u8],
+}
+
+/// Alias of `ChunkView`
+pub type ChunkRef<'a> = ChunkView<'a>;
+
+impl<'a> ChunkView<'a> {
+    /// Parses the chunk at the start of `bytes` and checks its CRC. Any bytes
+    /// after the chunk are ignored, use `serialized_len` to skip past it.
+    pub fn try_from_slice(bytes: &'a [u8]) -> PngResult<ChunkView<'a>> {
+        let view = ChunkView::try_from_slice_unchecked(bytes)?;
+        view.validate_crc()?;
+        Ok(view)
+    }
+
+    /// Like `try_from_slice`, but does not check the CRC
+    pub fn try_from_slice_unchecked(bytes: &'a [u8]) -> PngResult<ChunkView<'a>> {
+        let field = |offset: usize, len: usize, name: &'static str| {
+            bytes
+                .get(offset..offset + len)
+                .ok_or(PngError::TruncatedChunk {
+                    offset,
+                    field: name,
+                    expected: len,
+                    found: bytes.len().saturating_sub(offset),
+                })
+        };
+
+        let length_field: [u8; 4] = field(0, 4, "length")?.try_into().unwrap();
+        let length = u32::from_be_bytes(length_field);
+        let type_field: [u8; 4] = field(4, 4, "chunk type")?.try_into().unwrap();
+        let chunk_type = ChunkType::try_from(type_field)?;
+        if length > MAX_LENGTH {
+            return Err(PngError::ChunkTooLong { length });
+        }
+        let length = length as usize;
+        field(8, length, "data")?;
+        field(8 + length, 4, "CRC")?;
+
+        Ok(ChunkView {
             chunk_type,
-            data,
-            crc,
+            bytes: &bytes[..12 + length],
         })
     }
+
+    /// Parses the chunk at the start of `bytes`, returning it along with the
+    /// number of bytes it took up, like `Chunk::from_bytes_prefix` does.
+    pub fn parse_prefix(bytes: &'a [u8]) -> PngResult<(ChunkView<'a>, usize)> {
+        let view = ChunkView::try_from_slice(bytes)?;
+        Ok((view, view.serialized_len()))
+    }
+
+    pub fn length(&self) -> u32 {
+        self.data_len() as u32
+    }
+
+    /// Same as `length`, as a `usize`
+    pub fn data_len(&self) -> usize {
+        self.bytes.len() - 12
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
+        &self.chunk_type
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[8..self.bytes.len() - 4]
+    }
+
+    pub fn crc(&self) -> u32 {
+        let crc_field = &self.bytes[self.bytes.len() - 4..];
+        u32::from_be_bytes(crc_field.try_into().unwrap())
+    }
+
+    /// Same as `Chunk::display_with_data`
+    pub fn display_with_data(&self, show_data: bool, hex: bool) -> DisplayWithData<'_> {
+        DisplayWithData {
+            chunk_type: &self.chunk_type,
+            data: self.data(),
+            crc: self.crc(),
+            show_data,
+            hex,
+        }
+    }
+
+    /// The raw bytes of this chunk, from the length field to the CRC
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The number of bytes this chunk takes up in the slice it was parsed from
+    pub fn serialized_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Checks the stored CRC against the one computed from the type and data
+    pub fn validate_crc(&self) -> PngResult<()> {
+        let expected = png_crc(self.chunk_type.as_bytes(), self.data());
+        if expected != self.crc() {
+            return Err(PngError::CrcMismatch {
+                chunk_type: self.chunk_type,
+                expected,
+                actual: self.crc(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Copies the data of this view into an owned `Chunk`
+    pub fn to_owned(self) -> Chunk {
+        Chunk {
+            chunk_type: self.chunk_type,
+            data: self.data().to_vec(),
+            crc: self.crc(),
+        }
+    }
 }
 
+/// The largest data length the PNG spec allows in a chunk
+pub const MAX_LENGTH: u32 = (1 << 31) - 1;
+
+/// How many bytes to reserve upfront when reading chunk data. The length field
+/// is untrusted, so larger chunks grow their buffer as data actually arrives.
+const MAX_PREALLOCATION: usize = 64 * 1024;
+
+/// The number of data bytes shown by the detailed `Display` form of a `Chunk`
+const PREVIEW_LEN: usize = 32;
+
 impl fmt::Display for Chunk {
+    /// Prints a one line summary of the chunk, or a detailed block including a
+    /// preview of the data when the alternate flag (`{:#}`) is set
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Chunk {{",)?;
-        writeln!(f, "  Length: {}", self.length())?;
-        writeln!(f, "  Type: {}", self.chunk_type())?;
-        writeln!(f, "  Data: {} bytes", self.data().len())?;
-        writeln!(f, "  Crc: {}", self.crc())?;
-        writeln!(f, "}}",)?;
-        Ok(())
+        write_summary(f, &self.chunk_type, &self.data, self.crc)
+    }
+}
+
+impl fmt::Display for ChunkView<'_> {
+    /// Prints the same summary as `Chunk` does
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_summary(f, &self.chunk_type, self.data(), self.crc())
+    }
+}
+
+/// The `Display` form of a chunk followed by a line holding its data, made by
+/// `Chunk::display_with_data` and `ChunkView::display_with_data`
+pub struct DisplayWithData<'a> {
+    chunk_type: &'a ChunkType,
+    data: &'a [u8],
+    crc: u32,
+    show_data: bool,
+    hex: bool,
+}
+
+impl fmt::Display for DisplayWithData<'_> {
+    /// Prints the summary, or the detailed block with `{:#}`, then the data as
+    /// lossy UTF-8 when `show_data` is set, or as hex when `hex` is set
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_summary(f, self.chunk_type, self.data, self.crc)?;
+        if self.hex {
+            let pairs: Vec<String> = self.data.iter().map(|b| format!("{:02x}", b)).collect();
+            write!(f, "\n  Data (hex): {}", pairs.join(" "))
+        } else if self.show_data {
+            write!(f, "\n  Data (text): {}", String::from_utf8_lossy(self.data))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Writes the `Display` form of a chunk with the given fields
+fn write_summary(
+    f: &mut fmt::Formatter<'_>,
+    chunk_type: &ChunkType,
+    data: &[u8],
+    crc: u32,
+) -> fmt::Result {
+    if !f.alternate() {
+        return write!(f, "{}  {} bytes  crc=0x{:08x}", chunk_type, data.len(), crc);
+    }
+
+    let preview: String = data
+        .iter()
+        .take(PREVIEW_LEN)
+        .map(|&byte| match byte {
+            b' '..=b'~' => byte as char,
+            _ => '.',
+        })
+        .collect();
+    writeln!(f, "Chunk {{",)?;
+    writeln!(f, "  Length: {}", data.len())?;
+    match chunk_type.describe() {
+        Some(description) => writeln!(f, "  Type: {} ({})", chunk_type, description)?,
+        None => writeln!(f, "  Type: {}", chunk_type)?,
+    }
+    writeln!(f, "  Properties: {}", chunk_type.property_string())?;
+    writeln!(f, "  Data: {} bytes {:?}", data.len(), preview)?;
+    // Same as `Chunk::crc_as_hex`
+    writeln!(f, "  Crc: 0x{:08X}", crc)?;
+    write!(f, "}}",)
+}
+
+/// Writes `bytes` as hex digits, or as rows of 16 space-separated bytes when
+/// the alternate flag is set
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8], upper: bool) -> fmt::Result {
+    for (idx, byte) in bytes.iter().enumerate() {
+        if f.alternate() && idx > 0 {
+            if idx % 16 == 0 {
+                writeln!(f)?;
+            } else {
+                write!(f, " ")?;
+            }
+        }
+        if upper {
+            write!(f, "{:02X}", byte)?;
+        } else {
+            write!(f, "{:02x}", byte)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::UpperHex for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.as_bytes(), true)
+    }
+}
+
+impl fmt::LowerHex for Chunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.as_bytes(), false)
     }
 }
 
 impl Chunk {
-    pub(crate) fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
-        let combined_collection: Vec<u8> = chunk_type
-            .bytes()
-            .iter()
-            .chain(data.iter())
-            .copied()
-            .collect::<Vec<_>>();
+    pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
+        let crc = png_crc(&chunk_type.bytes(), &data);
+        Chunk {
+            chunk_type,
+            data,
+            crc,
+        }
+    }
+
+    /// Creates the empty IEND chunk that ends every PNG file
+    pub fn new_iend() -> Chunk {
+        Chunk::new(ChunkType::IEND, vec![])
+    }
+
+    /// Whether this is an IEND chunk
+    pub fn is_iend(&self) -> bool {
+        self.chunk_type == ChunkType::IEND
+    }
+
+    /// Whether this is an IHDR chunk
+    pub fn is_ihdr(&self) -> bool {
+        self.chunk_type == ChunkType::IHDR
+    }
+
+    /// Parses the chunk at the start of `bytes`, returning it along with the
+    /// number of bytes it took up.
+    pub fn from_bytes_prefix(bytes: &[u8]) -> PngResult<(Chunk, usize)> {
+        let mut reader = bytes;
+        let chunk = Chunk::read_from(&mut reader)?;
+        Ok((chunk, bytes.len() - reader.len()))
+    }
+
+    /// Reads exactly one chunk from `r`, leaving it positioned at the start of
+    /// the next chunk.
+    pub fn read_from<R: Read>(r: &mut R) -> PngResult<Chunk> {
+        match Chunk::read_header(r)? {
+            Some((length, chunk_type)) => Chunk::read_body(length, chunk_type, r),
+            None => Err(PngError::TruncatedChunk {
+                offset: 0,
+                field: "length",
+                expected: 4,
+                found: 0,
+            }),
+        }
+    }
+
+    /// Same as `read_from`, named after `TryFrom<&[u8]>`
+    pub fn try_from_reader<R: Read>(reader: &mut R) -> PngResult<Chunk> {
+        Chunk::read_from(reader)
+    }
+
+    /// Reads the length and type fields of the next chunk from `r`, returning
+    /// `None` if `r` is already at its end.
+    pub(crate) fn read_header<R: Read>(r: &mut R) -> PngResult<Option<(u32, ChunkType)>> {
+        let mut length_field: [u8; 4] = [0, 0, 0, 0];
+        match read_up_to(r, &mut length_field)? {
+            0 => return Ok(None),
+            4 => {}
+            found => {
+                return Err(PngError::TruncatedChunk {
+                    offset: 0,
+                    field: "length",
+                    expected: 4,
+                    found,
+                })
+            }
+        }
+        let mut chunk_type_field: [u8; 4] = [0, 0, 0, 0];
+        read_field(r, &mut chunk_type_field, "chunk type", 4)?;
+        let chunk_type = ChunkType::try_from(chunk_type_field)?;
+
+        let length = u32::from_be_bytes(length_field);
+        if length > MAX_LENGTH {
+            return Err(PngError::ChunkTooLong { length });
+        }
+        Ok(Some((length, chunk_type)))
+    }
+
+    /// Reads the data and CRC of a chunk whose length and type fields have
+    /// already been read from `r`.
+    pub(crate) fn read_body<R: Read>(
+        length: u32,
+        chunk_type: ChunkType,
+        r: &mut R,
+    ) -> PngResult<Chunk> {
+        let chunk = Chunk::read_body_unchecked(length, chunk_type, r)?;
+        chunk.validate_crc()?;
+        Ok(chunk)
+    }
+
+    /// Like `read_body`, but does not check the CRC
+    pub(crate) fn read_body_unchecked<R: Read>(
+        length: u32,
+        chunk_type: ChunkType,
+        r: &mut R,
+    ) -> PngResult<Chunk> {
+        let mut data = Vec::with_capacity((length as usize).min(MAX_PREALLOCATION));
+        r.take(length as u64).read_to_end(&mut data)?;
+        if data.len() != length as usize {
+            return Err(PngError::TruncatedChunk {
+                offset: 8,
+                field: "data",
+                expected: length as usize,
+                found: data.len(),
+            });
+        }
+
+        let mut crc_field: [u8; 4] = [0, 0, 0, 0];
+        read_field(r, &mut crc_field, "CRC", 8 + data.len())?;
+        let crc = u32::from_be_bytes(crc_field);
+
+        Ok(Chunk {
+            chunk_type,
+            data,
+            crc,
+        })
+    }
 
+    /// Creates a chunk with the given CRC as is, without checking that it matches
+    /// the type and data. Useful to represent chunks read from damaged files.
+    pub fn new_unchecked(chunk_type: ChunkType, data: Vec<u8>, crc: u32) -> Chunk {
         Chunk {
             chunk_type,
             data,
-            crc: Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(combined_collection.as_ref()),
+            crc,
+        }
+    }
+
+    /// Splits `data` into as many chunks of type `chunk_type` as needed so that
+    /// no chunk holds more than `max_chunk_size` bytes.
+    pub fn split_into_chunks(
+        chunk_type: ChunkType,
+        data: Vec<u8>,
+        max_chunk_size: usize,
+    ) -> Result<Vec<Chunk>> {
+        if max_chunk_size == 0 {
+            bail!("Chunk: Maximum chunk size must be greater than 0");
+        }
+        if data.is_empty() {
+            return Ok(vec![Chunk::new(chunk_type, data)]);
+        }
+        Ok(data
+            .chunks(max_chunk_size)
+            .map(|segment| Chunk::new(chunk_type, segment.to_vec()))
+            .collect())
+    }
+
+    /// Concatenates the data of `chunks`, which must all share the same chunk type.
+    pub fn merge_chunks(chunks: &[Chunk]) -> Result<Vec<u8>> {
+        let Some(first) = chunks.first() else {
+            return Ok(Vec::new());
+        };
+        if let Some(other) = chunks.iter().find(|chunk| chunk.chunk_type != first.chunk_type) {
+            bail!(
+                "Chunk: Cannot merge chunks of different types ({} and {})",
+                first.chunk_type,
+                other.chunk_type
+            );
         }
+        Ok(chunks.iter().flat_map(|chunk| chunk.data.iter()).copied().collect())
+    }
+
+    /// The length field as written in the file. Use `data_len` for `usize`
+    /// arithmetic.
+    pub fn length(&self) -> u32 {
+        self.data_len() as u32
     }
 
-    pub(crate) fn length(&self) -> u32 {
-        self.data.len() as u32
+    /// The number of data bytes, the same value as `length` as a `usize`
+    pub fn data_len(&self) -> usize {
+        self.data.len()
     }
 
-    pub(crate) fn chunk_type(&self) -> &ChunkType {
+    /// The number of bytes `as_bytes` would return: the length, type and CRC
+    /// fields plus the data, computed without allocating
+    pub fn serialized_len(&self) -> usize {
+        12 + self.data_len()
+    }
+
+    pub fn chunk_type(&self) -> &ChunkType {
         &self.chunk_type
     }
 
-    pub(crate) fn data(&self) -> &[u8] {
+    pub fn data(&self) -> &[u8] {
         &self.data
     }
 
-    pub(crate) fn crc(&self) -> u32 {
+    pub fn crc(&self) -> u32 {
         self.crc
     }
 
-    pub(crate) fn data_as_string(&self) -> Result<String> {
-        String::from_utf8(self.data.clone()).context("Chunk: Data is not valid UTF-8")
+    /// A CRC-32 of the data alone, unlike `crc` which also covers the type
+    /// bytes. Chunks of different types with the same data have the same
+    /// checksum, which makes it useful for deduplication and caching.
+    pub fn data_checksum(&self) -> u32 {
+        data_crc(&self.data)
     }
 
-    pub(crate) fn as_bytes(&self) -> Vec<u8> {
-        let length: u32 = self.data.len() as u32;
-        length
-            .to_be_bytes()
-            .iter()
-            .chain(self.chunk_type.bytes().iter())
-            .chain(self.data.iter())
-            .chain(self.crc.to_be_bytes().iter())
-            .copied()
-            .collect()
+    /// `data_checksum` as big-endian bytes
+    pub fn data_fingerprint(&self) -> [u8; 4] {
+        self.data_checksum().to_be_bytes()
+    }
+
+    /// The CRC as 8 uppercase hex digits, the way PNG documentation quotes them
+    pub fn crc_as_hex(&self) -> String {
+        format!("{:08X}", self.crc)
+    }
+
+    /// The CRC as 8 lowercase hex digits
+    pub fn crc_as_hex_lower(&self) -> String {
+        format!("{:08x}", self.crc)
+    }
+
+    /// Checks that the stored CRC matches the one computed from the type and data
+    pub fn validate_crc(&self) -> PngResult<()> {
+        let expected = png_crc(&self.chunk_type.bytes(), &self.data);
+        if expected != self.crc {
+            return Err(PngError::CrcMismatch {
+                chunk_type: self.chunk_type,
+                expected,
+                actual: self.crc,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the stored CRC matches the one computed from the type and data
+    pub fn verify_crc(&self) -> bool {
+        self.validate_crc().is_ok()
+    }
+
+    /// Replaces the data of this chunk and recomputes its CRC
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.crc = png_crc(&self.chunk_type.bytes(), &data);
+        self.data = data;
+    }
+
+    /// Transforms the data of this chunk with `f` and recomputes its CRC
+    pub fn map_data(&mut self, f: impl FnOnce(Vec<u8>) -> Vec<u8>) {
+        let data = f(std::mem::take(&mut self.data));
+        self.set_data(data);
+    }
+
+    /// Consumes this chunk and returns its type and data. The CRC is dropped,
+    /// `Chunk::new` computes it again from the other two.
+    pub fn into_parts(self) -> (ChunkType, Vec<u8>) {
+        (self.chunk_type, self.data)
+    }
+
+    /// Borrows the type and data of this chunk
+    pub fn as_parts(&self) -> (&ChunkType, &[u8]) {
+        (&self.chunk_type, &self.data)
+    }
+
+    pub fn data_as_string(&self) -> Result<String> {
+        let text = std::str::from_utf8(&self.data).context("Chunk: Data is not valid UTF-8")?;
+        Ok(text.to_owned())
+    }
+
+    /// Parses the data of this chunk as a JSON value
+    #[cfg(feature = "json")]
+    pub fn data_as_json(&self) -> Result<serde_json::Value> {
+        json_from_data(&self.data)
+    }
+
+    /// Like `data_as_string`, but replaces invalid UTF-8 sequences with U+FFFD
+    /// instead of failing. Borrows the data when it is valid UTF-8.
+    pub fn data_as_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+
+    /// Displays this chunk like `Display` does, followed by a line holding its
+    /// data as lossy UTF-8 when `show_data` is set, or as hex when `hex` is.
+    /// `hex` wins when both are set.
+    pub fn display_with_data(&self, show_data: bool, hex: bool) -> DisplayWithData<'_> {
+        DisplayWithData {
+            chunk_type: &self.chunk_type,
+            data: &self.data,
+            crc: self.crc,
+            show_data,
+            hex,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.write_to(&mut bytes)
+            .expect("Chunk: Writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Writes the length, type, data and CRC of this chunk to `w`, returning
+    /// the number of bytes written.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(&self.length().to_be_bytes())?;
+        w.write_all(&self.chunk_type.bytes())?;
+        w.write_all(&self.data)?;
+        w.write_all(&self.crc.to_be_bytes())?;
+        Ok(self.serialized_len())
     }
 }
 
@@ -131,6 +738,28 @@ mod tests {
     use crate::chunk_type::ChunkType;
     use std::str::FromStr;
 
+    #[test]
+    fn test_png_crc() {
+        let data = b"This is where your secret message will be!";
+        assert_eq!(png_crc(b"RuSt", data), 2882656334);
+        assert_eq!(png_crc(b"IEND", &[]), 0xAE426082);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_chunk_data_as_json() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let chunk = Chunk::new(chunk_type, br#"{"a": [1, 2], "b": null}"#.to_vec());
+        assert_eq!(chunk.data_as_json().unwrap(), serde_json::json!({"a": [1, 2], "b": null}));
+
+        let chunk = Chunk::new(chunk_type, b"{not json".to_vec());
+        let error = chunk.data_as_json().unwrap_err().to_string();
+        assert!(error.starts_with("Chunk: Data is text but not valid JSON"), "{}", error);
+
+        let chunk = Chunk::new(chunk_type, vec![0xFF]);
+        assert!(chunk.data_as_json().unwrap_err().to_string().contains("UTF-8"));
+    }
+
     fn testing_chunk() -> Chunk {
         let data_length: u32 = 42;
         let chunk_type = "RuSt".as_bytes();
@@ -160,10 +789,56 @@ mod tests {
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_new_iend() {
+        let iend = Chunk::new_iend();
+        assert_eq!(iend.crc(), 0xAE426082);
+        assert_eq!(iend.length(), 0);
+        assert!(iend.is_iend());
+        assert!(!iend.is_ihdr());
+        assert!(!testing_chunk().is_iend());
+
+        let ihdr = Chunk::new(ChunkType::IHDR, vec![0; 13]);
+        assert!(ihdr.is_ihdr());
+        assert!(!ihdr.is_iend());
+    }
+
     #[test]
     fn test_chunk_length() {
         let chunk = testing_chunk();
         assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.data_len(), chunk.length() as usize);
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.serialized_len(), 54);
+        assert_eq!(chunk.serialized_len(), chunk.as_bytes().len());
+        assert_eq!(chunk.as_bytes().capacity(), chunk.as_bytes().len());
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let chunk = testing_chunk();
+        assert_eq!(Vec::<u8>::from(&chunk), chunk.as_bytes());
+        let expected = chunk.as_bytes();
+        let bytes: Vec<u8> = chunk.into();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_into_parts_round_trip() {
+        let chunk = testing_chunk();
+        let (chunk_type, data) = chunk.as_parts();
+        assert_eq!(chunk_type, "RuSt");
+        assert_eq!(data, b"This is where your secret message will be!");
+
+        let bytes = chunk.as_bytes();
+        let (chunk_type, data) = chunk.into_parts();
+        let rebuilt = Chunk::new(chunk_type, data);
+        assert_eq!(rebuilt.crc(), 2882656334);
+        assert_eq!(rebuilt.as_bytes(), bytes);
     }
 
     #[test]
@@ -180,12 +855,115 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_view_matches_chunk() {
+        let chunk = testing_chunk();
+        let bytes = chunk.as_bytes();
+
+        let view = ChunkView::try_from_slice(&bytes).unwrap();
+
+        assert_eq!(view.length(), chunk.length());
+        assert_eq!(view.data_len(), chunk.data_len());
+        assert_eq!(view.chunk_type(), chunk.chunk_type());
+        assert_eq!(view.data(), chunk.data());
+        assert_eq!(view.crc(), chunk.crc());
+        assert_eq!(view.as_bytes(), bytes.as_slice());
+        assert_eq!(view.serialized_len(), chunk.serialized_len());
+
+        assert_eq!(view.to_string(), chunk.to_string());
+        assert_eq!(format!("{:#}", view), format!("{:#}", chunk));
+
+        let owned = view.to_owned();
+        assert_eq!(owned.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_chunk_ref_does_not_copy_data() {
+        let chunk = Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![7; 4 * 1024 * 1024]);
+        let mut bytes = chunk.as_bytes();
+        bytes.extend_from_slice(b"next");
+
+        let (view, consumed): (ChunkRef, usize) = ChunkRef::parse_prefix(&bytes).unwrap();
+
+        assert_eq!(consumed, chunk.serialized_len());
+        assert_eq!(view.data().as_ptr(), bytes[8..].as_ptr());
+        assert_eq!(view.data().len(), 4 * 1024 * 1024);
+        assert_eq!(view.to_owned().data(), chunk.data());
+    }
+
+    #[test]
+    fn test_chunk_view_scan() {
+        let chunks = [
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"first".to_vec()),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ];
+        let bytes: Vec<u8> = chunks.iter().flat_map(Chunk::as_bytes).collect();
+
+        let first = ChunkView::try_from_slice(&bytes).unwrap();
+        let second = ChunkView::try_from_slice(&bytes[first.serialized_len()..]).unwrap();
+
+        assert_eq!(first.data(), b"first");
+        assert_eq!(second.chunk_type(), "IEND");
+        assert_eq!(first.serialized_len() + second.serialized_len(), bytes.len());
+    }
+
+    #[test]
+    fn test_chunk_view_errors() {
+        let mut bytes = testing_chunk().as_bytes();
+
+        assert!(matches!(
+            ChunkView::try_from_slice(&bytes[..20]),
+            Err(PngError::TruncatedChunk {
+                offset: 8,
+                field: "data",
+                expected: 42,
+                found: 12
+            })
+        ));
+        assert!(matches!(
+            ChunkView::try_from_slice(&bytes[..52]),
+            Err(PngError::TruncatedChunk { field: "CRC", found: 2, .. })
+        ));
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            ChunkView::try_from_slice(&bytes),
+            Err(PngError::CrcMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chunk_string_lossy() {
+        let chunk = testing_chunk();
+        assert!(matches!(chunk.data_as_string_lossy(), Cow::Borrowed(_)));
+
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, b"ok \xFF then \xE2\x82 and \xC3\xA9".to_vec());
+        assert!(chunk.data_as_string().is_err());
+        assert_eq!(chunk.data_as_string_lossy(), "ok \u{FFFD} then \u{FFFD} and \u{e9}");
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_data_checksum() {
+        let chunk = testing_chunk();
+        assert_ne!(chunk.data_checksum(), chunk.crc());
+        let renamed = Chunk::new(ChunkType::from_str("ruSt").unwrap(), chunk.data().to_vec());
+        assert_eq!(renamed.data_checksum(), chunk.data_checksum());
+        assert_ne!(renamed.crc(), chunk.crc());
+
+        // The standard check value of CRC-32/ISO-HDLC
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"123456789".to_vec());
+        assert_eq!(chunk.data_checksum(), 0xCBF43926);
+        assert_eq!(chunk.data_fingerprint(), [0xCB, 0xF4, 0x39, 0x26]);
+    }
+
     #[test]
     fn test_valid_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -234,6 +1012,21 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_invalid_chunk_error_message() {
+        let mut bytes = testing_chunk().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let error = Chunk::try_from(bytes.as_ref()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "CRC mismatch in RuSt chunk: stored 0xabd1d84f, computed 0xabd1d84e"
+        );
+        assert!(matches!(error, PngError::CrcMismatch { .. }));
+    }
+
     #[test]
     fn test_wrong_length_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -255,6 +1048,407 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_set_data() {
+        let mut chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![1, 2, 3]);
+        chunk.set_data("This is where your secret message will be!".as_bytes().to_vec());
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2882656334);
+
+        let reparsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(reparsed.crc(), 2882656334);
+    }
+
+    /// Hands out at most 7 bytes per read to exercise partial reads
+    struct ThrottledReader<R>(R);
+
+    impl<R: Read> Read for ThrottledReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(7);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    fn chunk_stream() -> Vec<u8> {
+        [
+            testing_chunk(),
+            Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![42; 100]),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        ]
+        .iter()
+        .flat_map(|chunk| chunk.as_bytes())
+        .collect()
+    }
+
+    #[test]
+    fn test_read_from() {
+        let bytes = chunk_stream();
+        let mut reader = ThrottledReader(io::Cursor::new(&bytes));
+
+        let first = Chunk::read_from(&mut reader).unwrap();
+        let second = Chunk::read_from(&mut reader).unwrap();
+
+        assert_eq!(first.crc(), 2882656334);
+        assert_eq!(second.data(), &[42; 100][..]);
+        assert_eq!(reader.0.position(), 54 + 112);
+    }
+
+    #[test]
+    fn test_read_from_truncated() {
+        let bytes = chunk_stream();
+        let mut reader = ThrottledReader(&bytes[..50]);
+        assert!(Chunk::read_from(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_try_from_reader() {
+        let bytes = testing_chunk().as_bytes();
+        let mut reader = io::Cursor::new(bytes.clone());
+        let chunk = Chunk::try_from_reader(&mut reader).unwrap();
+        assert_eq!(chunk.as_bytes(), bytes);
+
+        let mut truncated = io::Cursor::new(bytes[..bytes.len() - 2].to_vec());
+        let error = Chunk::try_from_reader(&mut truncated).unwrap_err();
+        assert!(matches!(error, PngError::TruncatedChunk { field: "CRC", found: 2, .. }));
+    }
+
+    #[test]
+    fn test_chunk_reader() {
+        let mut bytes = chunk_stream();
+        bytes.extend_from_slice(b"trailing bytes after IEND");
+
+        let chunks: Vec<Chunk> = ChunkReader::new(ThrottledReader(io::Cursor::new(&bytes)))
+            .collect::<PngResult<_>>()
+            .unwrap();
+
+        let types: Vec<String> = chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["RuSt", "IDAT", "IEND"]);
+    }
+
+    #[test]
+    fn test_chunk_reader_until_eof() {
+        let bytes = testing_chunk().as_bytes();
+        let mut reader = ChunkReader::new(ThrottledReader(bytes.as_slice()));
+
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_chunk_reader_stops_at_error() {
+        let bytes = chunk_stream();
+        let mut reader = ChunkReader::new(ThrottledReader(&bytes[..60]));
+
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_exact_length_chunk_from_bytes() {
+        let bytes = testing_chunk().as_bytes();
+        assert!(Chunk::try_from(bytes.as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_trailing_bytes_chunk_from_bytes() {
+        let mut bytes = testing_chunk().as_bytes();
+        bytes.extend_from_slice(b"garbage");
+
+        assert!(matches!(
+            Chunk::try_from(bytes.as_ref()),
+            Err(PngError::TrailingBytes { count: 7 })
+        ));
+
+        let (chunk, consumed) = Chunk::from_bytes_prefix(&bytes).unwrap();
+        assert_eq!(consumed, 54);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_consecutive_chunks_from_bytes_prefix() {
+        let bytes = chunk_stream();
+
+        let (first, consumed) = Chunk::from_bytes_prefix(&bytes).unwrap();
+        let (second, _) = Chunk::from_bytes_prefix(&bytes[consumed..]).unwrap();
+
+        assert_eq!(first.chunk_type().to_string(), "RuSt");
+        assert_eq!(second.chunk_type().to_string(), "IDAT");
+    }
+
+    #[test]
+    fn test_write_to() {
+        let chunk = testing_chunk();
+        let mut bytes = Vec::new();
+
+        let written = chunk.write_to(&mut bytes).unwrap();
+
+        assert_eq!(written, 54);
+        assert_eq!(bytes, chunk.as_bytes());
+    }
+
+    #[test]
+    fn test_map_data() {
+        let mut chunk = Chunk::new(
+            ChunkType::from_str("RuSt").unwrap(),
+            b"This is where your secret".to_vec(),
+        );
+        chunk.map_data(|mut data| {
+            data.extend_from_slice(b" message will be!");
+            data
+        });
+
+        assert_eq!(chunk.crc(), 2882656334);
+        let reparsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(
+            reparsed.data_as_string().unwrap(),
+            "This is where your secret message will be!"
+        );
+    }
+
+    #[test]
+    fn test_verify_crc() {
+        let chunk = testing_chunk();
+        assert!(chunk.verify_crc());
+
+        let chunk = Chunk::new_unchecked(*chunk.chunk_type(), chunk.data().to_vec(), 0);
+        assert!(!chunk.verify_crc());
+    }
+
+    #[test]
+    fn test_validate_crc() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![1, 2, 3]);
+        assert!(chunk.validate_crc().is_ok());
+        assert!(testing_chunk().validate_crc().is_ok());
+    }
+
+    #[test]
+    fn test_validate_crc_mismatch() {
+        let data = "This is where your secret message will be!".as_bytes().to_vec();
+        let chunk = Chunk::new_unchecked(ChunkType::from_str("RuSt").unwrap(), data, 1234);
+
+        match chunk.validate_crc() {
+            Err(PngError::CrcMismatch {
+                chunk_type,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(chunk_type, "RuSt");
+                assert_eq!(expected, 2882656334);
+                assert_eq!(actual, 1234);
+            }
+            other => panic!("expected a CRC mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_split_and_merge_chunks() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+
+        let chunks = Chunk::split_into_chunks(chunk_type, data.clone(), 100).unwrap();
+
+        assert_eq!(chunks.len(), 10);
+        assert!(chunks.iter().all(|chunk| chunk.length() == 100));
+        assert_eq!(Chunk::merge_chunks(&chunks).unwrap(), data);
+    }
+
+    #[test]
+    fn test_split_uneven_chunks() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = vec![7; 250];
+
+        let chunks = Chunk::split_into_chunks(chunk_type, data.clone(), 100).unwrap();
+
+        let lengths: Vec<u32> = chunks.iter().map(|chunk| chunk.length()).collect();
+        assert_eq!(lengths, [100, 100, 50]);
+        assert_eq!(Chunk::merge_chunks(&chunks).unwrap(), data);
+    }
+
+    #[test]
+    fn test_split_zero_max_size() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        assert!(Chunk::split_into_chunks(chunk_type, vec![1, 2, 3], 0).is_err());
+    }
+
+    #[test]
+    fn test_merge_mixed_types() {
+        let chunks = vec![
+            Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![1]),
+            Chunk::new(ChunkType::from_str("RuSx").unwrap(), vec![2]),
+        ];
+        assert!(Chunk::merge_chunks(&chunks).is_err());
+    }
+
+    #[test]
+    fn test_chunk_hex() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"hi".to_vec());
+        let crc = format!("{:08X}", chunk.crc());
+
+        assert_eq!(format!("{:X}", chunk), format!("0000000252755374{}{}", "6869", crc));
+        assert_eq!(
+            format!("{:x}", chunk),
+            format!("0000000252755374{}{}", "6869", crc.to_lowercase())
+        );
+    }
+
+    #[test]
+    fn test_chunk_hex_alternate() {
+        let chunk = testing_chunk();
+        let dump = format!("{:#X}", chunk);
+        let rows: Vec<&str> = dump.lines().collect();
+
+        // 54 bytes: 12 bytes of framing plus 42 bytes of data
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], "00 00 00 2A 52 75 53 74 54 68 69 73 20 69 73 20");
+        assert_eq!(rows[3], "65 21 AB D1 D8 4E");
+        assert_eq!(format!("{:#x}", chunk).lines().nth(3), Some("65 21 ab d1 d8 4e"));
+    }
+
+    #[test]
+    fn test_crc_as_hex() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.crc(), 2882656334);
+        assert_eq!(chunk.crc_as_hex(), "ABD1D84E");
+        assert_eq!(chunk.crc_as_hex_lower(), "abd1d84e");
+
+        // Leading zeros are kept
+        let chunk = Chunk::new_unchecked(ChunkType::from_str("RuSt").unwrap(), vec![], 0xBEEF);
+        assert_eq!(chunk.crc_as_hex(), "0000BEEF");
+        assert_eq!(chunk.crc_as_hex_lower(), "0000beef");
+    }
+
+    #[test]
+    fn test_chunk_display() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.to_string(), "RuSt  42 bytes  crc=0xabd1d84e");
+    }
+
+    #[test]
+    fn test_chunk_display_alternate() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"hi\x00\n there".to_vec());
+        let expected = format!(
+            "Chunk {{\n  Length: 10\n  Type: RuSt\n  \
+             Properties: critical|private|reserved-valid|safe-to-copy\n  \
+             Data: 10 bytes \"hi.. there\"\n  Crc: 0x{}\n}}",
+            chunk.crc_as_hex()
+        );
+        assert_eq!(format!("{:#}", chunk), expected);
+
+        let preview_line = format!("{:#}", testing_chunk()).lines().nth(4).unwrap().to_string();
+        assert_eq!(preview_line, "  Data: 42 bytes \"This is where your secret messag\"");
+
+        let type_line = format!("{:#}", Chunk::new_iend()).lines().nth(2).unwrap().to_string();
+        assert_eq!(type_line, "  Type: IEND (Image trailer - marks the end of the PNG)");
+    }
+
+    #[test]
+    fn test_display_with_data() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"hi\xff".to_vec());
+        let summary = chunk.to_string();
+
+        assert_eq!(chunk.display_with_data(false, false).to_string(), summary);
+        assert_eq!(
+            chunk.display_with_data(true, false).to_string(),
+            format!("{}\n  Data (text): hi\u{FFFD}", summary)
+        );
+        let hex = format!("{}\n  Data (hex): 68 69 ff", summary);
+        assert_eq!(chunk.display_with_data(false, true).to_string(), hex);
+        assert_eq!(chunk.display_with_data(true, true).to_string(), hex);
+
+        let bytes = chunk.as_bytes();
+        let view = ChunkView::try_from_slice(&bytes).unwrap();
+        let expected = chunk.display_with_data(true, false).to_string();
+        assert_eq!(view.display_with_data(true, false).to_string(), expected);
+    }
+
+    fn parse_truncated(len: usize) -> PngResult<Chunk> {
+        let bytes = testing_chunk().as_bytes();
+        let truncated = bytes[..len].to_vec();
+        std::panic::catch_unwind(|| Chunk::try_from(truncated.as_ref()))
+            .expect("parsing a truncated chunk must not panic")
+    }
+
+    #[test]
+    fn test_truncated_mid_type() {
+        let error = parse_truncated(6).unwrap_err();
+        assert!(matches!(
+            error,
+            PngError::TruncatedChunk {
+                offset: 4,
+                field: "chunk type",
+                expected: 4,
+                found: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn test_truncated_mid_data() {
+        let error = parse_truncated(20).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Chunk: Not enough bytes in the data field at offset 8 (expected 42, found 12)"
+        );
+    }
+
+    #[test]
+    fn test_truncated_mid_crc() {
+        let error = parse_truncated(52).unwrap_err();
+        assert!(matches!(
+            error,
+            PngError::TruncatedChunk {
+                offset: 50,
+                field: "CRC",
+                expected: 4,
+                found: 2
+            }
+        ));
+    }
+
+    fn chunk_claiming_length(length: u32) -> Vec<u8> {
+        length
+            .to_be_bytes()
+            .iter()
+            .chain(b"RuSt")
+            .chain(&[1, 2, 3, 4, 5, 6, 7, 8])
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_huge_length_with_little_data() {
+        let bytes = chunk_claiming_length(MAX_LENGTH);
+
+        let error = Chunk::try_from(bytes.as_ref()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Chunk: Not enough bytes in the data field at offset 8 (expected 2147483647, found 8)"
+        );
+    }
+
+    #[test]
+    fn test_length_over_spec_limit() {
+        let bytes = chunk_claiming_length(u32::MAX);
+
+        let error = Chunk::try_from(bytes.as_ref()).unwrap_err();
+
+        assert!(matches!(error, PngError::ChunkTooLong { length: u32::MAX }));
+        assert!(error.to_string().contains("4294967295"));
+    }
+
+    #[test]
+    fn test_truncated_at_every_length() {
+        for len in 0..54 {
+            assert!(parse_truncated(len).is_err());
+        }
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
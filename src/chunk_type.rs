@@ -1,75 +1,236 @@
-use anyhow::{bail, Context};
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
 use std::fmt;
-use std::str::{from_utf8, FromStr};
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
 
-use crate::{Error, Result};
+use bitflags::bitflags;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::error::{PngError, PngResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChunkType {
     bytes: [u8; 4],
 }
 
-fn is_bytes_ascii_alphabetic(bytes: &[u8; 4]) -> bool {
-    let mut res = true;
-    for byte in bytes {
-        if !byte.is_ascii_alphabetic() {
-            res = false;
-        }
+bitflags! {
+    /// The four property bits of a chunk type, each set when the property
+    /// holds
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ChunkTypeFlags: u8 {
+        const CRITICAL = 1 << 0;
+        const PUBLIC = 1 << 1;
+        const RESERVED_VALID = 1 << 2;
+        const SAFE_TO_COPY = 1 << 3;
     }
-    res
+}
+
+/// Returns the index of the first byte that is not an ASCII letter, if any
+fn first_non_alphabetic(bytes: &[u8; 4]) -> Option<usize> {
+    bytes.iter().position(|byte| !byte.is_ascii_alphabetic())
+}
+
+/// Describes the chunk types defined by the PNG spec and its registered
+/// extensions, including APNG
+fn standard_description(bytes: &[u8; 4]) -> Option<&'static str> {
+    let description = match bytes {
+        b"IHDR" => "Image header - defines dimensions, bit depth, and color type",
+        b"PLTE" => "Palette - the colors of an indexed-color image",
+        b"IDAT" => "Image data - compressed pixel data",
+        b"IEND" => "Image trailer - marks the end of the PNG",
+        b"tRNS" => "Transparency - alpha for palette entries or a single color",
+        b"cHRM" => "Primary chromaticities and white point",
+        b"gAMA" => "Image gamma",
+        b"iCCP" => "Embedded ICC color profile",
+        b"sBIT" => "Significant bits - the original sample depth",
+        b"sRGB" => "Standard RGB color space - the rendering intent",
+        b"cICP" => "Coding-independent code points - color space for video and HDR",
+        b"mDCv" => "Mastering display color volume",
+        b"cLLI" => "Content light level information",
+        b"tEXt" => "Textual data - a Latin-1 keyword and text",
+        b"zTXt" => "Compressed textual data",
+        b"iTXt" => "International textual data - UTF-8, optionally compressed",
+        b"bKGD" => "Background color",
+        b"hIST" => "Palette histogram - how often each palette entry is used",
+        b"pHYs" => "Physical pixel dimensions - pixel size or aspect ratio",
+        b"sPLT" => "Suggested palette",
+        b"eXIf" => "Exchangeable image file (Exif) metadata",
+        b"tIME" => "Last modification time",
+        b"acTL" => "Animation control - number of frames and plays (APNG)",
+        b"fcTL" => "Frame control - size, position and timing of a frame (APNG)",
+        b"fdAT" => "Frame data - compressed pixel data of a frame (APNG)",
+        b"oFFs" => "Image offset",
+        b"pCAL" => "Calibration of pixel values",
+        b"sCAL" => "Physical scale of the image subject",
+        b"gIFg" => "GIF graphic control extension",
+        b"gIFx" => "GIF application extension",
+        b"sTER" => "Stereo image indicator",
+        b"dSIG" => "Digital signature",
+        _ => return None,
+    };
+    Some(description)
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
-    type Error = Error;
+    type Error = PngError;
 
-    fn try_from(value: [u8; 4]) -> Result<Self> {
-        if !is_bytes_ascii_alphabetic(&value) {
-            bail!("ChunkType: bytes must be ASCII alphabetic");
+    fn try_from(value: [u8; 4]) -> PngResult<Self> {
+        if let Some(index) = first_non_alphabetic(&value) {
+            return Err(PngError::InvalidChunkType {
+                bytes: value,
+                index,
+            });
         }
         Ok(ChunkType { bytes: value })
     }
 }
 
 impl FromStr for ChunkType {
-    type Err = Error;
+    type Err = PngError;
 
-    fn from_str(s: &str) -> Result<Self> {
+    fn from_str(s: &str) -> PngResult<Self> {
         let s_as_bytes: [u8; 4] = s
             .as_bytes()
             .try_into()
-            .context("ChunkType: chunk type string must be 4 bytes long")?;
+            .map_err(|_| PngError::InvalidChunkTypeLength {
+                input: s.to_string(),
+                length: s.len(),
+            })?;
         Self::try_from(s_as_bytes)
     }
 }
 
+impl PartialEq<str> for ChunkType {
+    fn eq(&self, other: &str) -> bool {
+        other.as_bytes() == self.bytes
+    }
+}
+
+impl PartialEq<&str> for ChunkType {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<&String> for ChunkType {
+    fn eq(&self, other: &&String) -> bool {
+        *self == ***other
+    }
+}
+
+impl PartialEq<[u8; 4]> for ChunkType {
+    fn eq(&self, other: &[u8; 4]) -> bool {
+        self.bytes == *other
+    }
+}
+
 impl fmt::Display for ChunkType {
+    /// Prints the four characters of the chunk type, or a breakdown of its
+    /// property bits when the alternate flag (`{:#}`) is set. Bytes that are
+    /// not printable ASCII, only possible with `from_raw_bytes_unchecked`,
+    /// are escaped.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return write!(f, "{}", self.bytes.escape_ascii());
+        }
+
         writeln!(f, "ChunkType {{",)?;
-        writeln!(
-            f,
-            "  Data: {:?}",
-            match from_utf8(&self.bytes) {
-                Ok(str) => str,
-                Err(_) => return Err(fmt::Error),
-            }
-        )?;
+        writeln!(f, "  Data: {:?}", self.bytes.escape_ascii().to_string())?;
         writeln!(f, "  Valid: {}", self.is_valid())?;
         writeln!(f, "  Critical: {}", self.is_critical())?;
         writeln!(f, "  Public: {}", self.is_public())?;
         writeln!(f, "  Reserved valid: {}", self.is_reserved_bit_valid())?;
         writeln!(f, "  Safe to copy: {}", self.is_safe_to_copy())?;
-        writeln!(f, "}}",)?;
-        Ok(())
+        write!(f, "}}",)
+    }
+}
+
+impl fmt::UpperHex for ChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}", u32::from_be_bytes(self.bytes))
+    }
+}
+
+impl fmt::LowerHex for ChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08x}", u32::from_be_bytes(self.bytes))
+    }
+}
+
+impl fmt::Binary for ChunkType {
+    /// Prints the bit pattern of each byte, separated by spaces. Bit 5 of each
+    /// byte is the property bit, set for lowercase.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.bytes;
+        write!(f, "{:08b} {:08b} {:08b} {:08b}", a, b, c, d)
     }
 }
 
 impl ChunkType {
+    /// The image header chunk, which must come first
+    pub const IHDR: ChunkType = ChunkType { bytes: *b"IHDR" };
+
+    /// The image data chunk, possibly split over several consecutive chunks
+    pub const IDAT: ChunkType = ChunkType { bytes: *b"IDAT" };
+
+    /// The image trailer chunk, which must come last
+    pub const IEND: ChunkType = ChunkType { bytes: *b"IEND" };
+
+    /// Makes up an ancillary, private, safe-to-copy chunk type such as `qzMx`.
+    /// The letters are random but not suitable for anything security related.
+    pub fn random_private() -> ChunkType {
+        // Every `RandomState` is keyed differently, which is all the randomness
+        // needed here, and unlike the clock it is available on every target
+        ChunkType::private_from_seed(RandomState::new().build_hasher().finish())
+    }
+
+    /// Same as `random_private`, but the letters come from `seed`, so the same
+    /// seed always gives the same chunk type
+    pub fn private_from_seed(seed: u64) -> ChunkType {
+        let mut bits = seed;
+        let mut letter = |first: u8| {
+            let letter = first + (bits % 26) as u8;
+            bits /= 26;
+            letter
+        };
+        ChunkType {
+            bytes: [letter(b'a'), letter(b'a'), letter(b'A'), letter(b'a')],
+        }
+    }
+
+    /// Creates a chunk type from any four bytes, without the checks done by
+    /// `TryFrom<[u8; 4]>`. Meant for inspecting corrupt files, use
+    /// `is_valid_per_spec` to tell whether the result is a real chunk type.
+    pub fn from_raw_bytes_unchecked(bytes: [u8; 4]) -> ChunkType {
+        ChunkType { bytes }
+    }
+
     pub fn bytes(&self) -> [u8; 4] {
         self.bytes
     }
 
+    /// Borrows the four bytes of this chunk type, for comparisons that don't
+    /// need a copy
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.bytes
+    }
+
+    /// The four characters of this chunk type, without allocating
+    pub fn name(&self) -> &str {
+        // The checked constructors only accept ASCII letters, which are always
+        // valid UTF-8, so this can only fail for types made with
+        // `from_raw_bytes_unchecked`. Those get a placeholder instead of a panic.
+        std::str::from_utf8(&self.bytes).unwrap_or("\u{FFFD}")
+    }
+
     pub fn is_valid(&self) -> bool {
-        is_bytes_ascii_alphabetic(&self.bytes) && self.is_reserved_bit_valid()
+        first_non_alphabetic(&self.bytes).is_none() && self.is_reserved_bit_valid()
+    }
+
+    /// Same as `is_valid`: whether the bytes are ASCII letters with the
+    /// reserved bit unset, as the PNG spec requires
+    pub fn is_valid_per_spec(&self) -> bool {
+        self.is_valid()
     }
 
     pub fn is_critical(&self) -> bool {
@@ -87,6 +248,105 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         self.bytes[3].is_ascii_lowercase()
     }
+
+    /// The four property bits as `(is_critical, is_public,
+    /// is_reserved_bit_valid, is_safe_to_copy)`
+    pub fn property_bits(&self) -> (bool, bool, bool, bool) {
+        let flags = self.property_flags();
+        (
+            flags.contains(ChunkTypeFlags::CRITICAL),
+            flags.contains(ChunkTypeFlags::PUBLIC),
+            flags.contains(ChunkTypeFlags::RESERVED_VALID),
+            flags.contains(ChunkTypeFlags::SAFE_TO_COPY),
+        )
+    }
+
+    /// The four property bits as flags
+    pub fn property_flags(&self) -> ChunkTypeFlags {
+        let mut flags = ChunkTypeFlags::empty();
+        flags.set(ChunkTypeFlags::CRITICAL, self.is_critical());
+        flags.set(ChunkTypeFlags::PUBLIC, self.is_public());
+        flags.set(ChunkTypeFlags::RESERVED_VALID, self.is_reserved_bit_valid());
+        flags.set(ChunkTypeFlags::SAFE_TO_COPY, self.is_safe_to_copy());
+        flags
+    }
+
+    /// The four properties by name, joined by `|`, such as
+    /// "ancillary|public|reserved-valid|safe-to-copy" for `tEXt`. Each bit is
+    /// named whether it is set or not, so the string always has four parts.
+    pub fn property_string(&self) -> String {
+        let flags = self.property_flags();
+        let name = |flag, yes: &'static str, no: &'static str| {
+            if flags.contains(flag) {
+                yes
+            } else {
+                no
+            }
+        };
+        [
+            name(ChunkTypeFlags::CRITICAL, "critical", "ancillary"),
+            name(ChunkTypeFlags::PUBLIC, "public", "private"),
+            name(ChunkTypeFlags::RESERVED_VALID, "reserved-valid", "reserved-invalid"),
+            name(ChunkTypeFlags::SAFE_TO_COPY, "safe-to-copy", "unsafe-to-copy"),
+        ]
+        .join("|")
+    }
+
+    /// Whether this is one of the chunk types defined by the PNG spec or its
+    /// registered extensions
+    pub fn is_known_standard(&self) -> bool {
+        standard_description(&self.bytes).is_some()
+    }
+
+    /// A short description of a standard chunk type, such as "Image gamma"
+    /// for `gAMA`, or `None` for types the spec doesn't define
+    pub fn describe(&self) -> Option<Cow<'static, str>> {
+        standard_description(&self.bytes).map(Cow::Borrowed)
+    }
+
+    /// Returns a copy of this chunk type with the safe-to-copy bit set
+    pub fn as_safe_to_copy(&self) -> ChunkType {
+        self.with_byte(3, self.bytes[3].to_ascii_lowercase())
+    }
+
+    /// Returns a copy of this chunk type with the safe-to-copy bit cleared
+    pub fn as_unsafe_to_copy(&self) -> ChunkType {
+        self.with_byte(3, self.bytes[3].to_ascii_uppercase())
+    }
+
+    /// Returns a copy of this chunk type with the ancillary bit cleared
+    pub fn as_critical(&self) -> ChunkType {
+        self.with_byte(0, self.bytes[0].to_ascii_uppercase())
+    }
+
+    /// Returns a copy of this chunk type with the ancillary bit set
+    pub fn as_ancillary(&self) -> ChunkType {
+        self.with_byte(0, self.bytes[0].to_ascii_lowercase())
+    }
+
+    /// Changing the case of an ASCII letter keeps it alphabetic, so the result
+    /// is always a valid `ChunkType`
+    fn with_byte(&self, idx: usize, byte: u8) -> ChunkType {
+        let mut bytes = self.bytes;
+        bytes[idx] = byte;
+        ChunkType { bytes }
+    }
+
+    /// Where chunks of this type belong in the canonical chunk ordering of the
+    /// PNG spec, lower values coming first. Custom chunk types are placed after
+    /// all spec-defined chunks but before `IEND`.
+    pub fn canonical_priority(&self) -> u8 {
+        match &self.bytes {
+            b"IHDR" => 0,
+            b"cHRM" | b"gAMA" | b"iCCP" | b"sBIT" | b"sRGB" => 1,
+            b"PLTE" => 2,
+            b"bKGD" | b"hIST" | b"tRNS" | b"pHYs" | b"sPLT" => 3,
+            b"IDAT" => 4,
+            b"tIME" | b"tEXt" | b"zTXt" | b"iTXt" => 5,
+            b"IEND" => 7,
+            _ => 6,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -110,6 +370,59 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    pub fn test_chunk_type_invalid_bytes() {
+        let error = ChunkType::try_from([82, 117, 49, 116]).unwrap_err();
+        assert!(matches!(
+            error,
+            PngError::InvalidChunkType {
+                bytes: [82, 117, 49, 116],
+                index: 2
+            }
+        ));
+    }
+
+    #[test]
+    pub fn test_chunk_type_invalid_length() {
+        let error = ChunkType::from_str("RuStY").unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunkTypeLength { length: 5, .. }));
+    }
+
+    #[test]
+    pub fn test_chunk_type_random_private() {
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            let chunk = ChunkType::random_private();
+            assert!(chunk.is_valid(), "{}", chunk);
+            assert!(!chunk.is_critical(), "{}", chunk);
+            assert!(!chunk.is_public(), "{}", chunk);
+            assert!(chunk.is_safe_to_copy(), "{}", chunk);
+            assert_eq!(ChunkType::try_from(chunk.bytes()).unwrap(), chunk);
+            seen.insert(chunk);
+        }
+        assert!(seen.len() > 900, "only {} distinct types", seen.len());
+    }
+
+    #[test]
+    pub fn test_chunk_type_private_from_seed() {
+        let chunk = ChunkType::private_from_seed(42);
+        assert_eq!(ChunkType::private_from_seed(42), chunk);
+        assert_ne!(ChunkType::private_from_seed(43), chunk);
+        assert!(chunk.is_valid() && !chunk.is_critical() && !chunk.is_public());
+        assert!(chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_raw_bytes() {
+        let chunk = ChunkType::from_raw_bytes_unchecked([b'R', 0, b'S', 0xFF]);
+
+        assert!(!chunk.is_valid_per_spec());
+        assert_eq!(chunk.bytes(), [b'R', 0, b'S', 0xFF]);
+        assert_eq!(chunk.to_string(), "R\\x00S\\xff");
+        assert!(format!("{:#}", chunk).contains("Valid: false"));
+        assert!(ChunkType::from_raw_bytes_unchecked(*b"RuSt").is_valid_per_spec());
+    }
+
     #[test]
     pub fn test_chunk_type_is_critical() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -169,8 +482,40 @@ mod tests {
         let chunk = ChunkType::from_str("Rust").unwrap();
         assert!(!chunk.is_valid());
 
-        let chunk = ChunkType::from_str("Ru1t");
-        assert!(chunk.is_err());
+        let error = ChunkType::from_str("Ru1t").unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunkType { index: 2, .. }));
+        assert_eq!(
+            error.to_string(),
+            "ChunkType: Invalid chunk type \"Ru1t\", byte 3 is '1' (0x31), expected A-Z or a-z"
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_chunk_type_messages() {
+        let error = ChunkType::from_str("ru5t").unwrap_err();
+        assert!(error.to_string().ends_with("byte 3 is '5' (0x35), expected A-Z or a-z"));
+
+        let error = ChunkType::try_from([b'r', b'u', b'S', 0]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ChunkType: Invalid chunk type \"ruS\\x00\", byte 4 is '\\x00' (0x00), \
+             expected A-Z or a-z"
+        );
+
+        let error = ChunkType::from_str(" ruSt").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "ChunkType: Chunk type \" ruSt\" is 5 bytes long, expected 4"
+        );
+        let error = ChunkType::from_str("RuSt\u{e9}").unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunkTypeLength { length: 6, .. }));
+        assert_eq!(
+            error.to_string(),
+            "ChunkType: Chunk type \"RuSt\\u{e9}\" is 6 bytes long, expected 4"
+        );
+        // Four characters, but not four bytes
+        let error = ChunkType::from_str("R\u{fc}St").unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunkTypeLength { length: 5, .. }));
     }
 
     #[test]
@@ -179,6 +524,162 @@ mod tests {
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_safe_to_copy_variants() {
+        let chunk = ChunkType::from_str("RuST").unwrap();
+
+        let safe = chunk.as_safe_to_copy();
+        assert_eq!(&safe.to_string(), "RuSt");
+        assert!(safe.is_safe_to_copy());
+        assert!(safe.is_valid());
+
+        let unsafe_to_copy = safe.as_unsafe_to_copy();
+        assert_eq!(unsafe_to_copy, chunk);
+        assert!(!unsafe_to_copy.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_critical_variants() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+
+        let ancillary = chunk.as_ancillary();
+        assert_eq!(&ancillary.to_string(), "ruSt");
+        assert!(!ancillary.is_critical());
+        assert!(ancillary.is_valid());
+
+        let critical = ancillary.as_critical();
+        assert_eq!(critical, chunk);
+        assert!(critical.is_critical());
+        assert_eq!(critical.as_critical(), chunk);
+    }
+
+    #[test]
+    pub fn test_chunk_type_canonical_priority() {
+        let priority = |s| ChunkType::from_str(s).unwrap().canonical_priority();
+        assert!(priority("IHDR") < priority("gAMA"));
+        assert!(priority("gAMA") < priority("PLTE"));
+        assert!(priority("PLTE") < priority("tRNS"));
+        assert!(priority("tRNS") < priority("IDAT"));
+        assert!(priority("IDAT") < priority("tEXt"));
+        assert!(priority("tEXt") < priority("ruSt"));
+        assert!(priority("ruSt") < priority("IEND"));
+    }
+
+    #[test]
+    pub fn test_chunk_type_string_alternate() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        let breakdown = format!("{:#}", chunk);
+        assert!(breakdown.starts_with("ChunkType {\n  Data: \"RuSt\"\n"));
+        assert!(breakdown.contains("  Critical: true\n"));
+        assert!(breakdown.contains("  Public: false\n"));
+        assert!(breakdown.ends_with("  Safe to copy: true\n}"));
+    }
+
+    #[test]
+    pub fn test_chunk_type_hex() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(format!("{:X}", chunk), "52755374");
+        assert_eq!(format!("{:x}", chunk), "52755374");
+
+        let chunk = ChunkType::from_str("tEXt").unwrap();
+        assert_eq!(format!("{:X}", chunk), "74455874");
+    }
+
+    #[test]
+    pub fn test_chunk_type_binary() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(format!("{:b}", chunk), "01010010 01110101 01010011 01110100");
+        assert_eq!(chunk.property_bits(), (true, false, true, true));
+
+        let chunk = ChunkType::from_str("tEXt").unwrap();
+        assert_eq!(format!("{:b}", chunk), "01110100 01000101 01011000 01110100");
+        assert_eq!(chunk.property_bits(), (false, true, true, true));
+    }
+
+    #[test]
+    pub fn test_chunk_type_eq_str() {
+        let chunk = ChunkType::from_str("IHDR").unwrap();
+        assert!(chunk == "IHDR");
+        assert!(chunk != "IEND");
+        assert!(chunk != "IHD");
+        assert!(chunk != "IHDRs");
+        assert!(chunk != "ihdr");
+        assert!(chunk == *"IHDR");
+        assert_eq!(chunk, "IHDR");
+
+        let name = String::from("IHDR");
+        assert!(chunk == &name);
+        assert!(chunk != &String::from("IEND"));
+    }
+
+    #[test]
+    pub fn test_chunk_type_name_and_as_bytes() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(chunk.name(), "RuSt");
+        assert_eq!(chunk.as_bytes(), b"RuSt");
+        assert_eq!(chunk.name(), chunk.to_string());
+
+        let raw = ChunkType::from_raw_bytes_unchecked([b'R', 0, b'S', 0xFF]);
+        assert_eq!(raw.name(), "\u{FFFD}");
+        assert_eq!(raw.as_bytes(), &[b'R', 0, b'S', 0xFF]);
+    }
+
+    #[test]
+    pub fn test_chunk_type_eq_bytes() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk == [82, 117, 83, 116]);
+        assert!(chunk == *b"RuSt");
+        assert!(chunk != *b"RUST");
+    }
+
+    #[test]
+    pub fn test_chunk_type_describe() {
+        let standard = [
+            "IHDR", "PLTE", "IDAT", "IEND", "tRNS", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "cICP",
+            "mDCv", "cLLI", "tEXt", "zTXt", "iTXt", "bKGD", "hIST", "pHYs", "sPLT", "eXIf", "tIME",
+            "acTL", "fcTL", "fdAT", "oFFs", "pCAL", "sCAL", "gIFg", "gIFx", "sTER", "dSIG",
+        ];
+        for name in standard {
+            let chunk_type = ChunkType::from_str(name).unwrap();
+            assert!(chunk_type.describe().is_some(), "{} has no description", name);
+            assert!(chunk_type.is_known_standard());
+        }
+        assert_eq!(
+            ChunkType::IHDR.describe().unwrap(),
+            "Image header - defines dimensions, bit depth, and color type"
+        );
+
+        let private = ChunkType::from_str("ruSt").unwrap();
+        assert!(private.describe().is_none());
+        assert!(!private.is_known_standard());
+    }
+
+    #[test]
+    pub fn test_chunk_type_property_flags() {
+        let text = ChunkType::from_str("tEXt").unwrap();
+        assert_eq!(
+            text.property_flags(),
+            ChunkTypeFlags::PUBLIC | ChunkTypeFlags::RESERVED_VALID | ChunkTypeFlags::SAFE_TO_COPY
+        );
+        assert_eq!(text.property_string(), "ancillary|public|reserved-valid|safe-to-copy");
+
+        assert_eq!(
+            ChunkType::IHDR.property_flags(),
+            ChunkTypeFlags::CRITICAL | ChunkTypeFlags::PUBLIC | ChunkTypeFlags::RESERVED_VALID
+        );
+        assert_eq!(
+            ChunkType::IHDR.property_string(),
+            "critical|public|reserved-valid|unsafe-to-copy"
+        );
+
+        let private = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(
+            private.property_flags(),
+            ChunkTypeFlags::RESERVED_VALID | ChunkTypeFlags::SAFE_TO_COPY
+        );
+        assert_eq!(private.property_string(), "ancillary|private|reserved-valid|safe-to-copy");
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
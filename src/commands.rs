@@ -1,62 +1,2059 @@
-use std::fs;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::Path;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{anyhow, Context, Ok};
+use clap::CommandFactory;
+use indicatif::MultiProgress;
 
-use crate::args::{DecodeArgs, EncodeArgs, PrintArgs, RemoveArgs};
-use crate::chunk::Chunk;
-use crate::chunk_type::ChunkType;
-use crate::png::Png;
-use crate::Result;
-
-/// Encodes a message into a PNG file and saves the result
-pub fn encode(args: EncodeArgs) -> Result<()> {
-    let mut png = Png::from_file(args.file_path.as_path())?;
-    let chunk = Chunk::new(
-        ChunkType::from_str(&args.chunk_type)?,
-        args.message.as_bytes().to_vec(),
-    );
+use pngme::chunk::{Chunk, ChunkView};
+use pngme::chunk_type::ChunkType;
+use pngme::compress;
+#[cfg(feature = "encryption")]
+use pngme::crypto;
+#[cfg(feature = "ecc")]
+use pngme::ecc;
+use pngme::doctor::{self, Severity};
+use pngme::error::{PngError, PngResult};
+#[cfg(feature = "json")]
+use pngme::export::PngDocument;
+#[cfg(feature = "mmap")]
+use pngme::mmap::MappedPng;
+use pngme::png::{temp_sibling, OnError, ParseOptions, Png, PngView, StructureError};
+use pngme::standard_chunks::{new_time_chunk, parse_ihdr};
+use pngme::steg;
+use pngme::Result;
+
+use crate::args::{
+    CapacityArgs, CompletionsArgs, DecodeArgs, DoctorArgs, DownloadArgs, EncodeArgs, ExtractArgs,
+    GenerateArgs, InfoArgs, MergeArgs, OutputFormat, PngMeArgs, PrintArgs, RemoveArgs, RenameArgs,
+    ReorderArgs, StegArgs, StegCommands, StripArgs, VerifyArgs,
+};
+#[cfg(feature = "json")]
+use crate::args::{ExportArgs, ImportArgs};
+use crate::progress;
+use crate::render::Palette;
+
+/// What `--deterministic` uses in place of randomness and the clock, so that
+/// running the same command twice writes the same bytes. The default is
+/// random and uses the current time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Determinism {
+    /// Seed for made-up chunk types and encryption, or `None` for randomness
+    pub seed: Option<u64>,
+    /// Time to record instead of the current one
+    pub time: Option<SystemTime>,
+}
+
+impl Determinism {
+    /// Reads `--deterministic` and `--seed`, and the time from
+    /// `SOURCE_DATE_EPOCH`
+    pub fn from_args(args: &PngMeArgs) -> Result<Determinism> {
+        if !args.deterministic {
+            return Ok(Determinism::default());
+        }
+        let secs = match std::env::var("SOURCE_DATE_EPOCH").ok() {
+            Some(value) => value.trim().parse().map_err(|_| {
+                anyhow!("Commands: SOURCE_DATE_EPOCH must be a number of seconds, got {:?}", value)
+            })?,
+            None => 0,
+        };
+        Ok(Determinism {
+            seed: Some(args.seed),
+            time: Some(UNIX_EPOCH + Duration::from_secs(secs)),
+        })
+    }
+
+    /// The time to record as the current one
+    fn now(&self) -> SystemTime {
+        self.time.unwrap_or_else(SystemTime::now)
+    }
+
+    /// The `attempt`th private chunk type for the seed, or a random one
+    fn private_type(&self, attempt: u64) -> ChunkType {
+        match self.seed {
+            Some(seed) => ChunkType::private_from_seed(splitmix64(seed.wrapping_add(attempt))),
+            None => ChunkType::random_private(),
+        }
+    }
+
+    /// Encrypts `message`, with a salt and nonce derived from the seed if
+    /// there is one
+    #[cfg(feature = "encryption")]
+    fn encrypt(&self, message: &[u8], password: &str) -> Result<Vec<u8>> {
+        match self.seed {
+            Some(seed) => crypto::encrypt_with_seed(message, password, seed),
+            None => crypto::encrypt_with_password(message, password),
+        }
+    }
+}
+
+/// Scrambles `x` so that nearby seeds give unrelated chunk types
+fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
 
-    png.append_chunk(chunk);
+/// The chunk type argument that asks `encode` to make up a private chunk type
+const RANDOM_CHUNK_TYPE: &str = "random";
+
+/// Encodes a message, every `--pair`, or every message in a batch file, into a
+/// PNG file and saves the result
+pub fn encode(args: EncodeArgs, determinism: &Determinism) -> Result<()> {
+    let random_type = args.chunk_type.as_deref() == Some(RANDOM_CHUNK_TYPE);
+    if random_type && (args.fast || args.idempotent) {
+        return Err(anyhow!(
+            "Commands: A random chunk type can't be used with --fast or --idempotent"
+        ));
+    }
+    let pairs = match &args.batch_file {
+        Some(batch_file) => {
+            let contents = fs::read_to_string(batch_file).with_context(|| {
+                format!("Commands: Could not read batch file {}", batch_file.display())
+            })?;
+            parse_batch(&contents)?
+        }
+        None if !args.pair.is_empty() => parse_pairs(&args.pair)?,
+        // Made up once the file is loaded, so it can avoid the types already there
+        None if random_type => Vec::new(),
+        None => {
+            let chunk_type = args.chunk_type.as_deref().context("Commands: Missing chunk type")?;
+            let message = args.message.as_deref().context("Commands: Missing message")?;
+            vec![(ChunkType::from_str(chunk_type)?, message.as_bytes().to_vec())]
+        }
+    };
+    for (chunk_type, _) in &pairs {
+        check_critical(chunk_type, args.force_critical, "encode into")?;
+    }
+    #[cfg(feature = "encryption")]
+    if args.password.is_some() && determinism.seed.is_some() {
+        eprintln!(
+            "Warning: --deterministic derives the encryption salt and nonce from --seed and \
+             the message, so the same message always encrypts the same way. Only use it for \
+             reproducible builds, not to protect real secrets."
+        );
+    }
+    let pairs = transform_messages(pairs, &args, determinism)?;
+    #[cfg(feature = "http")]
+    let is_url = pngme::http::as_url(&args.file_path).is_some();
+    #[cfg(not(feature = "http"))]
+    let is_url = false;
+    if is_url && args.output_file.is_none() {
+        return Err(anyhow!(
+            "Commands: Cannot write back to {}, give an output file",
+            args.file_path.display()
+        ));
+    }
+    if args.fast && !is_url {
+        let size_before = fs::metadata(&args.file_path).map(|metadata| metadata.len());
+        if encode_in_place(&args, &pairs)? {
+            let path = args.output_file.as_ref().unwrap_or(&args.file_path);
+            if !args.quiet {
+                let size_after = fs::metadata(path)?.len();
+                let added = describe_added(&chunk_sizes(&pairs), &[]);
+                print_summary(&format!("added {}", added), size_before?, size_after, path);
+            }
+            return Ok(());
+        }
+    }
+    let options = ParseOptions::default();
+    let bars = MultiProgress::new();
+    let (mut png, _) =
+        stream_png_with_progress(&args.file_path, &args.download, options, &bars, args.quiet)?;
+    let size_before = png.serialized_size() as u64;
+
+    let mut pairs = pairs;
+    if random_type {
+        let chunk_type = unused_private_type(&png, determinism);
+        println!("Encoded into chunk type {}", chunk_type);
+        let message = args.message.as_deref().context("Commands: Missing message")?;
+        let pair = (chunk_type, message.as_bytes().to_vec());
+        pairs = transform_messages(vec![pair], &args, determinism)?;
+    }
+    if args.idempotent {
+        let (present, missing): (Vec<_>, Vec<_>) = pairs
+            .into_iter()
+            .partition(|(chunk_type, data)| png.contains_identical(chunk_type, data));
+        if !args.quiet {
+            for (chunk_type, _) in &present {
+                eprintln!("{} already present, skipped", chunk_type);
+            }
+        }
+        pairs = missing;
+    }
+    if args.idempotent && pairs.is_empty() {
+        // Not writing keeps the modification time, so build tools see no change
+        match &args.output_file {
+            Some(output_file) => {
+                copy_unchanged(&args.file_path, &png, output_file, is_url)?;
+                if !args.quiet {
+                    eprintln!("already present; wrote {} unchanged", output_file.display());
+                }
+            }
+            None if !args.quiet => eprintln!("already present, nothing to do"),
+            None => {}
+        }
+        return Ok(());
+    }
+    let added = (!args.quiet).then(|| chunk_sizes(&pairs));
+    let expected = args.verify.then(|| pairs.clone());
+    let indexes = encode_batch(&mut png, pairs)?;
+    if args.strip_trailing {
+        png.take_trailing_data();
+    }
+    if args.timestamp {
+        let time_chunk = new_time_chunk(&determinism.now())?;
+        // The spec allows a single tIME chunk, so update any existing one
+        match png.position_of(time_chunk.chunk_type()) {
+            Some(idx) => png.chunks_mut()[idx] = time_chunk,
+            None => {
+                insert_before_iend(&mut png, time_chunk)?;
+            }
+        }
+    }
+    check_structure(&png, args.strict)?;
 
     let output_path = match args.output_file {
         Some(path) => path,
         None => args.file_path,
     };
 
-    fs::write(output_path.as_path(), png.as_bytes()).context("Commands: Could not write to file")
+    match expected {
+        Some(expected) => write_verified(&png, &output_path, &expected, &indexes, |png, path| {
+            png.write_to_file(path)
+        })?,
+        None => png.write_to_file_atomic(&output_path)?,
+    }
+    if let Some(added) = added {
+        let changes = format!("added {}", describe_added(&added, &indexes));
+        print_summary(&changes, size_before, png.serialized_size() as u64, &output_path);
+    }
+    Ok(())
+}
+
+/// Compresses, encrypts and adds error correction to the message of each pair,
+/// as `args` asks
+#[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+fn transform_messages(
+    pairs: Vec<(ChunkType, Vec<u8>)>,
+    args: &EncodeArgs,
+    determinism: &Determinism,
+) -> Result<Vec<(ChunkType, Vec<u8>)>> {
+    let pairs = match args.compress {
+        Some(compression) => pairs
+            .into_iter()
+            .map(|(chunk_type, message)| {
+                Ok((chunk_type, compress::compress(&message, compression)?))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => pairs,
+    };
+    #[cfg(feature = "encryption")]
+    let pairs = match &args.password {
+        Some(password) => pairs
+            .into_iter()
+            .map(|(chunk_type, message)| Ok((chunk_type, determinism.encrypt(&message, password)?)))
+            .collect::<Result<Vec<_>>>()?,
+        None => pairs,
+    };
+    #[cfg(feature = "ecc")]
+    let pairs = if args.ecc {
+        pairs
+            .into_iter()
+            .map(|(chunk_type, message)| Ok((chunk_type, ecc::encode(&message, args.parity)?)))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        pairs
+    };
+    Ok(pairs)
+}
+
+/// Copies the input file at `path` byte for byte to `output_file`, for an
+/// encode that had nothing to add. A download is no longer around to copy, but
+/// `png` was parsed from it strictly, so it serializes to the same bytes.
+fn copy_unchanged(path: &Path, png: &Png, output_file: &Path, is_url: bool) -> Result<()> {
+    if is_url {
+        return png.write_to_file_atomic(output_file);
+    }
+    // Through a temporary file, as copying a file onto itself would empty it
+    let temp_path = temp_sibling(output_file)?;
+    let result = fs::copy(path, &temp_path)
+        .map(|_| ())
+        .with_context(|| format!("Commands: Could not copy {}", path.display()));
+    replace_if_ok(&temp_path, output_file, result)
+}
+
+/// Writes `png` with `write` to a temporary file next to `path`, reads it back
+/// and checks that it holds exactly the bytes of `png`, with the chunk for each
+/// pair at its index in `indexes`, then renames it over `path`. When the check
+/// fails only the temporary file is removed, so `path` is left as it was.
+fn write_verified(
+    png: &Png,
+    path: &Path,
+    pairs: &[(ChunkType, Vec<u8>)],
+    indexes: &[usize],
+    write: impl FnOnce(&Png, &Path) -> Result<()>,
+) -> Result<()> {
+    let temp_path = temp_sibling(path)?;
+    let result = write(png, &temp_path).and_then(|()| {
+        let written = fs::read(&temp_path)
+            .with_context(|| format!("Commands: Could not read back {}", path.display()))?;
+        compare_written(png, &written, path)?;
+        let read_back = Png::try_from(written.as_slice())?;
+        verify_pairs(&read_back, path, pairs, indexes, png.validate().is_ok())
+    });
+    replace_if_ok(&temp_path, path, result)
+}
+
+/// Fails with the first byte where `written`, read back for `path`, differs
+/// from `png`
+fn compare_written(png: &Png, written: &[u8], path: &Path) -> Result<()> {
+    let expected = png.as_bytes();
+    if let Some(offset) = written.iter().zip(&expected).position(|(a, b)| a != b) {
+        return Err(anyhow!(
+            "Commands: Verification failed, {} differs from what was written at byte {}",
+            path.display(),
+            offset
+        ));
+    }
+    if written.len() != expected.len() {
+        return Err(anyhow!(
+            "Commands: Verification failed, {} is {} bytes long, expected {}",
+            path.display(),
+            written.len(),
+            expected.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Reads back the file `--fast` wrote to `temp_path` for `path` and checks
+/// that the chunks right before its first IEND are the ones for `pairs`
+fn verify_inserted(temp_path: &Path, path: &Path, pairs: &[(ChunkType, Vec<u8>)]) -> Result<()> {
+    let written = Png::try_from(temp_path)?;
+    let iend = written
+        .position_of(&ChunkType::IEND)
+        .filter(|&iend| iend >= pairs.len())
+        .with_context(|| {
+            format!("Commands: Verification failed, {} has no IEND chunk", path.display())
+        })?;
+    let indexes: Vec<usize> = (iend - pairs.len()..iend).collect();
+    // The file may not have been valid to begin with, which --fast doesn't check
+    verify_pairs(&written, path, pairs, &indexes, false)
+}
+
+/// Checks that `written`, read back from `path`, has the chunk for each pair at
+/// its index in `indexes`, and when `structure_ok` is set, that its chunk
+/// layout is still valid
+fn verify_pairs(
+    written: &Png,
+    path: &Path,
+    pairs: &[(ChunkType, Vec<u8>)],
+    indexes: &[usize],
+    structure_ok: bool,
+) -> Result<()> {
+    for ((chunk_type, data), &index) in pairs.iter().zip(indexes) {
+        verify_chunk_at(written, path, index, chunk_type, data)?;
+    }
+    if !structure_ok {
+        return Ok(());
+    }
+    if let Err(errors) = written.validate() {
+        let report: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        return Err(anyhow!(
+            "Commands: Verification failed, {} is no longer a valid PNG: {}",
+            path.display(),
+            report.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that the chunk at `index` of `written`, read back from `path`, is of
+/// type `chunk_type` and holds exactly `expected`. A matching chunk elsewhere
+/// in the file doesn't count, as it may have been there before.
+fn verify_chunk_at(
+    written: &Png,
+    path: &Path,
+    index: usize,
+    chunk_type: &ChunkType,
+    expected: &[u8],
+) -> Result<()> {
+    let chunk = written
+        .chunks()
+        .get(index)
+        .filter(|chunk| chunk.chunk_type() == chunk_type)
+        .with_context(|| {
+            format!(
+                "Commands: Verification failed, {} has no {} chunk at index {}",
+                path.display(),
+                chunk_type,
+                index
+            )
+        })?;
+    if chunk.data() != expected {
+        return Err(anyhow!(
+            "Commands: Verification failed, the {} chunk at index {} in {} does not hold the \
+             encoded message",
+            chunk_type,
+            index,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Reads the PNG file at `path` back and checks that it has a chunk of type
+/// `chunk_type` holding exactly `expected`, anywhere in the file. `encode
+/// --verify` uses `verify_chunk_at` instead, as it knows where it wrote each one.
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn verify_chunk_in_file(path: &Path, chunk_type: &ChunkType, expected: &[u8]) -> Result<()> {
+    let png = Png::try_from(path)?;
+    let mut matching = png.chunks().iter().filter(|chunk| chunk.chunk_type() == chunk_type);
+    if matching.clone().next().is_none() {
+        return Err(anyhow!(
+            "Commands: Verification failed, {} has no {} chunk",
+            path.display(),
+            chunk_type
+        ));
+    }
+    if !matching.any(|chunk| chunk.data() == expected) {
+        return Err(anyhow!(
+            "Commands: Verification failed, no {} chunk in {} holds the encoded message",
+            chunk_type,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// The type and data length of the chunk each pair becomes
+fn chunk_sizes(pairs: &[(ChunkType, Vec<u8>)]) -> Vec<(ChunkType, usize)> {
+    pairs.iter().map(|(chunk_type, data)| (*chunk_type, data.len())).collect()
+}
+
+/// Lists added chunks with their sizes, and with the indexes they ended up at
+/// when `indexes` has them
+fn describe_added(sizes: &[(ChunkType, usize)], indexes: &[usize]) -> String {
+    sizes
+        .iter()
+        .enumerate()
+        .map(|(idx, (chunk_type, len))| match indexes.get(idx) {
+            Some(index) => format!("{} ({} bytes) at index {}", chunk_type, len, index),
+            None => format!("{} ({} bytes) before IEND", chunk_type, len),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Tells the user what a command changed in the file it wrote to `path`
+fn print_summary(changes: &str, size_before: u64, size_after: u64, path: &Path) {
+    eprintln!(
+        "{}; file {} -> {}; wrote {}",
+        changes,
+        format_size(size_before),
+        format_size(size_after),
+        path.display()
+    );
+}
+
+/// Formats `bytes` in bytes, KiB or MiB, whichever reads best
+fn format_size(bytes: u64) -> String {
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * KIB;
+    match bytes {
+        0..KIB => format!("{} bytes", bytes),
+        KIB..MIB => format!("{:.1} KiB", bytes as f64 / KIB as f64),
+        _ => format!("{:.1} MiB", bytes as f64 / MIB as f64),
+    }
+}
+
+/// Adds the chunks for `pairs` before IEND without parsing the whole file,
+/// writing the result to the output file, or back to the input file if there
+/// is none. With `--verify` the result is checked before it replaces
+/// anything. Returns `false` if the file is not laid out as expected and needs
+/// the full parse instead.
+fn encode_in_place(args: &EncodeArgs, pairs: &[(ChunkType, Vec<u8>)]) -> Result<bool> {
+    let path = args.output_file.as_ref().unwrap_or(&args.file_path);
+    let temp_path = temp_sibling(path)?;
+    let chunks: Vec<Chunk> = pairs
+        .iter()
+        .map(|(chunk_type, data)| Chunk::new(*chunk_type, data.clone()))
+        .collect();
+    let inserted = Png::insert_before_iend_copying(&args.file_path, &temp_path, &chunks)
+        .map_err(load_error(&args.file_path))?;
+    if !inserted {
+        return Ok(false);
+    }
+    let result = match args.verify {
+        true => verify_inserted(&temp_path, path, pairs),
+        false => Ok(()),
+    };
+    replace_if_ok(&temp_path, path, result)?;
+    Ok(true)
+}
+
+/// Renames the file at `temp_path` over `path` if `result` is ok, and removes
+/// it otherwise, leaving `path` as it was
+fn replace_if_ok(temp_path: &Path, path: &Path, result: Result<()>) -> Result<()> {
+    if let Err(e) = result {
+        let _ = fs::remove_file(temp_path);
+        return Err(e);
+    }
+    fs::rename(temp_path, path)
+        .with_context(|| format!("Commands: Could not replace {}", path.display()))
+}
+
+/// Makes up a private chunk type that `png` has no chunks of
+fn unused_private_type(png: &Png, determinism: &Determinism) -> ChunkType {
+    let mut attempt = 0;
+    loop {
+        let chunk_type = determinism.private_type(attempt);
+        if png.position_of(&chunk_type).is_none() {
+            return chunk_type;
+        }
+        attempt += 1;
+    }
+}
+
+/// Adds one chunk per `(chunk_type, data)` pair to `png`, in order, and
+/// returns the index each one ended up at
+pub fn encode_batch(png: &mut Png, pairs: Vec<(ChunkType, Vec<u8>)>) -> Result<Vec<usize>> {
+    pairs
+        .into_iter()
+        .map(|(chunk_type, data)| insert_before_iend(png, Chunk::new(chunk_type, data)))
+        .collect()
+}
+
+/// Parses `chunk_type,message` CSV rows with no header row. Messages with
+/// commas, quotes or line breaks in them need quoting, and blank lines are
+/// skipped.
+fn parse_batch(contents: &str) -> Result<Vec<(ChunkType, Vec<u8>)>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let mut pairs = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let row = idx + 1;
+        let record = record
+            .with_context(|| format!("Commands: Row {} of the batch file is not valid CSV", row))?;
+        if record.len() != 2 {
+            return Err(anyhow!(
+                "Commands: Row {} of the batch file has {} fields, expected chunk_type,message",
+                row,
+                record.len()
+            ));
+        }
+        let chunk_type = ChunkType::from_str(record[0].trim()).with_context(|| {
+            format!("Commands: Row {} of the batch file has an invalid chunk type", row)
+        })?;
+        pairs.push((chunk_type, record[1].as_bytes().to_vec()));
+    }
+    Ok(pairs)
+}
+
+/// Parses `TYPE=MESSAGE` arguments. Everything after the first `=` is the
+/// message.
+fn parse_pairs(args: &[String]) -> Result<Vec<(ChunkType, Vec<u8>)>> {
+    args.iter()
+        .enumerate()
+        .map(|(idx, arg)| {
+            let pair = idx + 1;
+            let (chunk_type, message) = arg.split_once('=').ok_or_else(|| {
+                anyhow!("Commands: Pair {} \"{}\" has no = separator", pair, arg)
+            })?;
+            let chunk_type = ChunkType::from_str(chunk_type).with_context(|| {
+                format!("Commands: Pair {} has an invalid chunk type", pair)
+            })?;
+            Ok((chunk_type, message.as_bytes().to_vec()))
+        })
+        .collect()
+}
+
+/// Inserts `chunk` right before IEND, so that a valid PNG stays valid, and
+/// returns the index it was inserted at
+fn insert_before_iend(png: &mut Png, chunk: Chunk) -> Result<usize> {
+    match png.position_of(&ChunkType::IEND) {
+        Some(iend) => {
+            png.insert_chunk(iend, chunk)?;
+            Ok(iend)
+        }
+        None => {
+            png.append_chunk(chunk);
+            Ok(png.len() - 1)
+        }
+    }
 }
 
 /// Searches for a message hidden in a PNG file and prints the message if one is found
 pub fn decode(args: DecodeArgs) -> Result<()> {
-    let png = Png::from_file(args.file_path.as_path())?;
-    let chunk = png.chunk_by_type(&args.chunk_type);
+    let chunk_type = ChunkType::from_str(&args.chunk_type)?;
+    let nth = args.nth.unwrap_or(0);
+    #[cfg(feature = "ecc")]
+    let options = parse_options(args.no_verify || args.ecc);
+    #[cfg(not(feature = "ecc"))]
+    let options = parse_options(args.no_verify);
 
-    match chunk {
-        Some(chunk) => {
-            let message = chunk.data_as_string()?;
-            println!("{}", message);
-            Ok(())
+    #[cfg(feature = "mmap")]
+    let mapped = map_large_file(&args.file_path, args.no_mmap)?;
+    let first_only = !args.all && !args.count && nth == 0;
+
+    #[cfg(feature = "mmap")]
+    if let Some(mapped) = mapped.as_ref().filter(|_| !first_only) {
+        let (view, _) = mapped.view_with(options).map_err(load_error(&args.file_path))?;
+        let matching = view.chunks().iter().filter(|chunk| chunk.chunk_type() == &chunk_type);
+        if args.count || args.quiet {
+            return report_matches(matching.count(), &chunk_type, nth, &args);
+        }
+        if args.all {
+            return print_all_messages(matching.map(|chunk| chunk.data()), &args);
         }
-        None => Err(anyhow!("No message found")),
+        let data = view.nth_chunk_of_type(&chunk_type, nth).map(|chunk| chunk.data());
+        return print_message(check_nth(data, &chunk_type, nth, matching.count())?, &args);
     }
+
+    if first_only {
+        // Only the first match is needed, so skip over everything else. Chunks
+        // that are skipped don't have their CRCs checked, mapped or not.
+        #[cfg(feature = "mmap")]
+        let chunk = match &mapped {
+            Some(mapped) => {
+                let r = io::Cursor::new(mapped.as_bytes());
+                Png::find_chunk_in_reader_with(r, &chunk_type, options)
+                    .map_err(load_error(&args.file_path))?
+            }
+            None => stream_png(&args.file_path, &args.download, |r| {
+                Png::find_chunk_in_reader_with(r, &chunk_type, options)
+            })?,
+        };
+        #[cfg(not(feature = "mmap"))]
+        let chunk = stream_png(&args.file_path, &args.download, |r| {
+            Png::find_chunk_in_reader_with(r, &chunk_type, options)
+        })?;
+        if args.quiet {
+            return report_matches(usize::from(chunk.is_some()), &chunk_type, nth, &args);
+        }
+        return print_message(chunk.as_ref().map(|chunk| chunk.data()), &args);
+    }
+    let (png, _) =
+        stream_png(&args.file_path, &args.download, |r| Png::from_reader_with(r, options))?;
+    let matching = png.chunks().iter().filter(|chunk| chunk.chunk_type() == &chunk_type);
+    if args.count || args.quiet {
+        return report_matches(matching.count(), &chunk_type, nth, &args);
+    }
+    if args.all {
+        return print_all_messages(matching.map(|chunk| chunk.data()), &args);
+    }
+    let data = png.nth_chunk_of_type(&chunk_type, nth).map(|chunk| chunk.data());
+    print_message(check_nth(data, &chunk_type, nth, matching.count())?, &args)
+}
+
+/// Prints the number of matching chunks for `--count`. Otherwise `--quiet` is
+/// set, and this only fails if there is no `nth` match.
+fn report_matches(
+    found: usize,
+    chunk_type: &ChunkType,
+    nth: usize,
+    args: &DecodeArgs,
+) -> Result<()> {
+    if args.count {
+        println!("{}", found);
+    } else if found <= nth {
+        check_nth(None, chunk_type, nth, found)?;
+        return Err(anyhow!("No message found"));
+    }
+    Ok(())
+}
+
+/// The options for parsing a PNG file, skipping the CRC checks if `no_verify` is set
+fn parse_options(no_verify: bool) -> ParseOptions {
+    ParseOptions {
+        verify_crc: !no_verify,
+        ..ParseOptions::default()
+    }
+}
+
+/// Turns a missing `nth` match into an error that says how many chunks of
+/// type `chunk_type` there are. Asking for the first one keeps the plain
+/// "No message found" error.
+fn check_nth<'a>(
+    data: Option<&'a [u8]>,
+    chunk_type: &ChunkType,
+    nth: usize,
+    found: usize,
+) -> Result<Option<&'a [u8]>> {
+    if data.is_none() && nth > 0 {
+        return Err(anyhow!(
+            "No {} chunk at index {}, only {} found",
+            chunk_type,
+            nth,
+            found
+        ));
+    }
+    Ok(data)
+}
+
+/// Prints the message of every chunk in `messages`, failing if there are none
+fn print_all_messages<'a>(
+    messages: impl Iterator<Item = &'a [u8]>,
+    args: &DecodeArgs,
+) -> Result<()> {
+    let mut messages = messages.peekable();
+    if messages.peek().is_none() {
+        return Err(anyhow!("No message found"));
+    }
+    messages.try_for_each(|data| print_message(Some(data), args))
+}
+
+/// Prints a decoded message, replacing invalid UTF-8 with a warning unless
+/// `--strict` is set, or pretty-printing it as JSON with `--json`
+fn print_message(data: Option<&[u8]>, args: &DecodeArgs) -> Result<()> {
+    let data = data.ok_or_else(|| anyhow!("No message found"))?;
+    #[cfg(feature = "ecc")]
+    let repaired;
+    #[cfg(feature = "ecc")]
+    let data = if args.ecc {
+        repaired = ecc::decode(data)?;
+        if repaired.repaired > 0 {
+            eprintln!("Repaired {} damaged byte(s)", repaired.repaired);
+        }
+        repaired.message.as_slice()
+    } else {
+        data
+    };
+    #[cfg(feature = "encryption")]
+    let decrypted;
+    #[cfg(feature = "encryption")]
+    let data = match (&args.decrypt, &args.password) {
+        (true, Some(password)) => {
+            decrypted = crypto::decrypt_with_password(data, password)?;
+            decrypted.as_slice()
+        }
+        _ => data,
+    };
+    let decompressed;
+    let data = if args.decompress {
+        decompressed = compress::decompress(data)?;
+        decompressed.as_slice()
+    } else {
+        data
+    };
+    if raw_output(args) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(data)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    #[cfg(feature = "json")]
+    if args.json {
+        let value = pngme::chunk::json_from_data(data)?;
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+    if args.strict {
+        let message = std::str::from_utf8(data).context("Chunk: Data is not valid UTF-8")?;
+        println!("{}", message);
+        return Ok(());
+    }
+
+    let replaced = count_invalid_utf8(data);
+    if replaced > 0 {
+        eprintln!(
+            "Warning: Message is not valid UTF-8, replaced {} invalid sequence(s) with U+FFFD",
+            replaced
+        );
+    }
+    println!("{}", String::from_utf8_lossy(data));
+    Ok(())
+}
+
+/// Whether `decode` should write the message bytes as they are instead of as
+/// text. That is the default when stdout is piped, unless an option that only
+/// makes sense for text is set.
+fn raw_output(args: &DecodeArgs) -> bool {
+    #[cfg(feature = "json")]
+    let json = args.json;
+    #[cfg(not(feature = "json"))]
+    let json = false;
+    args.raw || !(args.text || args.strict || args.all || json || io::stdout().is_terminal())
+}
+
+/// Parses the PNG file at `path` with `parse`, streaming it instead of reading
+/// the whole file into memory first. With the `http` feature, `path` may also
+/// be a URL to download.
+fn stream_png<T>(
+    path: &Path,
+    download: &DownloadArgs,
+    parse: impl FnOnce(Input) -> PngResult<T>,
+) -> Result<T> {
+    let input = open_input(path, download)?;
+    Ok(parse(input).map_err(load_error(path))?)
+}
+
+/// Like `stream_png` with `Png::from_reader_with`, adding a progress bar to
+/// `bars` for large files unless `quiet` is set
+fn stream_png_with_progress(
+    path: &Path,
+    download: &DownloadArgs,
+    options: ParseOptions,
+    bars: &MultiProgress,
+    quiet: bool,
+) -> Result<(Png, Vec<PngError>)> {
+    stream_png(path, download, |input| {
+        let bar = progress::file_bar(bars, path, input.len(), quiet);
+        let parsed =
+            Png::from_reader_with_progress(input, options, |read| bar.set_position(read));
+        bar.finish_and_clear();
+        parsed
+    })
+}
+
+/// A PNG file being read, either from disk or from a download held in memory
+enum Input {
+    File(BufReader<File>),
+    #[cfg(feature = "http")]
+    Downloaded(io::Cursor<Vec<u8>>),
+}
+
+impl Input {
+    /// The size of the whole input in bytes, or 0 if it is unknown
+    fn len(&self) -> u64 {
+        match self {
+            Input::File(reader) => reader.get_ref().metadata().map_or(0, |m| m.len()),
+            #[cfg(feature = "http")]
+            Input::Downloaded(reader) => reader.get_ref().len() as u64,
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(reader) => reader.read(buf),
+            #[cfg(feature = "http")]
+            Input::Downloaded(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Input::File(reader) => reader.seek(pos),
+            #[cfg(feature = "http")]
+            Input::Downloaded(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Opens `path` for reading, downloading it first if it is a URL
+#[cfg_attr(not(feature = "http"), allow(unused_variables))]
+fn open_input(path: &Path, download: &DownloadArgs) -> Result<Input> {
+    #[cfg(feature = "http")]
+    if let Some(url) = pngme::http::as_url(path) {
+        let bytes = pngme::http::download(url, download.max_download_size)?;
+        return Ok(Input::Downloaded(io::Cursor::new(bytes)));
+    }
+    let file = File::open(path).map_err(|e| load_error(path)(e.into()))?;
+    Ok(Input::File(BufReader::new(file)))
+}
+
+/// Wraps an error from loading the PNG file at `path` so that it names the file
+fn load_error(path: &Path) -> impl FnOnce(PngError) -> PngError + '_ {
+    move |e| PngError::Load {
+        path: path.to_path_buf(),
+        source: Box::new(e),
+    }
+}
+
+/// Files at least this large are memory-mapped by read-only commands
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Memory-maps the file at `path` if it is large enough to benefit, unless
+/// `no_mmap` is set
+#[cfg(feature = "mmap")]
+fn map_large_file(path: &Path, no_mmap: bool) -> Result<Option<MappedPng>> {
+    let large = fs::metadata(path).is_ok_and(|metadata| metadata.len() >= MMAP_THRESHOLD);
+    if no_mmap || !large {
+        return Ok(None);
+    }
+    Ok(Some(MappedPng::open(path)?))
+}
+
+/// Counts the invalid UTF-8 sequences in `bytes`, each of which a lossy
+/// conversion replaces with one U+FFFD
+fn count_invalid_utf8(bytes: &[u8]) -> usize {
+    bytes
+        .utf8_chunks()
+        .filter(|chunk| !chunk.invalid().is_empty())
+        .count()
 }
 
 /// Removes a chunk from a PNG file and saves the result
 pub fn remove(args: RemoveArgs) -> Result<()> {
-    let mut png = Png::from_file(args.file_path.as_path())?;
+    let chunk_type = ChunkType::from_str(&args.chunk_type)?;
+    check_critical(&chunk_type, args.force_critical, "remove")?;
+    #[cfg(feature = "http")]
+    if pngme::http::as_url(&args.file_path).is_some() {
+        return Err(anyhow!(
+            "Commands: Cannot remove chunks from {}, only local files can be changed",
+            args.file_path.display()
+        ));
+    }
+    if chunk_type == ChunkType::IHDR || chunk_type == ChunkType::IEND {
+        eprintln!(
+            "Warning: Removing the {} chunk means the output won't be a valid PNG",
+            chunk_type
+        );
+    }
+    let mut png = Png::try_from(&args.file_path)?;
+    let size_before = png.serialized_size() as u64;
+
+    let index = png.position_of(&chunk_type);
+    let removed = png.remove_chunk(&chunk_type)?;
+    check_structure(&png, args.strict)?;
+
+    png.write_to_file_atomic(&args.file_path)?;
+    if !args.quiet {
+        let size_after = png.serialized_size() as u64;
+        let changes = format!(
+            "removed 1 {} chunk ({} bytes) at index {}, saving {} bytes",
+            removed.chunk_type(),
+            removed.data_len(),
+            index.unwrap_or_default(),
+            size_before - size_after
+        );
+        print_summary(&changes, size_before, size_after, &args.file_path);
+    }
+    Ok(())
+}
 
-    png.remove_chunk(&args.chunk_type)?;
+/// Refuses to `action` a critical chunk type, since that changes how the image
+/// is decoded, unless `force` is set
+fn check_critical(chunk_type: &ChunkType, force: bool, action: &str) -> Result<()> {
+    if chunk_type.is_critical() && !force {
+        return Err(anyhow!(
+            "Commands: Refusing to {} critical chunk type {}, which would likely corrupt the image; \
+             pass --force-critical to do it anyway",
+            action,
+            chunk_type
+        ));
+    }
+    Ok(())
+}
 
-    fs::write(args.file_path.as_path(), png.as_bytes()).context("Commands: Could not write to file")
+/// Warns about every way `png` breaks the PNG chunk layout rules, or fails
+/// on the first report when `strict` is set
+fn check_structure(png: &Png, strict: bool) -> Result<()> {
+    if let Err(errors) = png.validate() {
+        if strict {
+            let report: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+            return Err(anyhow!("Commands: Resulting PNG is invalid: {}", report.join("; ")));
+        }
+        for error in errors {
+            eprintln!("Warning: {}", error);
+        }
+    }
+    Ok(())
 }
 
 /// Prints all of the chunks in a PNG file
 pub fn print_chunks(args: PrintArgs) -> Result<()> {
-    let file = fs::read(args.file_path)?;
-    let png = Png::try_from(file.as_slice())?;
-    for chunk in png.chunks() {
-        println!("{}", chunk);
+    let options = parse_options(args.no_verify);
+    #[cfg(feature = "mmap")]
+    if let Some(mapped) = map_large_file(&args.file_path, args.no_mmap)? {
+        let (view, _) = mapped.view_with(options).map_err(load_error(&args.file_path))?;
+        return print_view(&view, &args);
+    }
+    // Reading the whole file lets each chunk borrow its data from the buffer
+    // instead of being copied into an owned `Chunk`
+    let bytes = stream_png(&args.file_path, &args.download, |mut input| {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        PngResult::Ok(bytes)
+    })?;
+    let (view, _) = PngView::parse_with(&bytes, options).map_err(load_error(&args.file_path))?;
+    print_view(&view, &args)
+}
+
+/// Prints the chunks of `view`, or a summary of them, as `args` asks
+fn print_view(view: &PngView, args: &PrintArgs) -> Result<()> {
+    if args.summary {
+        let chunks = view.chunks().iter().map(|chunk| (*chunk.chunk_type(), chunk.data_len()));
+        print_summary_table(&summarize(chunks), args)?;
+    } else {
+        print_listing(view.chunks(), Palette::new(args.color), args)?;
     }
+    warn_trailing_data(view.trailing_data().len());
     Ok(())
 }
+
+/// Prints `chunks` in the format `args` asks for
+fn print_listing(chunks: &[ChunkView], palette: Palette, args: &PrintArgs) -> Result<()> {
+    let file = args.file_path.display().to_string();
+    let mut offset = Png::STANDARD_HEADER.len() as u64;
+    let mut rows = Vec::with_capacity(chunks.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        rows.push(ChunkRow {
+            index,
+            offset,
+            chunk_type: *chunk.chunk_type(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+        });
+        offset += u64::from(chunk.length()) + 12;
+    }
+
+    match args.format {
+        OutputFormat::Human => {
+            for chunk in chunks {
+                // Without --no-verify a bad CRC has already failed the parse
+                let crc_ok = !args.no_verify || chunk.validate_crc().is_ok();
+                let entry = chunk.display_with_data(args.data, args.data_hex);
+                print_chunk(&entry, chunk.chunk_type(), crc_ok, args.verbose, palette);
+            }
+        }
+        OutputFormat::Json => {
+            let objects: Vec<String> =
+                rows.iter().map(|row| row.to_json(&file).to_string()).collect();
+            println!("[{}]", objects.join(",\n "));
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record(ChunkRow::CSV_HEADER)?;
+            for row in &rows {
+                writer.write_record(row.to_csv(&file))?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// One row of `print --summary`: how many chunks of a type a file has and how
+/// much data they hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSummaryEntry {
+    pub chunk_type: ChunkType,
+    pub count: usize,
+    pub total_bytes: usize,
+}
+
+/// Groups the chunks of `png` by type, with the largest total data first.
+/// `print --summary` works on a `PngView` and goes to `summarize` directly
+#[cfg_attr(not(test), allow(dead_code))]
+pub fn chunk_summary(png: &Png) -> Vec<ChunkSummaryEntry> {
+    summarize(png.chunks().iter().map(|chunk| (*chunk.chunk_type(), chunk.data_len())))
+}
+
+/// Groups `(chunk_type, data_len)` pairs by type, with the largest total first
+/// and types with the same total in the order they first appear
+fn summarize(chunks: impl Iterator<Item = (ChunkType, usize)>) -> Vec<ChunkSummaryEntry> {
+    let mut entries: Vec<ChunkSummaryEntry> = Vec::new();
+    for (chunk_type, len) in chunks {
+        match entries.iter_mut().find(|entry| entry.chunk_type == chunk_type) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.total_bytes += len;
+            }
+            None => entries.push(ChunkSummaryEntry {
+                chunk_type,
+                count: 1,
+                total_bytes: len,
+            }),
+        }
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.total_bytes));
+    entries
+}
+
+/// Prints `entries` as a table with a totals row, or as one record per type
+/// in the `json` and `csv` formats
+fn print_summary_table(entries: &[ChunkSummaryEntry], args: &PrintArgs) -> Result<()> {
+    let file = args.file_path.display().to_string();
+    match args.format {
+        OutputFormat::Human => {
+            let count: usize = entries.iter().map(|entry| entry.count).sum();
+            let total: usize = entries.iter().map(|entry| entry.total_bytes).sum();
+            let percent = |bytes: usize| match total {
+                0 => 0.0,
+                total => bytes as f64 * 100.0 / total as f64,
+            };
+            println!("{:<6} {:>6} {:>12} {:>10}", "TYPE", "COUNT", "TOTAL_BYTES", "%_OF_DATA");
+            for entry in entries {
+                println!(
+                    "{:<6} {:>6} {:>12} {:>9.1}%",
+                    entry.chunk_type.to_string(),
+                    entry.count,
+                    entry.total_bytes,
+                    percent(entry.total_bytes)
+                );
+            }
+            println!("{}", "-".repeat(37));
+            println!("{:<6} {:>6} {:>12} {:>9.1}%", "total", count, total, percent(total));
+        }
+        OutputFormat::Json => {
+            let objects: Vec<String> = entries
+                .iter()
+                .map(|entry| {
+                    let object = serde_json::json!({
+                        "file": file,
+                        "type": entry.chunk_type.to_string(),
+                        "count": entry.count,
+                        "total_bytes": entry.total_bytes,
+                    });
+                    object.to_string()
+                })
+                .collect();
+            println!("[{}]", objects.join(",\n "));
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record(["file", "type", "count", "total_bytes"])?;
+            for entry in entries {
+                writer.write_record([
+                    file.clone(),
+                    entry.chunk_type.to_string(),
+                    entry.count.to_string(),
+                    entry.total_bytes.to_string(),
+                ])?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The fields `print` lists for one chunk in the `json` and `csv` formats
+struct ChunkRow {
+    index: usize,
+    offset: u64,
+    chunk_type: ChunkType,
+    length: u32,
+    crc: u32,
+}
+
+impl ChunkRow {
+    const CSV_HEADER: [&'static str; 9] = [
+        "file",
+        "index",
+        "offset",
+        "type",
+        "length",
+        "crc_hex",
+        "critical",
+        "public",
+        "safe_to_copy",
+    ];
+
+    fn to_csv(&self, file: &str) -> [String; 9] {
+        [
+            file.to_string(),
+            self.index.to_string(),
+            self.offset.to_string(),
+            self.chunk_type.to_string(),
+            self.length.to_string(),
+            format!("{:08x}", self.crc),
+            self.chunk_type.is_critical().to_string(),
+            self.chunk_type.is_public().to_string(),
+            self.chunk_type.is_safe_to_copy().to_string(),
+        ]
+    }
+
+    fn to_json(&self, file: &str) -> serde_json::Value {
+        serde_json::json!({
+            "file": file,
+            "index": self.index,
+            "offset": self.offset,
+            "type": self.chunk_type.to_string(),
+            "length": self.length,
+            "crc_hex": format!("{:08x}", self.crc),
+            "critical": self.chunk_type.is_critical(),
+            "public": self.chunk_type.is_public(),
+            "safe_to_copy": self.chunk_type.is_safe_to_copy(),
+        })
+    }
+}
+
+/// Prints the `human` form of one chunk, colored as `palette` says. A chunk
+/// whose CRC is wrong gets `crc=BAD` at the end of its first line, so that it
+/// stands out without color too.
+fn print_chunk(
+    chunk: &impl std::fmt::Display,
+    chunk_type: &ChunkType,
+    crc_ok: bool,
+    verbose: bool,
+    palette: Palette,
+) {
+    let mut entry = if verbose {
+        format!("{:#}", chunk)
+    } else {
+        chunk.to_string()
+    };
+    if !crc_ok {
+        let end = entry.find('\n').unwrap_or(entry.len());
+        entry.insert_str(end, "  crc=BAD");
+    }
+    println!("{}", palette.paint_chunk(&entry, chunk_type, crc_ok));
+}
+
+/// Warns when a PNG has `len` bytes after IEND that are not part of the image
+fn warn_trailing_data(len: usize) {
+    if len > 0 {
+        eprintln!("Warning: {}", trailing_data_warning(len));
+    }
+}
+
+fn trailing_data_warning(len: usize) -> String {
+    format!("{} bytes of trailing data after the IEND chunk", len)
+}
+
+/// Writes the raw data of a chunk in a PNG file to another file, or to stdout
+/// when the output file is `-`
+pub fn extract(args: ExtractArgs) -> Result<()> {
+    let png = Png::try_from(&args.file_path)?;
+    let chunk_type = ChunkType::from_str(&args.chunk_type)?;
+    let data = extract_chunk_data(&png, &chunk_type, args.nth.unwrap_or(0))?;
+
+    if args.output_file.as_os_str() == "-" {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&data).context("Commands: Could not write to stdout")?;
+        stdout.flush().context("Commands: Could not write to stdout")
+    } else {
+        fs::write(args.output_file.as_path(), data).context("Commands: Could not write to file")
+    }
+}
+
+/// Returns the data of the `nth` (0-indexed) chunk of type `chunk_type`, untouched
+pub fn extract_chunk_data(png: &Png, chunk_type: &ChunkType, nth: usize) -> Result<Vec<u8>> {
+    png.nth_chunk_of_type(chunk_type, nth)
+        .map(|chunk| chunk.data().to_vec())
+        .ok_or_else(|| anyhow!("No chunk of type {} found at index {}", chunk_type, nth))
+}
+
+/// Changes the type of a chunk in a PNG file, keeping its data, and saves the result
+pub fn rename(args: RenameArgs) -> Result<()> {
+    let mut png = Png::try_from(&args.file_path)?;
+    let old_type = ChunkType::from_str(&args.old_type)?;
+    let new_type = ChunkType::from_str(&args.new_type)?;
+
+    rename_chunks(&mut png, &old_type, &new_type, args.all)?;
+
+    let output_path = match args.output_file {
+        Some(path) => path,
+        None => args.file_path,
+    };
+
+    png.write_to_file_atomic(&output_path)
+}
+
+/// Gives the first chunk of type `old_type` (or all of them when `all` is set)
+/// the type `new_type`, returning how many chunks were renamed. The CRC covers
+/// the type bytes, so each renamed chunk is rebuilt with a fresh CRC.
+pub fn rename_chunks(
+    png: &mut Png,
+    old_type: &ChunkType,
+    new_type: &ChunkType,
+    all: bool,
+) -> Result<usize> {
+    let mut renamed = 0;
+    for chunk in png.chunks_mut() {
+        if chunk.chunk_type() == old_type {
+            *chunk = Chunk::new(*new_type, chunk.data().to_vec());
+            renamed += 1;
+            if !all {
+                break;
+            }
+        }
+    }
+
+    if renamed == 0 {
+        return Err(anyhow!("No chunk of type {} found", old_type));
+    }
+    Ok(renamed)
+}
+
+/// Removes every ancillary chunk not listed in `--keep` from a PNG file and saves the result
+pub fn strip(args: StripArgs) -> Result<()> {
+    let mut png = Png::try_from(&args.file_path)?;
+    let keep = args
+        .keep
+        .iter()
+        .map(|chunk_type| ChunkType::from_str(chunk_type))
+        .collect::<PngResult<Vec<_>>>()?;
+
+    let removed = png.retain_chunks(|chunk| {
+        chunk.chunk_type().is_critical() || keep.contains(chunk.chunk_type())
+    });
+    println!("Removed {} chunk(s)", removed.len());
+    if args.strip_trailing {
+        let trailing = png.take_trailing_data();
+        println!("Removed {} bytes of trailing data", trailing.len());
+    }
+
+    let output_path = match args.output_file {
+        Some(path) => path,
+        None => args.file_path,
+    };
+
+    png.write_to_file_atomic(&output_path)
+}
+
+/// Moves the chunks of a PNG file into an order the PNG spec allows, printing
+/// each chunk that had to move
+pub fn reorder(args: ReorderArgs) -> Result<()> {
+    let mut png = Png::try_from(&args.file_path)?;
+    let reorderings = png.normalize_order();
+    if reorderings.is_empty() {
+        println!("Chunks are already in order");
+        // Nothing to write back in place, but an output file is still expected
+        return match &args.output_file {
+            Some(output_file) => copy_unchanged(&args.file_path, &png, output_file, false),
+            None => Ok(()),
+        };
+    }
+    for reordering in &reorderings {
+        println!("{}", reordering);
+    }
+
+    let output_path = match args.output_file {
+        Some(path) => path,
+        None => args.file_path,
+    };
+
+    png.write_to_file_atomic(&output_path)
+}
+
+/// Checks the CRC of every chunk and the chunk layout of a PNG file, failing
+/// if any problem is found
+pub fn verify(args: VerifyArgs) -> Result<()> {
+    let bars = MultiProgress::new();
+    let check = |path: &Path| verify_file(path, &args, &bars);
+    #[cfg(feature = "parallel")]
+    let reports = verify_files(&args.file_paths, check, args.jobs)?;
+    #[cfg(not(feature = "parallel"))]
+    let reports: Vec<VerifyReport> = args.file_paths.iter().map(|path| check(path)).collect();
+
+    let mut problems = 0;
+    let mut failed_files = 0;
+    for (path, report) in args.file_paths.iter().zip(&reports) {
+        if args.file_paths.len() > 1 {
+            println!("{}:", path.display());
+        }
+        for line in &report.lines {
+            println!("{}", line);
+        }
+        for warning in &report.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        problems += report.problems;
+        failed_files += usize::from(report.problems > 0);
+    }
+
+    match (problems, &args.file_paths[..]) {
+        (0, _) => {
+            println!("No problems found");
+            Ok(())
+        }
+        (_, [path]) => Err(anyhow!("Found {} problem(s) in {}", problems, path.display())),
+        (_, paths) => Err(anyhow!(
+            "Found {} problem(s) in {} of {} files",
+            problems,
+            failed_files,
+            paths.len()
+        )),
+    }
+}
+
+/// What `verify` found in one file, buffered so that files checked in
+/// parallel don't interleave their output
+struct VerifyReport {
+    lines: Vec<String>,
+    warnings: Vec<String>,
+    problems: usize,
+}
+
+/// Checks the files at `paths` with `check` on a pool of `jobs` threads,
+/// returning their reports in the same order as `paths`
+#[cfg(feature = "parallel")]
+fn verify_files(
+    paths: &[std::path::PathBuf],
+    check: impl Fn(&Path) -> VerifyReport + Sync,
+    jobs: Option<usize>,
+) -> Result<Vec<VerifyReport>> {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()
+        .context("Commands: Could not start the thread pool")?;
+    Ok(pool.install(|| paths.par_iter().map(|path| check(path)).collect()))
+}
+
+/// Checks the CRC of every chunk and the chunk layout of the file at `path`,
+/// showing progress in `bars` unless `--quiet` is set. Large files are
+/// memory-mapped unless `--no-mmap` is set, and checked the same way.
+fn verify_file(path: &Path, args: &VerifyArgs, bars: &MultiProgress) -> VerifyReport {
+    let mut report = VerifyReport {
+        lines: Vec::new(),
+        warnings: Vec::new(),
+        problems: 0,
+    };
+    let mut fail = |e: anyhow::Error| {
+        report.lines.push(format!("{:#}", e));
+        report.problems += 1;
+    };
+    // Keep going past broken chunks so that everything wrong gets reported.
+    // CRCs are checked below instead, which also keeps bad chunks in the list.
+    let options = ParseOptions {
+        verify_crc: false,
+        on_error: OnError::Collect,
+    };
+    #[cfg(feature = "mmap")]
+    match map_large_file(path, args.no_mmap) {
+        Result::Ok(Some(mapped)) => {
+            match mapped.view_with(options).map_err(|e| anyhow!(load_error(path)(e))) {
+                Result::Ok((view, errors)) => {
+                    let chunks = view.chunks().iter();
+                    let crcs = chunks.map(|chunk| (*chunk.chunk_type(), chunk.validate_crc()));
+                    let layout = view.validate();
+                    report_chunks(&mut report, crcs, errors, layout, view.trailing_data().len());
+                }
+                Err(e) => fail(e),
+            }
+            return report;
+        }
+        Result::Ok(None) => {}
+        Err(e) => {
+            fail(e);
+            return report;
+        }
+    }
+    match stream_png_with_progress(path, &args.download, options, bars, args.quiet) {
+        Result::Ok((png, errors)) => {
+            let crcs = png.chunks().iter().map(|chunk| (*chunk.chunk_type(), chunk.validate_crc()));
+            let layout = png.validate();
+            report_chunks(&mut report, crcs, errors, layout, png.trailing_data().len());
+        }
+        Err(e) => fail(e),
+    }
+    report
+}
+
+/// Adds to `report` the result of the CRC check of each chunk, the `errors`
+/// collected while parsing, the problems with the chunk `layout` and a warning
+/// for `trailing` bytes after IEND
+fn report_chunks(
+    report: &mut VerifyReport,
+    crcs: impl Iterator<Item = (ChunkType, PngResult<()>)>,
+    errors: Vec<PngError>,
+    layout: std::result::Result<(), Vec<StructureError>>,
+    trailing: usize,
+) {
+    for (idx, (chunk_type, crc)) in crcs.enumerate() {
+        if let Err(error) = crc {
+            report.lines.push(format!("{:>4}  {}  {}", idx, chunk_type, error));
+            report.problems += 1;
+        } else {
+            report.lines.push(format!("{:>4}  {}  ok", idx, chunk_type));
+        }
+    }
+    for error in errors {
+        report.lines.push(format!("{:#}", anyhow::Error::from(error)));
+        report.problems += 1;
+    }
+    if let Err(errors) = layout {
+        report.lines.extend(errors.iter().map(|error| error.to_string()));
+        report.problems += errors.len();
+    }
+    if trailing > 0 {
+        report.warnings.push(trailing_data_warning(trailing));
+    }
+}
+
+/// Runs every `doctor` rule on a PNG file and prints the findings grouped by
+/// severity, failing if there are errors, or warnings with `--strict`
+pub fn doctor(args: DoctorArgs) -> Result<()> {
+    let png = stream_png(&args.file_path, &args.download, Png::from_reader)?;
+    let findings = doctor::diagnose(&png);
+
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    for severity in [Severity::Error, Severity::Warning, Severity::Info] {
+        let group: Vec<_> = findings.iter().filter(|f| f.severity == severity).collect();
+        if group.is_empty() {
+            continue;
+        }
+        println!("{} ({}):", severity, group.len());
+        for finding in group {
+            println!("  {}", finding);
+        }
+    }
+
+    if errors > 0 || (args.strict && warnings > 0) {
+        return Err(anyhow!(
+            "Found {} error(s) and {} warning(s) in {}",
+            errors,
+            warnings,
+            args.file_path.display()
+        ));
+    }
+    if findings.is_empty() {
+        println!("No problems found");
+    }
+    Ok(())
+}
+
+/// Hides a message in, or reads it back from, the least significant bits of
+/// the image samples of a PNG file
+pub fn steg(args: StegArgs) -> Result<()> {
+    match args.command {
+        StegCommands::Encode(args) => {
+            let mut png = Png::try_from(&args.file_path)?;
+            steg::embed(&mut png, args.message.as_bytes())?;
+            let output_path = args.output_file.unwrap_or(args.file_path);
+            png.write_to_file_atomic(&output_path)
+        }
+        StegCommands::Decode(args) => {
+            let png = Png::try_from(&args.file_path)?;
+            let message = String::from_utf8(steg::extract(&png)?)
+                .context("Commands: Hidden message is not valid UTF-8")?;
+            println!("{}", message);
+            Ok(())
+        }
+    }
+}
+
+/// Reports the largest single chunk, how much fits in `--budget` bytes of
+/// `--chunk-size` chunks, the data already in custom chunks and how much
+/// `steg encode` can hide
+pub fn capacity(args: CapacityArgs) -> Result<()> {
+    let png = Png::try_from(&args.file_path)?;
+    let chunked = Png::chunked_capacity(args.budget, args.chunk_size);
+    let custom = png.custom_data_len();
+    let lsb = steg::capacity(&png);
+    let max_chunk = pngme::chunk::MAX_LENGTH;
+
+    match args.format {
+        OutputFormat::Human => {
+            println!("Single chunk: {} bytes", max_chunk);
+            println!(
+                "Chunks of {} bytes: {} bytes in a budget of {} bytes",
+                args.chunk_size, chunked, args.budget
+            );
+            println!("Already in custom chunks: {} bytes", custom);
+            match &lsb {
+                Result::Ok(lsb) => println!("Sample LSBs: {} bytes", lsb),
+                Err(error) => println!("Sample LSBs: none, {:#}", error),
+            }
+        }
+        OutputFormat::Json => {
+            let object = serde_json::json!({
+                "file": args.file_path.display().to_string(),
+                "max_chunk": max_chunk,
+                "chunk_size": args.chunk_size,
+                "budget": args.budget,
+                "chunked": chunked,
+                "custom": custom,
+                "lsb": lsb.ok(),
+            });
+            println!("{}", object);
+        }
+        OutputFormat::Csv => {
+            let lsb = lsb.map_or(String::new(), |lsb| lsb.to_string());
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record([
+                "file",
+                "max_chunk",
+                "chunk_size",
+                "budget",
+                "chunked",
+                "custom",
+                "lsb",
+            ])?;
+            writer.write_record([
+                args.file_path.display().to_string(),
+                max_chunk.to_string(),
+                args.chunk_size.to_string(),
+                args.budget.to_string(),
+                chunked.to_string(),
+                custom.to_string(),
+                lsb,
+            ])?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a solid color image of the given size
+pub fn generate(args: GenerateArgs) -> Result<()> {
+    let png = Png::generate(args.width, args.height, args.color)?;
+    png.write_to_file(&args.output_file)?;
+    Ok(())
+}
+
+/// Prints a completion script for the given shell to stdout
+pub fn completions(args: CompletionsArgs) -> Result<()> {
+    let mut command = PngMeArgs::command();
+    clap_complete::generate(args.shell, &mut command, "pngme", &mut io::stdout());
+    Ok(())
+}
+
+/// Prints the image header fields stored in the IHDR chunk of a PNG file
+pub fn info(args: InfoArgs) -> Result<()> {
+    let png = Png::try_from(&args.file_path)?;
+    let chunk = png
+        .chunk_by_type("IHDR")
+        .ok_or_else(|| anyhow!("Commands: {} has no IHDR chunk", args.file_path.display()))?;
+    let ihdr = parse_ihdr(chunk)?;
+
+    let format = if args.json { OutputFormat::Json } else { args.format };
+    match format {
+        OutputFormat::Human => println!("{}", ihdr),
+        OutputFormat::Json => println!("{}", ihdr.to_json()),
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(io::stdout().lock());
+            writer.write_record([
+                "file",
+                "width",
+                "height",
+                "bit_depth",
+                "color_type",
+                "compression_method",
+                "filter_method",
+                "interlace_method",
+            ])?;
+            writer.write_record([
+                args.file_path.display().to_string(),
+                ihdr.width.to_string(),
+                ihdr.height.to_string(),
+                ihdr.bit_depth.to_string(),
+                ihdr.color_type.to_string(),
+                ihdr.compression_method.to_string(),
+                ihdr.filter_method.to_string(),
+                ihdr.interlace_method.to_string(),
+            ])?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies chunks from one PNG file into another, before its IEND chunk, and
+/// saves the result
+pub fn merge(args: MergeArgs) -> Result<()> {
+    let src = Png::try_from(&args.src)?;
+    let mut dst = Png::try_from(&args.dst)?;
+
+    merge_from(&mut dst, &src, args.critical, args.overwrite);
+    check_structure(&dst, false)?;
+
+    let output_path = match args.output {
+        Some(path) => path,
+        None => args.dst,
+    };
+    dst.write_to_file_atomic(&output_path)
+}
+
+/// Adds the ancillary chunks of `src` to `dst`, along with the critical ones
+/// other than IHDR and IEND when `include_critical` is set. Chunk types `dst`
+/// already has are skipped with a warning, or replace the existing chunks when
+/// `overwrite` is set. Chunks that the spec places before PLTE or IDAT, such
+/// as iCCP or PLTE itself, are moved there, and the rest go before IEND.
+/// Returns how many chunks were added.
+pub fn merge_from(dst: &mut Png, src: &Png, include_critical: bool, overwrite: bool) -> usize {
+    let existing: HashSet<ChunkType> =
+        dst.chunks().iter().map(|chunk| *chunk.chunk_type()).collect();
+    let mut warned = HashSet::new();
+    let mut added = 0;
+
+    for chunk in src.chunks() {
+        let chunk_type = chunk.chunk_type();
+        if chunk.is_ihdr() || chunk.is_iend() {
+            continue;
+        }
+        if chunk_type.is_critical() && !include_critical {
+            continue;
+        }
+        if existing.contains(chunk_type) {
+            let first = warned.insert(*chunk_type);
+            if !overwrite {
+                if first {
+                    eprintln!(
+                        "Warning: Skipping {} chunks, the destination already has one",
+                        chunk_type
+                    );
+                }
+                continue;
+            }
+            if first {
+                eprintln!("Warning: Replacing the {} chunks in the destination", chunk_type);
+                dst.remove_all_of_type(chunk_type);
+            }
+        }
+
+        let position = dst.position_of(&ChunkType::IEND).unwrap_or(dst.len());
+        dst.insert_chunk(position, chunk.clone())
+            .expect("Commands: The IEND position is in bounds");
+        added += 1;
+    }
+    dst.normalize_order();
+    added
+}
+
+/// Writes every chunk of a PNG file to an editable JSON file
+#[cfg(feature = "json")]
+pub fn export(args: ExportArgs) -> Result<()> {
+    let png = Png::try_from(&args.file_path)?;
+    let json = PngDocument::from_png(&png).to_json()?;
+
+    fs::write(&args.output_file, json).with_context(|| {
+        format!("Commands: Could not write to {}", args.output_file.display())
+    })
+}
+
+/// Rebuilds a PNG file from the JSON written by `export`
+#[cfg(feature = "json")]
+pub fn import(args: ImportArgs) -> Result<()> {
+    let json = fs::read_to_string(&args.input_file).with_context(|| {
+        format!("Commands: Failed to read {}", args.input_file.display())
+    })?;
+    let png = PngDocument::from_json(&json)?.to_png(args.trust_crc)?;
+    check_structure(&png, false)?;
+
+    png.write_to_file(&args.output_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testing_png(payload: &[u8]) -> Png {
+        let chunks = vec![
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), b"I am the first chunk".to_vec()),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"not this one".to_vec()),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), payload.to_vec()),
+        ];
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_chunk_summary() {
+        let text = ChunkType::from_str("tEXt").unwrap();
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::IHDR, vec![0; 13]),
+            Chunk::new(text, b"Title\0Dice".to_vec()),
+            Chunk::new(text, b"Author\0Someone".to_vec()),
+        ]);
+
+        // `super::` because the merge tests below have a helper of the same name
+        let summary = super::chunk_summary(&png);
+        assert_eq!(
+            summary,
+            [
+                ChunkSummaryEntry {
+                    chunk_type: text,
+                    count: 2,
+                    total_bytes: 24,
+                },
+                ChunkSummaryEntry {
+                    chunk_type: ChunkType::IHDR,
+                    count: 1,
+                    total_bytes: 13,
+                },
+            ]
+        );
+        assert!(super::chunk_summary(&Png::from_chunks(Vec::new())).is_empty());
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        assert_eq!(count_invalid_utf8(b"plain ascii"), 0);
+        assert_eq!(count_invalid_utf8("caf\u{e9} \u{FFFD}".as_bytes()), 0);
+        assert_eq!(count_invalid_utf8(b"ok \xFF then \xE2\x82 and \xC3\xA9"), 2);
+        assert_eq!(count_invalid_utf8(b"\xFF\xFE"), 2);
+    }
+
+    fn merge_png(chunks: &[(&str, &str)]) -> Png {
+        Png::from_chunks(
+            chunks
+                .iter()
+                .map(|(chunk_type, data)| {
+                    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.as_bytes().to_vec())
+                })
+                .collect(),
+        )
+    }
+
+    fn chunk_summary(png: &Png) -> Vec<(String, String)> {
+        png.chunks()
+            .iter()
+            .map(|chunk| (chunk.chunk_type().to_string(), chunk.data_as_string().unwrap()))
+            .collect()
+    }
+
+    fn merge_src() -> Png {
+        merge_png(&[
+            ("IHDR", "src"),
+            ("iCCP", "profile"),
+            ("PLTE", "palette"),
+            ("tEXt", "src text"),
+            ("IDAT", "src"),
+            ("IEND", ""),
+        ])
+    }
+
+    fn merge_dst() -> Png {
+        merge_png(&[("IHDR", "dst"), ("tEXt", "dst text"), ("IDAT", "dst"), ("IEND", "")])
+    }
+
+    #[test]
+    fn test_merge_ancillary_chunks() {
+        let mut dst = merge_dst();
+
+        assert_eq!(merge_from(&mut dst, &merge_src(), false, false), 1);
+        let types: Vec<String> = chunk_summary(&dst).into_iter().map(|(t, _)| t).collect();
+        assert_eq!(types, ["IHDR", "tEXt", "iCCP", "IDAT", "IEND"]);
+        assert_eq!(chunk_summary(&dst)[1].1, "dst text");
+        assert!(dst.validate().is_ok());
+    }
+
+    #[test]
+    fn test_merge_critical_chunks() {
+        let mut dst = merge_dst();
+
+        // IDAT is skipped since the destination already has one
+        assert_eq!(merge_from(&mut dst, &merge_src(), true, false), 2);
+        let types: Vec<String> = chunk_summary(&dst).into_iter().map(|(t, _)| t).collect();
+        assert_eq!(types, ["IHDR", "tEXt", "iCCP", "PLTE", "IDAT", "IEND"]);
+        assert!(dst.validate().is_ok());
+    }
+
+    #[test]
+    fn test_merge_overwrite() {
+        let mut dst = merge_dst();
+
+        assert_eq!(merge_from(&mut dst, &merge_src(), false, true), 2);
+        let summary = chunk_summary(&dst);
+        let texts: Vec<&(String, String)> = summary.iter().filter(|(t, _)| t == "tEXt").collect();
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].1, "src text");
+        assert_eq!(summary.last().unwrap().0, "IEND");
+        assert!(dst.validate().is_ok());
+    }
+
+    #[test]
+    fn test_encode_batch() {
+        let csv = "ruSt,first message\n\nraIn,\"second, \"\"quoted\"\"\"\nrOSt,third\n";
+        let mut png = Png::from_chunks(vec![Chunk::new(
+            ChunkType::from_str("IEND").unwrap(),
+            vec![],
+        )]);
+
+        encode_batch(&mut png, parse_batch(csv).unwrap()).unwrap();
+
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["ruSt", "raIn", "rOSt", "IEND"]);
+        assert_eq!(png.chunks()[0].data(), b"first message");
+        assert_eq!(png.chunks()[1].data(), b"second, \"quoted\"");
+        assert_eq!(png.chunks()[2].data(), b"third");
+    }
+
+    #[test]
+    fn test_parse_batch_errors_name_the_row() {
+        let error = parse_batch("ruSt,fine\nno separator\n").unwrap_err();
+        assert!(error.to_string().contains("Row 2 of the batch file has 1 fields"), "{}", error);
+
+        let error = parse_batch("ruSt,fine\nruSt,not,quoted\n").unwrap_err();
+        assert!(error.to_string().contains("Row 2 of the batch file has 3 fields"), "{}", error);
+
+        let error = parse_batch("ruSt,fine\nruSt,fine\nru1t,bad type\n").unwrap_err();
+        assert!(error.to_string().contains("Row 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 bytes");
+        assert_eq!(format_size(1023), "1023 bytes");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1258291), "1.2 MiB");
+    }
+
+    #[test]
+    fn test_parse_pairs() {
+        let args = ["ruSt=first".to_string(), "raIn=x=y".to_string()];
+        let pairs = parse_pairs(&args).unwrap();
+        assert_eq!(pairs[0], (ChunkType::from_str("ruSt").unwrap(), b"first".to_vec()));
+        assert_eq!(pairs[1], (ChunkType::from_str("raIn").unwrap(), b"x=y".to_vec()));
+
+        let error = parse_pairs(&["ruSt=fine".to_string(), "ruSt".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("Pair 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_verify_chunk_at() {
+        let path = Path::new("test.png");
+        let png = testing_png(b"payload");
+        let rust = ChunkType::from_str("ruSt").unwrap();
+
+        verify_chunk_at(&png, path, 2, &rust, b"payload").unwrap();
+        // The same message earlier in the file is not the one just written
+        let error = verify_chunk_at(&png, path, 2, &rust, b"not this one").unwrap_err();
+        assert!(error.to_string().contains("does not hold the encoded message"), "{}", error);
+        let error = verify_chunk_at(&png, path, 0, &rust, b"payload").unwrap_err();
+        assert!(error.to_string().contains("has no ruSt chunk at index 0"), "{}", error);
+        assert!(verify_chunk_at(&png, path, 3, &rust, b"payload").is_err());
+    }
+
+    #[test]
+    fn test_verify_chunk_in_file() {
+        let path = std::env::temp_dir().join(format!("pngme-verify-{}.png", std::process::id()));
+        testing_png(b"payload").write_to_file(&path).unwrap();
+        let rust = ChunkType::from_str("ruSt").unwrap();
+
+        verify_chunk_in_file(&path, &rust, b"payload").unwrap();
+        verify_chunk_in_file(&path, &rust, b"not this one").unwrap();
+        let error = verify_chunk_in_file(&path, &rust, b"missing").unwrap_err();
+        assert!(error.to_string().contains("holds the encoded message"), "{}", error);
+        let text = ChunkType::from_str("tEXt").unwrap();
+        let error = verify_chunk_in_file(&path, &text, b"payload").unwrap_err();
+        assert!(error.to_string().contains("has no tEXt chunk"), "{}", error);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_verified() {
+        let path = std::env::temp_dir().join(format!("pngme-write-{}.png", std::process::id()));
+        let temp_path = temp_sibling(&path).unwrap();
+        let png = testing_png(b"payload");
+        let pairs = [(ChunkType::from_str("ruSt").unwrap(), b"payload".to_vec())];
+
+        write_verified(&png, &path, &pairs, &[2], |png, path| png.write_to_file(path)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+
+        // A writer that flips a bit in the last chunk's data
+        let flip = |png: &Png, path: &Path| {
+            let mut bytes = png.as_bytes();
+            let len = bytes.len();
+            bytes[len - 6] ^= 1;
+            Ok(fs::write(path, bytes)?)
+        };
+        let error = write_verified(&png, &path, &pairs, &[2], flip).unwrap_err();
+        let offset = png.serialized_size() - 6;
+        assert!(error.to_string().ends_with(&format!("at byte {}", offset)), "{}", error);
+        // The file from before is still there
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        assert!(!temp_path.exists());
+
+        let truncate = |png: &Png, path: &Path| Ok(fs::write(path, &png.as_bytes()[..20])?);
+        let error = write_verified(&png, &path, &pairs, &[2], truncate).unwrap_err();
+        assert!(error.to_string().contains("is 20 bytes long"), "{}", error);
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        assert!(!temp_path.exists());
+
+        // The written bytes are right, but the pair is not where it was added
+        let error = write_verified(&png, &path, &pairs, &[1], |png, path| png.write_to_file(path))
+            .unwrap_err();
+        assert!(error.to_string().contains("at index 1"), "{}", error);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replace_if_ok() {
+        let path = std::env::temp_dir().join(format!("pngme-replace-{}.png", std::process::id()));
+        let temp_path = temp_sibling(&path).unwrap();
+        fs::write(&path, b"original").unwrap();
+
+        fs::write(&temp_path, b"rejected").unwrap();
+        assert!(replace_if_ok(&temp_path, &path, Err(anyhow!("bad write"))).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+        assert!(!temp_path.exists());
+
+        fs::write(&temp_path, b"accepted").unwrap();
+        replace_if_ok(&temp_path, &path, Ok(())).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"accepted");
+        assert!(!temp_path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_extract_binary_payload() {
+        let payload: Vec<u8> = (0..=255).chain([0, 0xff, b'\n', b'\r']).collect();
+        let png = testing_png(&payload);
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+
+        let data = extract_chunk_data(&png, &chunk_type, 1).unwrap();
+
+        assert_eq!(data, payload);
+    }
+
+    #[test]
+    fn test_extract_first_by_default() {
+        let png = testing_png(&[1, 2, 3]);
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+
+        let data = extract_chunk_data(&png, &chunk_type, 0).unwrap();
+
+        assert_eq!(data, b"not this one");
+    }
+
+    #[test]
+    fn test_extract_missing_chunk() {
+        let png = testing_png(&[1, 2, 3]);
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+
+        assert!(extract_chunk_data(&png, &chunk_type, 2).is_err());
+        assert!(extract_chunk_data(&png, &ChunkType::from_str("NoPe").unwrap(), 0).is_err());
+    }
+
+    #[test]
+    fn test_rename_first_chunk() {
+        let mut png = testing_png(&[1, 2, 3]);
+        let old_type = ChunkType::from_str("ruSt").unwrap();
+        let new_type = ChunkType::from_str("ruSX").unwrap();
+        let original = png.chunks()[1].clone();
+
+        let renamed = rename_chunks(&mut png, &old_type, &new_type, false).unwrap();
+
+        assert_eq!(renamed, 1);
+        let chunk = &png.chunks()[1];
+        assert_eq!(chunk.chunk_type(), &new_type);
+        assert_eq!(chunk.data(), original.data());
+        assert_ne!(chunk.crc(), original.crc());
+        assert_eq!(png.chunks()[2].chunk_type(), &old_type);
+    }
+
+    #[test]
+    fn test_rename_all_chunks() {
+        let mut png = testing_png(&[1, 2, 3]);
+        let old_type = ChunkType::from_str("ruSt").unwrap();
+        let new_type = ChunkType::from_str("ruSX").unwrap();
+
+        let renamed = rename_chunks(&mut png, &old_type, &new_type, true).unwrap();
+
+        assert_eq!(renamed, 2);
+        assert_eq!(png.chunks()[2].chunk_type(), &new_type);
+        assert_eq!(png.chunks()[2].data(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_rename_missing_chunk() {
+        let mut png = testing_png(&[1, 2, 3]);
+        let old_type = ChunkType::from_str("NoPe").unwrap();
+        let new_type = ChunkType::from_str("ruSX").unwrap();
+
+        assert!(rename_chunks(&mut png, &old_type, &new_type, false).is_err());
+    }
+}
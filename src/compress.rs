@@ -0,0 +1,259 @@
+//! Compression for hidden messages, used by `encode --compress` and
+//! `decode --decompress`.
+//!
+//! Compressed data starts with a 4 byte header, the magic bytes `PMZ` and a
+//! method byte, so that decoding can tell which method to use:
+//!
+//! ```text
+//! "PMZ" || method (1 byte) || compressed message
+//! ```
+//!
+//! zlib is always available. Zstandard needs the `zstd` feature.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+
+use crate::Result;
+
+/// The bytes every compressed message starts with, before the method byte
+pub const MAGIC: [u8; 3] = *b"PMZ";
+
+/// The largest message decompression will produce, so that a small chunk
+/// cannot expand to fill memory
+pub const MAX_DECOMPRESSED_LEN: usize = 256 << 20;
+
+/// A compression method, stored in the header as its method byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Zlib,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Method {
+    /// The byte that identifies this method in the header
+    pub fn byte(&self) -> u8 {
+        match self {
+            Method::Zlib => 0,
+            #[cfg(feature = "zstd")]
+            Method::Zstd => 1,
+        }
+    }
+
+    /// The method `byte` identifies, if this build supports it
+    pub fn from_byte(byte: u8) -> Option<Method> {
+        match byte {
+            0 => Some(Method::Zlib),
+            #[cfg(feature = "zstd")]
+            1 => Some(Method::Zstd),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Zlib => "zlib",
+            #[cfg(feature = "zstd")]
+            Method::Zstd => "zstd",
+        }
+    }
+}
+
+/// A method and an optional level, parsed from `METHOD[:LEVEL]` such as
+/// `zlib`, `zlib:9` or `zstd:19`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    pub method: Method,
+    pub level: Option<i32>,
+}
+
+impl FromStr for Compression {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => {
+                let level = level
+                    .parse()
+                    .map_err(|_| anyhow!("Compress: Invalid compression level {:?}", level))?;
+                (name, Some(level))
+            }
+            None => (s, None),
+        };
+        let method = match name {
+            "zlib" => Method::Zlib,
+            #[cfg(feature = "zstd")]
+            "zstd" => Method::Zstd,
+            #[cfg(not(feature = "zstd"))]
+            "zstd" => bail!("Compress: zstd needs pngme to be built with the `zstd` feature"),
+            _ => bail!("Compress: Unknown compression method {:?}, expected zlib or zstd", name),
+        };
+        if let (Method::Zlib, Some(level)) = (method, level) {
+            if !(0..=9).contains(&level) {
+                bail!("Compress: zlib levels go from 0 to 9, got {}", level);
+            }
+        }
+        Ok(Compression { method, level })
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Some(level) => write!(f, "{}:{}", self.method.name(), level),
+            None => write!(f, "{}", self.method.name()),
+        }
+    }
+}
+
+/// Compresses `message` and puts the header in front of it
+pub fn compress(message: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let mut data = MAGIC.to_vec();
+    data.push(compression.method.byte());
+    match compression.method {
+        Method::Zlib => {
+            let level = match compression.level {
+                Some(level) => flate2::Compression::new(level as u32),
+                None => flate2::Compression::default(),
+            };
+            let mut encoder = ZlibEncoder::new(data, level);
+            encoder.write_all(message)?;
+            Ok(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Method::Zstd => {
+            let level = compression.level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+            let mut encoder = zstd::Encoder::new(data, level)?;
+            encoder.write_all(message)?;
+            Ok(encoder.finish()?)
+        }
+    }
+}
+
+/// Reads the header of data written by `compress` and decompresses the rest,
+/// failing if the result would be longer than `MAX_DECOMPRESSED_LEN`
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() < MAGIC.len() + 1 || data[..MAGIC.len()] != MAGIC {
+        bail!("Compress: Data does not start with a compression header");
+    }
+    let byte = data[MAGIC.len()];
+    let method = Method::from_byte(byte).with_context(|| {
+        format!(
+            "Compress: Unknown compression method {}, this pngme may have been built without \
+             the feature for it, such as `zstd`",
+            byte
+        )
+    })?;
+    let compressed = &data[MAGIC.len() + 1..];
+    match method {
+        Method::Zlib => read_capped(ZlibDecoder::new(compressed), method, MAX_DECOMPRESSED_LEN),
+        #[cfg(feature = "zstd")]
+        Method::Zstd => {
+            read_capped(zstd::Decoder::new(compressed)?, method, MAX_DECOMPRESSED_LEN)
+        }
+    }
+}
+
+/// Reads all of `decoder`, failing once it produces more than `limit` bytes
+fn read_capped(decoder: impl Read, method: Method, limit: usize) -> Result<Vec<u8>> {
+    let mut message = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut message)
+        .with_context(|| format!("Compress: Could not decompress {} data", method.name()))?;
+    if message.len() > limit {
+        bail!("Compress: Message decompresses to more than {} bytes", limit);
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> Vec<u8> {
+        b"{\"key\": \"value\", \"list\": [1, 2, 3]}\n".repeat(100)
+    }
+
+    #[test]
+    fn test_zlib_round_trip() {
+        for spec in ["zlib", "zlib:0", "zlib:9"] {
+            let compression = Compression::from_str(spec).unwrap();
+            assert_eq!(compression.to_string(), spec);
+            let data = compress(&message(), compression).unwrap();
+            assert_eq!(data[..4], *b"PMZ\0");
+            assert_eq!(decompress(&data).unwrap(), message());
+        }
+        let data = compress(&message(), Compression::from_str("zlib").unwrap()).unwrap();
+        assert!(data.len() < message().len() / 10);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        for spec in ["zstd", "zstd:1", "zstd:19"] {
+            let compression = Compression::from_str(spec).unwrap();
+            let data = compress(&message(), compression).unwrap();
+            assert_eq!(data[..4], *b"PMZ\x01");
+            assert_eq!(decompress(&data).unwrap(), message());
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zlib_still_decodes_with_zstd() {
+        // Written by a build without the zstd feature
+        let mut data = b"PMZ\0".to_vec();
+        let mut encoder = ZlibEncoder::new(&mut data, flate2::Compression::default());
+        encoder.write_all(b"old message").unwrap();
+        encoder.finish().unwrap();
+
+        assert_eq!(decompress(&data).unwrap(), b"old message");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Compression::from_str("gzip").is_err());
+        assert!(Compression::from_str("zlib:fast").is_err());
+        assert!(Compression::from_str("zlib:10").is_err());
+        #[cfg(not(feature = "zstd"))]
+        assert!(Compression::from_str("zstd")
+            .unwrap_err()
+            .to_string()
+            .contains("`zstd` feature"));
+    }
+
+    #[test]
+    fn test_unknown_method() {
+        let error = decompress(b"PMZ\x07data").unwrap_err();
+        assert!(error.to_string().contains("Unknown compression method 7"), "{}", error);
+        assert!(error.to_string().contains("`zstd`"), "{}", error);
+        assert!(decompress(b"plain message").is_err());
+    }
+
+    #[test]
+    fn test_decompress_is_capped() {
+        let compression = Compression::from_str("zlib").unwrap();
+        let data = compress(&[0; 1001], compression).unwrap();
+        let decoder = || ZlibDecoder::new(&data[4..]);
+
+        assert_eq!(read_capped(decoder(), Method::Zlib, 1001).unwrap().len(), 1001);
+        let error = read_capped(decoder(), Method::Zlib, 1000).unwrap_err();
+        assert_eq!(error.to_string(), "Compress: Message decompresses to more than 1000 bytes");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_decompress_is_capped() {
+        let compression = Compression::from_str("zstd").unwrap();
+        let data = compress(&[0; 1001], compression).unwrap();
+        let decoder = zstd::Decoder::new(&data[4..]).unwrap();
+
+        assert!(read_capped(decoder, Method::Zstd, 1000).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Password-based encryption for hidden messages, used by `encode --password`
+//! and `decode --decrypt`.
+//!
+//! The password is stretched into a 256-bit key with PBKDF2-HMAC-SHA256 over
+//! a random salt, and the message is sealed with AES-256-GCM under a random
+//! nonce. An encrypted chunk's data is laid out as
+//!
+//! ```text
+//! salt (16 bytes) || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! ```
+//!
+//! so that it holds everything needed to decrypt it besides the password.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail};
+use hmac::Hmac;
+use sha2::{Digest, Sha256};
+
+use crate::Result;
+
+/// Length of the random salt at the start of encrypted data
+pub const SALT_LEN: usize = 16;
+
+/// Length of the AES-GCM nonce that follows the salt
+pub const NONCE_LEN: usize = 12;
+
+/// Length of the authentication tag at the end of encrypted data
+pub const TAG_LEN: usize = 16;
+
+/// Number of PBKDF2 iterations used to derive a key from a password
+pub const PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Derives the AES-256 key for `password` and `salt`
+fn derive_key(password: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key = Key::<Aes256Gcm>::default();
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, PBKDF2_ROUNDS, &mut key)
+        .expect("Crypto: HMAC accepts keys of any length");
+    key
+}
+
+/// Encrypts `message` with a key derived from `password`, returning the salt,
+/// nonce and ciphertext in the layout described above
+pub fn encrypt_with_password(message: &[u8], password: &str) -> Result<Vec<u8>> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    encrypt_with_salt_and_nonce(message, password, &salt, &nonce.into())
+}
+
+/// Same as `encrypt_with_password`, but with a given salt and nonce instead of
+/// random ones, for output that must be reproducible. Encrypting two
+/// different messages with the same password, salt and nonce breaks AES-GCM,
+/// so the nonce must be unique to the message.
+pub fn encrypt_with_salt_and_nonce(
+    message: &[u8],
+    password: &str,
+    salt: &[u8; SALT_LEN],
+    nonce: &[u8; NONCE_LEN],
+) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&derive_key(password, salt));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(nonce), message)
+        .map_err(|_| anyhow!("Crypto: Failed to encrypt the message"))?;
+    Ok([&salt[..], &nonce[..], &ciphertext].concat())
+}
+
+/// Same as `encrypt_with_password`, but the salt and nonce are derived from
+/// `seed` and the message, so encrypting the same message with the same seed
+/// always gives the same output. That shows when two chunks hold the same
+/// message, so this is only meant for reproducible builds.
+pub fn encrypt_with_seed(message: &[u8], password: &str, seed: u64) -> Result<Vec<u8>> {
+    let digest = Sha256::new().chain_update(seed.to_be_bytes()).chain_update(message).finalize();
+    let (salt, rest) = digest.split_at(SALT_LEN);
+    let nonce = &rest[..NONCE_LEN];
+    encrypt_with_salt_and_nonce(
+        message,
+        password,
+        salt.try_into().expect("Crypto: SHA-256 is long enough for a salt"),
+        nonce.try_into().expect("Crypto: SHA-256 is long enough for a nonce"),
+    )
+}
+
+/// Decrypts data written by `encrypt_with_password`, failing if `password` is
+/// wrong or the data was changed
+pub fn decrypt_with_password(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    if data.len() < SALT_LEN + NONCE_LEN + TAG_LEN {
+        bail!(
+            "Crypto: Data is {} bytes long, too short to be encrypted (at least {} bytes)",
+            data.len(),
+            SALT_LEN + NONCE_LEN + TAG_LEN
+        );
+    }
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(&derive_key(password, salt));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Crypto: Wrong password, or the data is not an encrypted message"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = encrypt_with_password(b"This is where your secret message will be!", "hunter2")
+            .unwrap();
+        assert_eq!(
+            data.len(),
+            SALT_LEN + NONCE_LEN + b"This is where your secret message will be!".len() + TAG_LEN
+        );
+        assert_eq!(
+            decrypt_with_password(&data, "hunter2").unwrap(),
+            b"This is where your secret message will be!"
+        );
+    }
+
+    #[test]
+    fn test_wrong_password_fails() {
+        let data = encrypt_with_password(b"secret", "hunter2").unwrap();
+        let error = decrypt_with_password(&data, "hunter3").unwrap_err();
+        assert!(error.to_string().contains("Wrong password"), "{}", error);
+    }
+
+    #[test]
+    fn test_tampered_data_fails() {
+        let mut data = encrypt_with_password(b"secret", "hunter2").unwrap();
+        *data.last_mut().unwrap() ^= 1;
+        assert!(decrypt_with_password(&data, "hunter2").is_err());
+    }
+
+    #[test]
+    fn test_salt_and_nonce_are_random() {
+        let first = encrypt_with_password(b"secret", "hunter2").unwrap();
+        let second = encrypt_with_password(b"secret", "hunter2").unwrap();
+        assert_ne!(first[..SALT_LEN], second[..SALT_LEN]);
+        assert_ne!(first[SALT_LEN..SALT_LEN + NONCE_LEN], second[SALT_LEN..SALT_LEN + NONCE_LEN]);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_given_salt_and_nonce() {
+        let (salt, nonce) = ([1; SALT_LEN], [2; NONCE_LEN]);
+        let first = encrypt_with_salt_and_nonce(b"secret", "hunter2", &salt, &nonce).unwrap();
+        let second = encrypt_with_salt_and_nonce(b"secret", "hunter2", &salt, &nonce).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first[..SALT_LEN], salt);
+        assert_eq!(first[SALT_LEN..SALT_LEN + NONCE_LEN], nonce);
+        assert_eq!(decrypt_with_password(&first, "hunter2").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_encrypt_with_seed() {
+        let first = encrypt_with_seed(b"secret", "hunter2", 7).unwrap();
+        assert_eq!(encrypt_with_seed(b"secret", "hunter2", 7).unwrap(), first);
+        assert_ne!(encrypt_with_seed(b"secret", "hunter2", 8).unwrap(), first);
+        let other = encrypt_with_seed(b"secreT", "hunter2", 7).unwrap();
+        assert_ne!(other[SALT_LEN..SALT_LEN + NONCE_LEN], first[SALT_LEN..SALT_LEN + NONCE_LEN]);
+        assert_eq!(decrypt_with_password(&first, "hunter2").unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_short_data_fails() {
+        let error = decrypt_with_password(&[0; SALT_LEN + NONCE_LEN], "hunter2").unwrap_err();
+        assert!(error.to_string().contains("too short"), "{}", error);
+    }
+}
@@ -0,0 +1,419 @@
+//! Checks for the ways real-world PNGs bend the spec, beyond what
+//! `Png::validate` covers. Each rule is a plain function over a `Png`, listed
+//! in `RULES`, so that new ones are easy to add and to test on their own.
+
+use std::fmt;
+
+use crate::chunk::Chunk;
+use crate::png::{Png, AFTER_PLTE, BEFORE_IDAT, BEFORE_PLTE};
+use crate::standard_chunks::{decode_latin1, encode_keyword};
+
+/// How serious a `Finding` is, most serious first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Decoders may reject the image
+    Error,
+    /// The image breaks a rule most decoders let slide
+    Warning,
+    /// Allowed by the spec, but worth knowing about
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One problem found by a rule, with the index of the chunk it is about when
+/// there is one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "[{}] chunk {}: {}", self.rule, index, self.message),
+            None => write!(f, "[{}] {}", self.rule, self.message),
+        }
+    }
+}
+
+/// A named check run by `diagnose`
+pub struct Rule {
+    pub name: &'static str,
+    pub check: fn(&Png) -> Vec<Finding>,
+}
+
+/// Every rule `diagnose` runs, in order
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "layout",
+        check: layout,
+    },
+    Rule {
+        name: "unknown-critical",
+        check: unknown_critical,
+    },
+    Rule {
+        name: "duplicate",
+        check: duplicate,
+    },
+    Rule {
+        name: "chunk-order",
+        check: chunk_order,
+    },
+    Rule {
+        name: "text-keyword",
+        check: text_keyword,
+    },
+    Rule {
+        name: "trailing-data",
+        check: trailing_data,
+    },
+    Rule {
+        name: "empty-idat",
+        check: empty_idat,
+    },
+    Rule {
+        name: "private-chunk",
+        check: private_chunk,
+    },
+];
+
+/// Runs every rule in `RULES` on `png`, returning the findings sorted by
+/// severity, most serious first
+pub fn diagnose(png: &Png) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = RULES.iter().flat_map(|rule| (rule.check)(png)).collect();
+    findings.sort_by_key(|finding| finding.severity);
+    findings
+}
+
+fn finding(
+    rule: &'static str,
+    severity: Severity,
+    index: Option<usize>,
+    message: String,
+) -> Finding {
+    Finding {
+        rule,
+        severity,
+        index,
+        message,
+    }
+}
+
+/// Chunks of type `name` along with their indexes
+fn chunks_of<'a>(png: &'a Png, name: &'a [u8; 4]) -> impl Iterator<Item = (usize, &'a Chunk)> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(move |(_, chunk)| chunk.chunk_type().as_bytes() == name)
+}
+
+/// The layout rules checked by `Png::validate`, such as IHDR coming first and
+/// the IDAT chunks being consecutive
+pub fn layout(png: &Png) -> Vec<Finding> {
+    match png.validate() {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .into_iter()
+            .map(|error| finding("layout", Severity::Error, error.index, error.rule.name().into()))
+            .collect(),
+    }
+}
+
+/// Critical chunks that no version of the spec defines. Decoders must refuse
+/// images with critical chunks they don't know.
+pub fn unknown_critical(png: &Png) -> Vec<Finding> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            let chunk_type = chunk.chunk_type();
+            chunk_type.is_critical() && !chunk_type.is_known_standard()
+        })
+        .map(|(index, chunk)| {
+            let message = format!("unknown critical chunk type {}", chunk.chunk_type());
+            finding("unknown-critical", Severity::Error, Some(index), message)
+        })
+        .collect()
+}
+
+/// Chunk types the spec allows at most once. IHDR and IEND are left to
+/// `layout`.
+const SINGLE: [&[u8; 4]; 20] = [
+    b"PLTE", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"mDCv", b"cLLI", b"bKGD",
+    b"hIST", b"tRNS", b"pHYs", b"eXIf", b"tIME", b"acTL", b"oFFs", b"pCAL", b"sCAL", b"sTER",
+];
+
+/// Second and later chunks of a type that may only appear once
+pub fn duplicate(png: &Png) -> Vec<Finding> {
+    SINGLE
+        .iter()
+        .flat_map(|&name| chunks_of(png, name).skip(1))
+        .map(|(index, chunk)| {
+            let message = format!("more than one {} chunk", chunk.chunk_type());
+            finding("duplicate", Severity::Warning, Some(index), message)
+        })
+        .collect()
+}
+
+/// Ancillary chunks on the wrong side of PLTE or the first IDAT
+pub fn chunk_order(png: &Png) -> Vec<Finding> {
+    let first = |name: &[u8; 4]| chunks_of(png, name).next().map(|(index, _)| index);
+    let plte = first(b"PLTE");
+    let idat = first(b"IDAT");
+    let mut findings = Vec::new();
+    let mut misplaced = |index: usize, chunk: &Chunk, landmark: &str, should: &str| {
+        let chunk_type = chunk.chunk_type();
+        let message = format!("{} comes after {}, it must come {}", chunk_type, landmark, should);
+        findings.push(finding("chunk-order", Severity::Warning, Some(index), message));
+    };
+
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let name = chunk.chunk_type().as_bytes();
+        let after_idat = idat.is_some_and(|idat| index > idat);
+        if BEFORE_PLTE.contains(&name) && plte.is_some_and(|plte| index > plte) {
+            misplaced(index, chunk, "PLTE", "before it");
+        } else if BEFORE_PLTE.contains(&name) && after_idat {
+            misplaced(index, chunk, "IDAT", "before it");
+        } else if AFTER_PLTE.contains(&name) && after_idat {
+            misplaced(index, chunk, "IDAT", "between PLTE and IDAT");
+        } else if BEFORE_IDAT.contains(&name) && after_idat {
+            misplaced(index, chunk, "IDAT", "before it");
+        }
+    }
+    for (index, chunk) in AFTER_PLTE.iter().flat_map(|&name| chunks_of(png, name)) {
+        if plte.is_some_and(|plte| index < plte) {
+            let chunk_type = chunk.chunk_type();
+            let message = format!("{} comes before PLTE, it must come after it", chunk_type);
+            findings.push(finding("chunk-order", Severity::Warning, Some(index), message));
+        }
+    }
+    findings.sort_by_key(|finding| finding.index);
+    findings
+}
+
+/// `tEXt`, `zTXt` and `iTXt` chunks whose keyword is missing, too long, not
+/// printable Latin-1, or has leading, trailing or consecutive spaces
+pub fn text_keyword(png: &Png) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for name in [b"tEXt", b"zTXt", b"iTXt"] {
+        for (index, chunk) in chunks_of(png, name) {
+            let message = match chunk.data().iter().position(|&b| b == 0) {
+                None => format!("{} chunk has no keyword separator", chunk.chunk_type()),
+                Some(separator) => {
+                    let keyword = decode_latin1(&chunk.data()[..separator]);
+                    if encode_keyword(&keyword).is_ok() {
+                        continue;
+                    }
+                    format!("{} chunk has an invalid keyword {:?}", chunk.chunk_type(), keyword)
+                }
+            };
+            findings.push(finding("text-keyword", Severity::Warning, Some(index), message));
+        }
+    }
+    findings.sort_by_key(|finding| finding.index);
+    findings
+}
+
+/// Bytes after IEND, which decoders ignore and which may hide data
+pub fn trailing_data(png: &Png) -> Vec<Finding> {
+    match png.trailing_data().len() {
+        0 => Vec::new(),
+        len => {
+            let message = format!("{} bytes of data after the IEND chunk", len);
+            vec![finding("trailing-data", Severity::Warning, None, message)]
+        }
+    }
+}
+
+/// IDAT chunks with no data. Allowed, but usually the sign of a buggy encoder.
+pub fn empty_idat(png: &Png) -> Vec<Finding> {
+    chunks_of(png, b"IDAT")
+        .filter(|(_, chunk)| chunk.data_len() == 0)
+        .map(|(index, _)| {
+            finding("empty-idat", Severity::Info, Some(index), "empty IDAT chunk".into())
+        })
+        .collect()
+}
+
+/// Private ancillary chunks, which is where tools like this one hide messages
+pub fn private_chunk(png: &Png) -> Vec<Finding> {
+    png.chunks()
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            let chunk_type = chunk.chunk_type();
+            !chunk_type.is_critical() && !chunk_type.is_public()
+        })
+        .map(|(index, chunk)| {
+            let message = format!("private chunk type {}", chunk.chunk_type());
+            finding("private-chunk", Severity::Info, Some(index), message)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use crate::png::StructureRule;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    /// A PNG with every required chunk in place, and `extra` inserted
+    /// before the first IDAT
+    fn png_with(extra: Vec<Chunk>) -> Png {
+        let mut chunks = vec![chunk("IHDR", &[0; 13])];
+        chunks.extend(extra);
+        chunks.push(chunk("IDAT", b"pixels"));
+        chunks.push(Chunk::new_iend());
+        Png::from_chunks(chunks)
+    }
+
+    /// The rule, severity and index of each finding
+    fn summary(findings: &[Finding]) -> Vec<(&'static str, Severity, Option<usize>)> {
+        findings
+            .iter()
+            .map(|finding| (finding.rule, finding.severity, finding.index))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_png_has_no_findings() {
+        assert!(diagnose(&png_with(vec![chunk("gAMA", &[0; 4])])).is_empty());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut png = png_with(vec![]);
+        png.insert_chunk(2, chunk("tEXt", b"Title\0between")).unwrap();
+        png.insert_chunk(3, chunk("IDAT", b"more")).unwrap();
+
+        let findings = layout(&png);
+        assert_eq!(summary(&findings), [("layout", Severity::Error, Some(2))]);
+        assert_eq!(findings[0].message, StructureRule::IdatNotConsecutive.name());
+    }
+
+    #[test]
+    fn test_unknown_critical() {
+        let png = png_with(vec![chunk("RUST", b"")]);
+        assert_eq!(
+            summary(&unknown_critical(&png)),
+            [("unknown-critical", Severity::Error, Some(1))]
+        );
+        assert!(unknown_critical(&png_with(vec![chunk("PLTE", &[0; 3])])).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let png = png_with(vec![
+            chunk("pHYs", &[0; 9]),
+            chunk("tEXt", b"a\0b"),
+            chunk("tEXt", b"c\0d"),
+            chunk("pHYs", &[0; 9]),
+        ]);
+        let findings = duplicate(&png);
+        assert_eq!(summary(&findings), [("duplicate", Severity::Warning, Some(4))]);
+        assert_eq!(findings[0].message, "more than one pHYs chunk");
+    }
+
+    #[test]
+    fn test_chunk_order() {
+        let png = png_with(vec![
+            chunk("tRNS", &[0]),
+            chunk("PLTE", &[0; 3]),
+            chunk("iCCP", b"profile\0\0"),
+        ]);
+        let findings = chunk_order(&png);
+        assert_eq!(
+            summary(&findings),
+            [
+                ("chunk-order", Severity::Warning, Some(1)),
+                ("chunk-order", Severity::Warning, Some(3)),
+            ]
+        );
+        assert_eq!(findings[0].message, "tRNS comes before PLTE, it must come after it");
+        assert_eq!(findings[1].message, "iCCP comes after PLTE, it must come before it");
+
+        let mut png = png_with(vec![]);
+        png.insert_chunk(2, chunk("pHYs", &[0; 9])).unwrap();
+        assert_eq!(chunk_order(&png)[0].message, "pHYs comes after IDAT, it must come before it");
+    }
+
+    #[test]
+    fn test_text_keyword() {
+        let png = png_with(vec![
+            chunk("tEXt", b"Title\0fine"),
+            chunk("tEXt", b" Title\0leading space"),
+            chunk("zTXt", b"no separator"),
+            chunk("iTXt", b"\0\0\0\0\0empty keyword"),
+        ]);
+        let messages: Vec<String> = text_keyword(&png).iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "[text-keyword] chunk 2: tEXt chunk has an invalid keyword \" Title\"",
+                "[text-keyword] chunk 3: zTXt chunk has no keyword separator",
+                "[text-keyword] chunk 4: iTXt chunk has an invalid keyword \"\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut png = png_with(vec![]);
+        assert!(trailing_data(&png).is_empty());
+        png.set_trailing_data(b"secret".to_vec());
+        assert_eq!(
+            trailing_data(&png)[0].to_string(),
+            "[trailing-data] 6 bytes of data after the IEND chunk"
+        );
+    }
+
+    #[test]
+    fn test_empty_idat() {
+        let mut png = png_with(vec![]);
+        png.insert_chunk(2, chunk("IDAT", b"")).unwrap();
+        assert_eq!(summary(&empty_idat(&png)), [("empty-idat", Severity::Info, Some(2))]);
+    }
+
+    #[test]
+    fn test_private_chunk() {
+        let png = png_with(vec![chunk("ruSt", b"hidden"), chunk("tEXt", b"a\0b")]);
+        assert_eq!(summary(&private_chunk(&png)), [("private-chunk", Severity::Info, Some(1))]);
+    }
+
+    #[test]
+    fn test_diagnose_sorts_by_severity() {
+        let mut png = png_with(vec![chunk("ruSt", b"hidden"), chunk("RUST", b"")]);
+        png.set_trailing_data(b"secret".to_vec());
+
+        let severities: Vec<Severity> = diagnose(&png).iter().map(|f| f.severity).collect();
+        assert_eq!(severities, [Severity::Error, Severity::Warning, Severity::Info]);
+    }
+
+    #[test]
+    fn test_rule_names_match_findings() {
+        let mut png = png_with(vec![chunk("ruSt", b"")]);
+        png.set_trailing_data(b"secret".to_vec());
+        for rule in RULES {
+            assert!((rule.check)(&png).iter().all(|finding| finding.rule == rule.name));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Reed-Solomon error correction for hidden messages, used by `encode --ecc`
+//! and `decode --ecc`.
+//!
+//! The message is split into blocks that each get their own parity bytes, so
+//! that damage to one part of the chunk does not use up the parity of the
+//! rest. Encoded data is laid out as
+//!
+//! ```text
+//! header (6 bytes) || header parity (6 bytes) || block || block parity || ...
+//! ```
+//!
+//! where the header holds a format version, the number of parity bytes per
+//! block and the message length as a big-endian `u32`.
+
+use anyhow::{bail, Context};
+use reed_solomon::{Decoder, Encoder};
+
+use crate::Result;
+
+/// Version of the layout above, stored first in the header
+const VERSION: u8 = 1;
+
+/// Length of the header, not counting its parity
+pub const HEADER_LEN: usize = 6;
+
+/// Parity bytes protecting the header, enough to repair 3 of its bytes
+pub const HEADER_PARITY: usize = 6;
+
+/// Largest Reed-Solomon block, data and parity together
+pub const BLOCK_LEN: usize = 255;
+
+/// What `decode` recovered: the message and how many bytes it had to repair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub message: Vec<u8>,
+    pub repaired: usize,
+}
+
+/// The number of parity bytes per block for `percent` percent parity. It is
+/// even, as each repaired byte takes two, and at least 2.
+pub fn parity_len(percent: u8) -> usize {
+    (BLOCK_LEN * usize::from(percent) / 100).clamp(2, BLOCK_LEN - 1) & !1
+}
+
+/// Adds Reed-Solomon parity to `message`, `percent` percent of each block
+pub fn encode(message: &[u8], percent: u8) -> Result<Vec<u8>> {
+    if !(1..=90).contains(&percent) {
+        bail!("Ecc: Parity must be between 1 and 90 percent, got {}", percent);
+    }
+    let length = u32::try_from(message.len()).context("Ecc: Message is too long")?;
+    let parity = parity_len(percent);
+
+    let mut header = vec![VERSION, parity as u8];
+    header.extend_from_slice(&length.to_be_bytes());
+    let mut encoded = Encoder::new(HEADER_PARITY).encode(&header).to_vec();
+
+    let encoder = Encoder::new(parity);
+    for block in message.chunks(BLOCK_LEN - parity) {
+        encoded.extend_from_slice(&encoder.encode(block));
+    }
+    Ok(encoded)
+}
+
+/// Repairs and returns a message written by `encode`. Fails with the number
+/// of blocks that had more damage than their parity can repair.
+pub fn decode(data: &[u8]) -> Result<Decoded> {
+    if data.len() < HEADER_LEN + HEADER_PARITY {
+        bail!("Ecc: Data is too short to hold an error correction header");
+    }
+    let (header, blocks) = data.split_at(HEADER_LEN + HEADER_PARITY);
+    let (header, mut repaired) = Decoder::new(HEADER_PARITY)
+        .correct_err_count(header, None)
+        .ok()
+        .context("Ecc: Error correction header is damaged beyond repair")?;
+    let header = header.data();
+    if header[0] != VERSION {
+        bail!("Ecc: Unknown error correction format version {}", header[0]);
+    }
+    let parity = usize::from(header[1]);
+    let length = u32::from_be_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    if !(2..BLOCK_LEN).contains(&parity) {
+        bail!("Ecc: Invalid parity length {} in the header", parity);
+    }
+
+    let block_count = length.div_ceil(BLOCK_LEN - parity);
+    if blocks.len() != length + block_count * parity {
+        bail!(
+            "Ecc: Expected {} bytes of blocks for a {} byte message, found {}",
+            length + block_count * parity,
+            length,
+            blocks.len()
+        );
+    }
+
+    let decoder = Decoder::new(parity);
+    let mut message = Vec::with_capacity(length);
+    let mut failed = 0;
+    for block in blocks.chunks(BLOCK_LEN) {
+        match decoder.correct_err_count(block, None) {
+            Ok((corrected, count)) => {
+                message.extend_from_slice(corrected.data());
+                repaired += count;
+            }
+            Err(_) => failed += 1,
+        }
+    }
+    if failed > 0 {
+        bail!("Ecc: {} of {} block(s) are damaged beyond repair", failed, block_count);
+    }
+    Ok(Decoded { message, repaired })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = b"This is where your secret message will be!";
+
+    #[test]
+    fn test_round_trip() {
+        for percent in [1, 20, 90] {
+            let encoded = encode(MESSAGE, percent).unwrap();
+            let decoded = decode(&encoded).unwrap();
+            assert_eq!(decoded.message, MESSAGE);
+            assert_eq!(decoded.repaired, 0);
+        }
+        let decoded = decode(&encode(b"", 20).unwrap()).unwrap();
+        assert_eq!(decoded.message, b"");
+    }
+
+    #[test]
+    fn test_parity_len() {
+        assert_eq!(parity_len(1), 2);
+        assert_eq!(parity_len(20), 50);
+        assert_eq!(parity_len(90), 228);
+    }
+
+    #[test]
+    fn test_repairs_up_to_the_parity_budget() {
+        // Blocks of up to 205 data bytes, each able to repair 25 bytes
+        let message: Vec<u8> = (0..500).map(|i| (i % 251) as u8).collect();
+        let mut encoded = encode(&message, 20).unwrap();
+        assert_eq!(encoded.len(), HEADER_LEN + HEADER_PARITY + 500 + 3 * 50);
+
+        for i in 0..3 {
+            encoded[i * 5] ^= 0xFF;
+        }
+        let blocks = HEADER_LEN + HEADER_PARITY;
+        for block in 0..3 {
+            for i in 0..25 {
+                encoded[blocks + block * BLOCK_LEN + i * 5] ^= 0x5A;
+            }
+        }
+
+        let decoded = decode(&encoded).unwrap();
+        assert_eq!(decoded.message, message);
+        assert_eq!(decoded.repaired, 3 + 3 * 25);
+    }
+
+    #[test]
+    fn test_too_much_damage() {
+        let message = vec![7; 500];
+        let mut encoded = encode(&message, 20).unwrap();
+        let second_block = HEADER_LEN + HEADER_PARITY + BLOCK_LEN;
+        for byte in &mut encoded[second_block..second_block + 26] {
+            *byte ^= 0xFF;
+        }
+
+        let error = decode(&encoded).unwrap_err();
+        assert_eq!(error.to_string(), "Ecc: 1 of 3 block(s) are damaged beyond repair");
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(encode(MESSAGE, 0).is_err());
+        assert!(encode(MESSAGE, 91).is_err());
+        assert!(decode(&[0; 4]).is_err());
+
+        let mut encoded = encode(MESSAGE, 20).unwrap();
+        encoded.truncate(encoded.len() - 1);
+        assert!(decode(&encoded).is_err());
+    }
+}
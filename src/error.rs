@@ -0,0 +1,107 @@
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+
+/// Errors produced while parsing or editing PNG files, so that callers can
+/// tell failure causes apart
+#[derive(Debug, Error)]
+pub enum PngError {
+    /// The input does not start with the PNG signature. `guess` names the
+    /// format it looks like instead, if any.
+    #[error(
+        "Png: Invalid signature, expected {} but found {}{}",
+        hex_bytes(&Png::STANDARD_HEADER),
+        hex_bytes(found),
+        match guess {
+            Some(format) => format!(" (this looks like a {} file)", format),
+            None => String::new(),
+        }
+    )]
+    InvalidSignature {
+        found: [u8; 8],
+        guess: Option<&'static str>,
+    },
+
+    #[error("Png: Input is empty, expected a PNG signature")]
+    EmptyInput,
+
+    #[error("Png: Input is only {found} bytes long, too short for the 8 byte PNG signature")]
+    TruncatedSignature { found: usize },
+
+    /// The input ended in the middle of a chunk. `offset` is where the
+    /// truncated field starts, counted from the start of the chunk.
+    #[error("Chunk: Not enough bytes in the {field} field at offset {offset} (expected {expected}, found {found})")]
+    TruncatedChunk {
+        offset: usize,
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Chunk: Length field claims {length} bytes of data, more than the {} allowed", crate::chunk::MAX_LENGTH)]
+    ChunkTooLong { length: u32 },
+
+    #[error("Chunk: {count} unexpected bytes after the CRC field")]
+    TrailingBytes { count: usize },
+
+    #[error("CRC mismatch in {chunk_type} chunk: stored 0x{actual:08x}, computed 0x{expected:08x}")]
+    CrcMismatch {
+        chunk_type: ChunkType,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// `index` is the 0-based position of the first byte that is not an ASCII
+    /// letter. The message counts from 1.
+    #[error(
+        "ChunkType: Invalid chunk type \"{}\", byte {} is '{}' (0x{:02X}), expected A-Z or a-z",
+        bytes.escape_ascii(),
+        index + 1,
+        [bytes[*index]].escape_ascii(),
+        bytes[*index]
+    )]
+    InvalidChunkType { bytes: [u8; 4], index: usize },
+
+    /// `length` counts bytes, not characters
+    #[error("ChunkType: Chunk type \"{}\" is {length} bytes long, expected 4", input.escape_default())]
+    InvalidChunkTypeLength { input: String, length: usize },
+
+    #[error("Png: No chunk found with chunk type {chunk_type}")]
+    ChunkNotFound { chunk_type: ChunkType },
+
+    #[error("Png: Insertion index {index} is out of bounds for {len} chunks")]
+    IndexOutOfBounds { index: usize, len: usize },
+
+    #[error("Png: Invalid chunk {index} at offset {offset}")]
+    InvalidChunk {
+        index: usize,
+        offset: usize,
+        #[source]
+        source: Box<PngError>,
+    },
+
+    #[error("Png: Failed to load PNG from {}", path.display())]
+    Load {
+        path: PathBuf,
+        #[source]
+        source: Box<PngError>,
+    },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type PngResult<T> = std::result::Result<T, PngError>;
+
+/// Formats `bytes` as space separated uppercase hex pairs
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,181 @@
+//! An editable JSON representation of a PNG's full chunk list, used by the
+//! `export` and `import` commands
+
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, Context};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::Result;
+
+/// A whole `Png` as written by `export`: its chunks in file order plus any
+/// bytes found after `IEND`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PngDocument {
+    pub chunks: Vec<ChunkEntry>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub trailing_data_base64: String,
+}
+
+/// One chunk of a `PngDocument`. `crc` is informational unless the import
+/// trusts it, since editing the data by hand leaves it stale.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    #[serde(rename = "type")]
+    pub chunk_type: String,
+    pub data_base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crc: Option<u32>,
+}
+
+impl PngDocument {
+    /// Describes every chunk of `png`, keeping the CRCs as they are stored
+    pub fn from_png(png: &Png) -> PngDocument {
+        PngDocument {
+            chunks: png
+                .chunks()
+                .iter()
+                .map(|chunk| ChunkEntry {
+                    chunk_type: chunk.chunk_type().to_string(),
+                    data_base64: BASE64.encode(chunk.data()),
+                    crc: Some(chunk.crc()),
+                })
+                .collect(),
+            trailing_data_base64: BASE64.encode(png.trailing_data()),
+        }
+    }
+
+    /// Rebuilds the `Png` this document describes. CRCs are recomputed from
+    /// the chunk data unless `trust_crc` is set, in which case any `crc` field
+    /// is written out as is.
+    pub fn to_png(&self, trust_crc: bool) -> Result<Png> {
+        let chunks = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                entry
+                    .to_chunk(trust_crc)
+                    .with_context(|| format!("Export: Invalid chunk {}", index))
+            })
+            .collect::<Result<Vec<Chunk>>>()?;
+        let trailing_data = BASE64
+            .decode(&self.trailing_data_base64)
+            .context("Export: trailing_data_base64 is not valid base64")?;
+
+        let mut png = Png::from_chunks(chunks);
+        png.set_trailing_data(trailing_data);
+        Ok(png)
+    }
+
+    /// Parses a document from the JSON written by `to_json`
+    pub fn from_json(json: &str) -> Result<PngDocument> {
+        serde_json::from_str(json).context("Export: Not a valid PNG chunk document")
+    }
+
+    /// Writes this document as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Export: Failed to write JSON")
+    }
+}
+
+impl ChunkEntry {
+    fn to_chunk(&self, trust_crc: bool) -> Result<Chunk> {
+        let bytes: [u8; 4] = self.chunk_type.as_bytes().try_into().map_err(|_| {
+            anyhow!("Export: Chunk type {:?} is not 4 bytes long", self.chunk_type)
+        })?;
+        let chunk_type = ChunkType::try_from(bytes)?;
+        let data = BASE64
+            .decode(&self.data_base64)
+            .context("Export: data_base64 is not valid base64")?;
+
+        Ok(match self.crc {
+            Some(crc) if trust_crc => Chunk::new_unchecked(chunk_type, data, crc),
+            _ => Chunk::new(chunk_type, data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn testing_png() -> Png {
+        let mut png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0, 1, 2, 255]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()),
+            Chunk::new_unchecked(ChunkType::from_str("IDAT").unwrap(), vec![], 0xDEADBEEF),
+            Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]),
+        ]);
+        png.set_trailing_data(b"after IEND".to_vec());
+        png
+    }
+
+    fn round_trip(png: &Png, trust_crc: bool) -> Png {
+        let json = PngDocument::from_png(png).to_json().unwrap();
+        PngDocument::from_json(&json).unwrap().to_png(trust_crc).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_is_byte_for_byte() {
+        let mut png = testing_png();
+        png.retain_chunks(Chunk::verify_crc);
+        assert_eq!(round_trip(&png, false).as_bytes(), png.as_bytes());
+
+        let png = testing_png();
+        assert_eq!(round_trip(&png, true).as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_import_recomputes_stale_crc() {
+        let png = round_trip(&testing_png(), false);
+        assert!(png.chunks().iter().all(Chunk::verify_crc));
+        assert_eq!(png.trailing_data(), b"after IEND");
+    }
+
+    #[test]
+    fn test_import_ignores_edited_crc_unless_trusted() {
+        let mut document = PngDocument::from_png(&testing_png());
+        document.chunks[1].data_base64 = BASE64.encode("edited");
+
+        let png = document.to_png(false).unwrap();
+        assert_eq!(png[1].data_as_string().unwrap(), "edited");
+        assert!(png[1].verify_crc());
+
+        let png = document.to_png(true).unwrap();
+        assert!(!png[1].verify_crc());
+    }
+
+    #[test]
+    fn test_import_without_crc_field() {
+        let json = r#"{ "chunks": [ { "type": "ruSt", "data_base64": "aGk=" } ] }"#;
+        let png = PngDocument::from_json(json).unwrap().to_png(true).unwrap();
+        assert_eq!(png[0].data_as_string().unwrap(), "hi");
+        assert!(png[0].verify_crc());
+        assert!(png.trailing_data().is_empty());
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_chunk_type() {
+        for chunk_type in ["ru1t", "rust!", "ru"] {
+            let json = format!(
+                r#"{{ "chunks": [ {{ "type": "{}", "data_base64": "" }} ] }}"#,
+                chunk_type
+            );
+            let error = PngDocument::from_json(&json).unwrap().to_png(false).unwrap_err();
+            assert!(format!("{:#}", error).contains("Invalid chunk 0"), "{:#}", error);
+        }
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_base64() {
+        let json = r#"{ "chunks": [ { "type": "ruSt", "data_base64": "not base64!" } ] }"#;
+        assert!(PngDocument::from_json(json).unwrap().to_png(false).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+//! Downloading input PNGs from HTTP(S) URLs
+
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context};
+
+use crate::Result;
+
+/// The most bytes `download` reads unless told otherwise, 100 MiB
+pub const DEFAULT_MAX_DOWNLOAD_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Returns the URL `path` holds if it starts with `http://` or `https://`
+pub fn as_url(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Downloads `url` into memory, failing on an HTTP error status or once more
+/// than `max_size` bytes have been received
+pub fn download(url: &str, max_size: u64) -> Result<Vec<u8>> {
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            bail!("Http: {} returned HTTP {} {}", url, status, response.status_text())
+        }
+        Err(error) => return Err(anyhow!(error).context(format!("Http: Could not fetch {}", url))),
+    };
+
+    let too_large = || anyhow!("Http: {} is larger than the {} byte download limit", url, max_size);
+    let length = response.header("Content-Length").and_then(|len| len.parse::<u64>().ok());
+    if length.is_some_and(|length| length > max_size) {
+        return Err(too_large());
+    }
+
+    // Read one byte past the limit to tell a file of exactly `max_size` bytes
+    // from a larger one sent without a Content-Length
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Http: Failed to download {}", url))?;
+    if bytes.len() as u64 > max_size {
+        return Err(too_large());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_url() {
+        assert_eq!(as_url(Path::new("http://example.com/a.png")), Some("http://example.com/a.png"));
+        assert_eq!(as_url(Path::new("https://example.com/a.png")), Some("https://example.com/a.png"));
+        assert_eq!(as_url(Path::new("dice.png")), None);
+        assert_eq!(as_url(Path::new("./http://dice.png")), None);
+        assert_eq!(as_url(Path::new("ftp://example.com/a.png")), None);
+    }
+}
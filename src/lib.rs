@@ -0,0 +1,25 @@
+pub mod chunk;
+pub mod chunk_type;
+pub mod compress;
+#[cfg(feature = "encryption")]
+pub mod crypto;
+pub mod doctor;
+#[cfg(feature = "ecc")]
+pub mod ecc;
+pub mod error;
+#[cfg(feature = "json")]
+pub mod export;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod png;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod standard_chunks;
+pub mod steg;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub type Error = anyhow::Error;
+pub type Result<T> = anyhow::Result<T>;
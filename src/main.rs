@@ -1,23 +1,37 @@
 mod args;
-mod chunk;
-mod chunk_type;
 mod commands;
-mod png;
+mod progress;
+mod render;
 
 use clap::Parser;
 
-pub type Error = anyhow::Error;
-pub type Result<T> = anyhow::Result<T>;
-
 use args::{PngMeCommands, PngMeArgs};
+use pngme::Result;
 
 fn main() -> Result<()> {
     let args = PngMeArgs::parse();
+    let determinism = commands::Determinism::from_args(&args)?;
 
     match args.command {
-        PngMeCommands::Encode(encode_args) => commands::encode(encode_args),
+        PngMeCommands::Encode(encode_args) => commands::encode(encode_args, &determinism),
         PngMeCommands::Decode(decode_args) => commands::decode(decode_args),
         PngMeCommands::Remove(remove_args) => commands::remove(remove_args),
         PngMeCommands::Print(print_args) => commands::print_chunks(print_args),
+        PngMeCommands::Extract(extract_args) => commands::extract(extract_args),
+        PngMeCommands::Rename(rename_args) => commands::rename(rename_args),
+        PngMeCommands::Strip(strip_args) => commands::strip(strip_args),
+        PngMeCommands::Reorder(reorder_args) => commands::reorder(reorder_args),
+        PngMeCommands::Verify(verify_args) => commands::verify(verify_args),
+        PngMeCommands::Info(info_args) => commands::info(info_args),
+        PngMeCommands::Merge(merge_args) => commands::merge(merge_args),
+        PngMeCommands::Doctor(doctor_args) => commands::doctor(doctor_args),
+        PngMeCommands::Steg(steg_args) => commands::steg(steg_args),
+        PngMeCommands::Capacity(capacity_args) => commands::capacity(capacity_args),
+        PngMeCommands::Generate(generate_args) => commands::generate(generate_args),
+        PngMeCommands::Completions(completions_args) => commands::completions(completions_args),
+        #[cfg(feature = "json")]
+        PngMeCommands::Export(export_args) => commands::export(export_args),
+        #[cfg(feature = "json")]
+        PngMeCommands::Import(import_args) => commands::import(import_args),
     }
 }
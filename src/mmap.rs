@@ -0,0 +1,147 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::error::{PngError, PngResult};
+use crate::png::{ParseOptions, Png, PngView};
+
+/// The bytes backing a `MappedPng`
+#[derive(Debug)]
+enum Backing {
+    Mapped(Mmap),
+    // Used for empty files, which cannot be mapped, and files that shrank
+    // while being mapped
+    Read(Vec<u8>),
+}
+
+/// A PNG file mapped into memory, so that its chunks can be inspected without
+/// reading or copying their data
+#[derive(Debug)]
+pub struct MappedPng {
+    backing: Backing,
+}
+
+impl MappedPng {
+    /// Maps the file at `path` into memory
+    pub fn open(path: &Path) -> PngResult<MappedPng> {
+        Self::map(path).map_err(|e| PngError::Load {
+            path: path.to_path_buf(),
+            source: Box::new(e),
+        })
+    }
+
+    fn map(path: &Path) -> PngResult<MappedPng> {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        if length == 0 {
+            return Ok(MappedPng {
+                backing: Backing::Read(Vec::new()),
+            });
+        }
+
+        // SAFETY: the mapping is only read from. Another process truncating
+        // the file while it is mapped is not something we can fully guard
+        // against, but a file that already shrank is read normally instead.
+        let map = unsafe { Mmap::map(&file)? };
+        if file.metadata()?.len() < map.len() as u64 {
+            return Ok(MappedPng {
+                backing: Backing::Read(fs::read(path)?),
+            });
+        }
+        Ok(MappedPng {
+            backing: Backing::Mapped(map),
+        })
+    }
+
+    /// The raw bytes of the file
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.backing {
+            Backing::Mapped(map) => map,
+            Backing::Read(bytes) => bytes,
+        }
+    }
+
+    /// Parses the chunks of the file without copying their data
+    pub fn view(&self) -> PngResult<PngView<'_>> {
+        PngView::parse(self.as_bytes())
+    }
+
+    /// Like `view`, but parses as `options` says, see `PngView::parse_with`
+    pub fn view_with(&self, options: ParseOptions) -> PngResult<(PngView<'_>, Vec<PngError>)> {
+        PngView::parse_with(self.as_bytes(), options)
+    }
+}
+
+impl Png {
+    /// Creates a `Png` from a memory-mapped file, copying only the chunks and
+    /// not the whole file
+    pub fn from_mmap<P: AsRef<Path>>(path: P) -> PngResult<Png> {
+        let path = path.as_ref();
+        MappedPng::open(path)?
+            .view()
+            .map(|view| view.to_png())
+            .map_err(|e| PngError::Load {
+                path: path.to_path_buf(),
+                source: Box::new(e),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("pngme-mmap-{}-{}.png", std::process::id(), name))
+    }
+
+    fn testing_png() -> Png {
+        let chunks = ["IHDR", "ruSt", "IDAT", "IEND"]
+            .iter()
+            .map(|chunk_type| {
+                let data = chunk_type.repeat(100).into_bytes();
+                Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+            })
+            .collect();
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_mapped_matches_read() {
+        let path = temp_path("matches_read");
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(b"trailing");
+        fs::write(&path, &bytes).unwrap();
+
+        let mapped = MappedPng::open(&path).unwrap();
+        let view = mapped.view().unwrap();
+
+        assert_eq!(mapped.as_bytes(), bytes.as_slice());
+        assert_eq!(view.trailing_data(), b"trailing");
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(view.find_chunk(&chunk_type).unwrap().data(), "ruSt".repeat(100).as_bytes());
+        assert_eq!(Png::from_mmap(&path).unwrap().as_bytes(), bytes);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_empty_file() {
+        let path = temp_path("empty_file");
+        fs::write(&path, b"").unwrap();
+
+        let mapped = MappedPng::open(&path).unwrap();
+
+        assert!(matches!(mapped.view(), Err(PngError::EmptyInput)));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_missing_file() {
+        let path = temp_path("missing_file");
+        assert!(matches!(MappedPng::open(&path), Err(PngError::Load { .. })));
+    }
+}
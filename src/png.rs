@@ -1,265 +1,2959 @@
+use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fs;
-use std::io::{BufReader, Read};
-use std::path::Path;
+use std::ops::{Index, IndexMut};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::{bail, Context};
+use flate2::write::ZlibEncoder;
 
-use crate::chunk::Chunk;
+use crate::chunk::{png_crc, Chunk, ChunkReader, ChunkView};
 use crate::chunk_type::ChunkType;
-use crate::{Error, Result};
+use crate::error::{PngError, PngResult};
+use crate::standard_chunks::{new_ihdr_chunk, IhdrData};
+use crate::Result;
+
+/// The path of the temporary file written next to `path` before it is
+/// renamed over `path`: `.NAME.tmp` in the same directory, so that the rename
+/// stays on one filesystem
+pub fn temp_sibling(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Png: {} is not a file path", path.display()),
+        )
+    })?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    Ok(path.with_file_name(temp_name))
+}
+
+/// The 8 bytes every PNG file starts with
+pub const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The most pixels `Png::generate` makes an image of, 8192x8192, so that its
+/// 256 MiB of image data is compressed in reasonable time and memory
+pub const GENERATE_MAX_PIXELS: u64 = 1 << 26;
+
+/// Chunk types that must come before PLTE and IDAT
+pub(crate) const BEFORE_PLTE: [&[u8; 4]; 8] = [
+    b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"mDCv", b"cLLI",
+];
+
+/// Chunk types that must come after PLTE, when there is one, and before IDAT
+pub(crate) const AFTER_PLTE: [&[u8; 4]; 3] = [b"bKGD", b"hIST", b"tRNS"];
+
+/// Chunk types that must come before IDAT, wherever PLTE is
+pub(crate) const BEFORE_IDAT: [&[u8; 4]; 4] = [b"pHYs", b"sPLT", b"eXIf", b"acTL"];
 
 /// A PNG container as described by the PNG spec
 /// http://www.libpng.org/pub/png/spec/1.2/PNG-Contents.html
 #[derive(Debug)]
 pub struct Png {
     chunks: Vec<Chunk>,
+    trailing_data: Vec<u8>,
 }
 
-impl TryFrom<&[u8]> for Png {
-    type Error = Error;
+/// A rule of the PNG chunk layout that a `Png` can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureRule {
+    MissingIhdr,
+    IhdrNotFirst,
+    DuplicateIhdr,
+    MissingIend,
+    DuplicateIend,
+    ChunkAfterIend,
+    MissingIdat,
+    IdatNotConsecutive,
+    PlteAfterIdat,
+}
 
-    fn try_from(bytes: &[u8]) -> Result<Png> {
-        let mut reader = BufReader::new(bytes);
+impl StructureRule {
+    /// A human-readable name for this rule
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructureRule::MissingIhdr => "an IHDR chunk is required",
+            StructureRule::IhdrNotFirst => "IHDR must be the first chunk",
+            StructureRule::DuplicateIhdr => "only one IHDR chunk is allowed",
+            StructureRule::MissingIend => "an IEND chunk is required",
+            StructureRule::DuplicateIend => "only one IEND chunk is allowed",
+            StructureRule::ChunkAfterIend => "no chunks may follow IEND",
+            StructureRule::MissingIdat => "at least one IDAT chunk is required",
+            StructureRule::IdatNotConsecutive => "IDAT chunks must be consecutive",
+            StructureRule::PlteAfterIdat => "PLTE must precede the first IDAT chunk",
+        }
+    }
+}
 
-        let mut header: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0];
-        reader.read_exact(&mut header).context("Png: Not enough bytes in the header")?;
+/// A violation of a `StructureRule`, with the index of the offending chunk
+/// when there is one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructureError {
+    pub index: Option<usize>,
+    pub rule: StructureRule,
+}
 
-        if header != Self::STANDARD_HEADER {
-            bail!("Png: Invalid header")
-        }
-        let mut chunk_offset = 8usize;
-        let mut chunks: Vec<Chunk> = Vec::new();
-        while let Ok(chunk) = Chunk::try_from(&bytes[chunk_offset..]) {
-            chunk_offset += chunk.length() as usize + 12;
-            chunks.push(chunk);
+impl fmt::Display for StructureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "chunk {}: {}", index, self.rule.name()),
+            None => write!(f, "{}", self.rule.name()),
         }
-        Ok(Png::from_chunks(chunks))
     }
 }
 
-impl fmt::Display for Png {
+impl std::error::Error for StructureError {}
+
+/// Where a chunk lives in the serialized form of a `Png`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSpan {
+    /// Byte offset of the length field of the chunk
+    pub offset: u64,
+    /// Size of the chunk including its length, type and CRC fields
+    pub total_len: u64,
+}
+
+/// A chunk that `Png::normalize_order` moved back past chunks that must
+/// follow it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reordering {
+    pub chunk_type: ChunkType,
+    /// Index of the chunk before reordering
+    pub from: usize,
+    /// Index of the chunk after reordering
+    pub to: usize,
+}
+
+impl fmt::Display for Reordering {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Png {{",)?;
-        for chunk in &self.chunks {
-            writeln!(f, "{}", chunk)?;
+        write!(f, "moved {} from chunk {} to chunk {}", self.chunk_type, self.from, self.to)
+    }
+}
+
+/// What parsing does when a chunk cannot be read or has a bad CRC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    /// Stop and return the error
+    Fail,
+    /// Drop chunks with a bad CRC. A chunk that cannot be read at all ends
+    /// parsing, and everything from it onwards is kept as trailing data.
+    Skip,
+    /// Like `Skip`, but keep chunks with a bad CRC and return every error
+    Collect,
+}
+
+/// Options for parsing a PNG leniently or quickly. The default checks every
+/// CRC and fails on the first error, like `Png::try_from` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Whether to check the CRC of every chunk
+    pub verify_crc: bool,
+    pub on_error: OnError,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            verify_crc: true,
+            on_error: OnError::Fail,
         }
-        writeln!(f, "}}")
     }
 }
 
-impl Png {
-    // Fill in this array with the correct values per the PNG spec
-    pub(crate) const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// Applies `on_error` to a chunk that failed to parse, returning the error
+/// if parsing has to stop with it and recording it if it is collected
+fn handle_error(error: PngError, on_error: OnError, errors: &mut Vec<PngError>) -> PngResult<()> {
+    match on_error {
+        OnError::Fail => Err(error),
+        OnError::Skip => Ok(()),
+        OnError::Collect => {
+            errors.push(error);
+            Ok(())
+        }
+    }
+}
 
-    /// Creates a `Png` from a list of chunks using the correct header
-    pub(crate) fn from_chunks(chunks: Vec<Chunk>) -> Self {
-        Self { chunks }
+/// Runs `validate_crc` if `options` ask for it, returning whether the chunk
+/// should be kept. A failure is wrapped with `invalid` and handled like any
+/// other chunk error.
+fn check_crc(
+    validate_crc: impl FnOnce() -> PngResult<()>,
+    invalid: impl FnOnce(PngError) -> PngError,
+    options: ParseOptions,
+    errors: &mut Vec<PngError>,
+) -> PngResult<bool> {
+    if !options.verify_crc {
+        return Ok(true);
+    }
+    match validate_crc() {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            handle_error(invalid(e), options.on_error, errors)?;
+            Ok(options.on_error == OnError::Collect)
+        }
     }
+}
 
-    /// Creates a `Png` from a file path
-    pub(crate) fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = fs::read(path).context("Png: Failed to read file")?;
-        Self::try_from(file.as_ref())
+/// Checks that `bytes` start with the standard PNG signature
+fn check_signature(bytes: &[u8]) -> PngResult<()> {
+    if Png::header_is_valid(bytes) {
+        return Ok(());
+    }
+    if bytes.is_empty() {
+        return Err(PngError::EmptyInput);
     }
+    let found: [u8; 8] = match bytes.get(..8) {
+        Some(header) => header.try_into().expect("Png: Slice has 8 bytes"),
+        None => return Err(PngError::TruncatedSignature { found: bytes.len() }),
+    };
+    if found != Png::STANDARD_HEADER {
+        return Err(PngError::InvalidSignature {
+            found,
+            guess: guess_format(bytes),
+        });
+    }
+    Ok(())
+}
 
-    /// Appends a chunk to the end of this `Png` file's `Chunk` list.
-    pub(crate) fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk);
+/// Makes a best-effort guess at what kind of file starts with `bytes`
+fn guess_format(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        Some("JPEG")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("GIF")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("WebP")
+    } else if bytes
+        .iter()
+        .take(8)
+        .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+    {
+        Some("plain text")
+    } else {
+        None
     }
+}
 
-    /// Searches for a `Chunk` with the specified `chunk_type` and removes the first
-    /// matching `Chunk` from this `Png` list of chunks.
-    pub(crate) fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type)?;
-        for idx in 0..self.chunks.len() {
-            let chunk_to_compare = self.chunks[idx].chunk_type();
-            if *chunk_to_compare == chunk_type {
-                return Ok(self.chunks.remove(idx));
+impl Default for Png {
+    /// A `Png` with no chunks, which is not a valid PNG until at least IHDR,
+    /// IDAT and IEND chunks are added. See `PngBuilder` for building one.
+    fn default() -> Self {
+        Png::from_chunks(Vec::new())
+    }
+}
+
+/// An 8-bit RGBA color, parsed from hex digits as `RRGGBBAA` or `#RRGGBBAA`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl FromStr for Rgba {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let value = match hex.len() {
+            8 if hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+                u32::from_str_radix(hex, 16)?
             }
+            _ => bail!("Png: Expected a color as 8 hex digits RRGGBBAA, got {:?}", s),
+        };
+        let [r, g, b, a] = value.to_be_bytes();
+        Ok(Rgba { r, g, b, a })
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02X}{:02X}{:02X}{:02X}", self.r, self.g, self.b, self.a)
+    }
+}
+
+/// Builds a `Png` from scratch, checking the result with `Png::validate`
+#[derive(Debug, Default)]
+pub struct PngBuilder {
+    ihdr: Option<Chunk>,
+    chunks: Vec<Chunk>,
+}
+
+impl PngBuilder {
+    pub fn new() -> Self {
+        PngBuilder::default()
+    }
+
+    /// Sets the image header, which always becomes the first chunk
+    pub fn with_ihdr(mut self, ihdr: IhdrData) -> Self {
+        self.ihdr = Some(new_ihdr_chunk(&ihdr));
+        self
+    }
+
+    /// Adds `chunk` after the ones added so far
+    pub fn append_chunk(mut self, chunk: Chunk) -> Self {
+        self.chunks.push(chunk);
+        self
+    }
+
+    /// Puts the chunks together after the header, adding an IEND chunk if the
+    /// last one isn't, and fails if the result is not a valid PNG
+    pub fn build(self) -> Result<Png> {
+        let mut chunks: Vec<Chunk> = self.ihdr.into_iter().chain(self.chunks).collect();
+        if !chunks.last().is_some_and(Chunk::is_iend) {
+            chunks.push(Chunk::new_iend());
         }
-        bail!("Png: No chunk found with chunk type {}", chunk_type)
+        let png = Png::from_chunks(chunks);
+        if let Err(errors) = png.validate() {
+            let report: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+            anyhow::bail!("Png: Built PNG is invalid: {}", report.join("; "));
+        }
+        Ok(png)
     }
+}
 
-    /// The header of this PNG.
-    pub(crate) fn header(&self) -> &[u8; 8] {
-        &Self::STANDARD_HEADER
+impl TryFrom<&[u8]> for Png {
+    type Error = PngError;
+
+    fn try_from(bytes: &[u8]) -> PngResult<Png> {
+        PngView::parse(bytes).map(|view| view.to_png())
     }
+}
 
-    /// Lists the `Chunk`s stored in this `Png`
-    pub(crate) fn chunks(&self) -> &[Chunk] {
-        &self.chunks
+impl From<Png> for Vec<u8> {
+    /// Same as `Png::into_bytes`
+    fn from(png: Png) -> Self {
+        png.into_bytes()
     }
+}
 
-    /// Searches for a `Chunk` with the specified `chunk_type` and returns the first
-    /// matching `Chunk` from this `Png`.
-    pub(crate) fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
-        for chunk in &self.chunks {
-            if *chunk.chunk_type() == chunk_type {
-                return Some(chunk);
+/// A PNG borrowed from a byte slice, for inspecting its chunks without
+/// copying their data. Use `to_png` to get an owned, editable `Png`.
+#[derive(Debug, Clone)]
+pub struct PngView<'a> {
+    chunks: Vec<ChunkView<'a>>,
+    trailing_data: &'a [u8],
+}
+
+impl<'a> PngView<'a> {
+    /// Parses the signature and chunks in `bytes`, checking every CRC
+    pub fn parse(bytes: &'a [u8]) -> PngResult<PngView<'a>> {
+        let (view, _) = PngView::parse_with(bytes, ParseOptions::default())?;
+        Ok(view)
+    }
+
+    /// Parses the signature and chunks in `bytes` as `options` says, returning
+    /// the errors collected along the way with `OnError::Collect`
+    pub fn parse_with(
+        bytes: &'a [u8],
+        options: ParseOptions,
+    ) -> PngResult<(PngView<'a>, Vec<PngError>)> {
+        check_signature(bytes)?;
+
+        let mut chunk_offset = 8usize;
+        let mut chunks: Vec<ChunkView> = Vec::new();
+        let mut errors = Vec::new();
+        while chunk_offset < bytes.len() {
+            let invalid = |e| PngError::InvalidChunk {
+                index: chunks.len(),
+                offset: chunk_offset,
+                source: Box::new(e),
+            };
+            let chunk = match ChunkView::try_from_slice_unchecked(&bytes[chunk_offset..]) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    handle_error(invalid(e), options.on_error, &mut errors)?;
+                    return Ok((
+                        PngView {
+                            chunks,
+                            trailing_data: &bytes[chunk_offset..],
+                        },
+                        errors,
+                    ));
+                }
+            };
+            let keep = check_crc(|| chunk.validate_crc(), invalid, options, &mut errors)?;
+            chunk_offset += chunk.serialized_len();
+            if keep {
+                chunks.push(chunk);
+            }
+            if chunk.chunk_type() == "IEND" {
+                break;
             }
         }
-        None
+        let trailing_data = views_after_iend(&bytes[chunk_offset..], &mut chunks);
+        Ok((
+            PngView {
+                chunks,
+                trailing_data,
+            },
+            errors,
+        ))
     }
 
-    /// Returns this `Png` as a byte sequence.
-    /// These bytes will contain the header followed by the bytes of all of the chunks.
-    pub(crate) fn as_bytes(&self) -> Vec<u8> {
-        let chunks_as_bytes: Vec<u8> = self.chunks().iter().flat_map(|chunk| chunk.as_bytes()).collect();
+    /// Lists the chunks of this PNG
+    pub fn chunks(&self) -> &[ChunkView<'a>] {
+        &self.chunks
+    }
+
+    /// The bytes found after `IEND` that are not chunks
+    pub fn trailing_data(&self) -> &'a [u8] {
+        self.trailing_data
+    }
 
-        self.header()
+    /// Returns the first chunk with the specified `chunk_type`
+    pub fn find_chunk(&self, chunk_type: &ChunkType) -> Option<&ChunkView<'a>> {
+        self.chunks
             .iter()
-            .copied()
-            .chain(chunks_as_bytes)
-            .collect()
+            .find(|chunk| chunk.chunk_type() == chunk_type)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::chunk::Chunk;
-    use crate::chunk_type::ChunkType;
-    use std::convert::TryFrom;
+    /// Returns the `n`th (0-indexed) chunk of type `chunk_type`, if there are that many
+    pub fn nth_chunk_of_type(&self, chunk_type: &ChunkType, n: usize) -> Option<&ChunkView<'a>> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == chunk_type)
+            .nth(n)
+    }
 
-    fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
+    /// Like `Png::validate`, checks that the chunks are laid out as the PNG
+    /// spec requires
+    pub fn validate(&self) -> std::result::Result<(), Vec<StructureError>> {
+        let types: Vec<ChunkType> = self.chunks.iter().map(|chunk| *chunk.chunk_type()).collect();
+        validate_layout(&types)
+    }
+
+    /// Copies every chunk into an owned `Png`
+    pub fn to_png(&self) -> Png {
+        Png {
+            chunks: self.chunks.iter().copied().map(ChunkView::to_owned).collect(),
+            trailing_data: self.trailing_data.to_vec(),
+        }
+    }
+}
 
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
+/// Checks that chunks of these types, in this order, are laid out as the PNG
+/// spec requires, returning every violated rule
+fn validate_layout(types: &[ChunkType]) -> std::result::Result<(), Vec<StructureError>> {
+    let mut errors = Vec::new();
+    let mut error = |index, rule| errors.push(StructureError { index, rule });
 
-        chunks
+    let positions = |name: &[u8; 4]| -> Vec<usize> {
+        types
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk_type)| chunk_type.as_bytes() == name)
+            .map(|(idx, _)| idx)
+            .collect()
+    };
+    let ihdr = positions(ChunkType::IHDR.as_bytes());
+    let iend = positions(ChunkType::IEND.as_bytes());
+    let idat = positions(b"IDAT");
+    let plte = positions(b"PLTE");
+
+    match ihdr.first() {
+        None => error(None, StructureRule::MissingIhdr),
+        Some(&idx) if idx != 0 => error(Some(idx), StructureRule::IhdrNotFirst),
+        Some(_) => {}
+    }
+    for &idx in ihdr.iter().skip(1) {
+        error(Some(idx), StructureRule::DuplicateIhdr);
     }
 
-    fn testing_png() -> Png {
-        let chunks = testing_chunks();
-        Png::from_chunks(chunks)
+    match iend.first() {
+        None => error(None, StructureRule::MissingIend),
+        Some(&idx) if idx + 1 != types.len() => {
+            error(Some(idx + 1), StructureRule::ChunkAfterIend)
+        }
+        Some(_) => {}
+    }
+    for &idx in iend.iter().skip(1) {
+        error(Some(idx), StructureRule::DuplicateIend);
     }
 
-    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type)?;
-        let data: Vec<u8> = data.bytes().collect();
+    match (idat.first(), idat.last()) {
+        (Some(&first), Some(&last)) => {
+            if last - first + 1 != idat.len() {
+                let gap = (first..=last).find(|idx| !idat.contains(idx));
+                error(gap, StructureRule::IdatNotConsecutive);
+            }
+            if let Some(&idx) = plte.iter().find(|&&idx| idx > first) {
+                error(Some(idx), StructureRule::PlteAfterIdat);
+            }
+        }
+        _ => error(None, StructureRule::MissingIdat),
+    }
 
-        Ok(Chunk::new(chunk_type, data))
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
+}
 
-    #[test]
-    fn test_from_chunks() {
-        let chunks = testing_chunks();
-        let png = Png::from_chunks(chunks);
+/// The error for a chunk with `length` bytes of data of which only
+/// `remaining` bytes, CRC included, are left in the input
+fn truncated_body(length: usize, remaining: usize) -> PngError {
+    if remaining < length {
+        PngError::TruncatedChunk {
+            offset: 8,
+            field: "data",
+            expected: length,
+            found: remaining,
+        }
+    } else {
+        PngError::TruncatedChunk {
+            offset: 8 + length,
+            field: "CRC",
+            expected: 4,
+            found: remaining - length,
+        }
+    }
+}
 
-        assert_eq!(png.chunks().len(), 3);
+/// Parses what follows IEND leniently. Chunks there are kept, since older
+/// versions of pngme appended messages after IEND, and everything from the
+/// first byte that does not parse as a chunk is returned as trailing data.
+fn views_after_iend<'a>(mut bytes: &'a [u8], chunks: &mut Vec<ChunkView<'a>>) -> &'a [u8] {
+    while let Ok(chunk) = ChunkView::try_from_slice(bytes) {
+        chunks.push(chunk);
+        bytes = &bytes[chunk.serialized_len()..];
     }
+    bytes
+}
 
-    #[test]
-    fn test_valid_from_bytes() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+/// Walks the chunk headers of the PNG read from `r`, seeking past their data,
+/// to find the offset of the first IEND chunk. Returns `None` if there is no
+/// IEND or a chunk header cannot be read before it.
+fn find_iend_offset<R: Read + Seek>(mut r: R) -> PngResult<Option<u64>> {
+    read_signature(&mut r)?;
 
-        let bytes: Vec<u8> = Png::STANDARD_HEADER
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+    let mut offset = 8u64;
+    loop {
+        let (length, chunk_type) = match Chunk::read_header(&mut r) {
+            Ok(Some(header)) => header,
+            Err(PngError::Io(e)) => return Err(e.into()),
+            Ok(None) | Err(_) => return Ok(None),
+        };
+        if chunk_type == "IEND" {
+            return Ok(Some(offset));
+        }
+        r.seek(SeekFrom::Current(i64::from(length) + 4))?;
+        offset += 12 + u64::from(length);
+    }
+}
+
+/// Reads the 8 byte signature from `r` and checks it against the standard one
+fn read_signature<R: Read>(r: &mut R) -> PngResult<()> {
+    let mut header: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0];
+    let mut found = 0;
+    while found < header.len() {
+        match r.read(&mut header[found..]) {
+            Ok(0) => break,
+            Ok(n) => found += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    check_signature(&header[..found])
+}
 
-        let png = Png::try_from(bytes.as_ref());
+impl TryFrom<&Path> for Png {
+    type Error = PngError;
 
-        assert!(png.is_ok());
+    fn try_from(path: &Path) -> PngResult<Png> {
+        fs::read(path)
+            .map_err(PngError::from)
+            .and_then(|bytes| Png::try_from(bytes.as_slice()))
+            .map_err(|e| PngError::Load {
+                path: path.to_path_buf(),
+                source: Box::new(e),
+            })
     }
+}
 
-    #[test]
-    fn test_invalid_header() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+impl TryFrom<&PathBuf> for Png {
+    type Error = PngError;
 
-        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+    fn try_from(path: &PathBuf) -> PngResult<Png> {
+        Png::try_from(path.as_path())
+    }
+}
 
-        let png = Png::try_from(bytes.as_ref());
+impl IntoIterator for Png {
+    type Item = Chunk;
+    type IntoIter = std::vec::IntoIter<Chunk>;
 
-        assert!(png.is_err());
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter()
     }
+}
 
-    #[test]
-    fn test_invalid_chunk() {
-        let mut chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+impl Extend<Chunk> for Png {
+    /// Same as `Png::append_chunks`
+    fn extend<I: IntoIterator<Item = Chunk>>(&mut self, chunks: I) {
+        self.append_chunks(chunks);
+    }
+}
 
-        #[rustfmt::skip]
-        let mut bad_chunk = vec![
-            0, 0, 0, 5,         // length
-            32, 117, 83, 116,   // Chunk Type (bad)
-            65, 64, 65, 66, 67, // Data
-            1, 2, 3, 4, 5       // CRC (bad)
-        ];
+impl<'a> IntoIterator for &'a Png {
+    type Item = &'a Chunk;
+    type IntoIter = std::slice::Iter<'a, Chunk>;
 
-        chunk_bytes.append(&mut bad_chunk);
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.iter()
+    }
+}
 
-        let png = Png::try_from(chunk_bytes.as_ref());
+impl Index<usize> for Png {
+    type Output = Chunk;
 
-        assert!(png.is_err());
+    /// Returns the chunk at `index`, panicking if it is out of bounds. See
+    /// `Png::chunk_at` for a non-panicking version.
+    fn index(&self, index: usize) -> &Chunk {
+        let len = self.chunks.len();
+        match self.chunk_at(index) {
+            Some(chunk) => chunk,
+            None => panic!("Png: Chunk index {} is out of bounds for {} chunks", index, len),
+        }
     }
+}
 
-    #[test]
-    fn test_list_chunks() {
-        let png = testing_png();
-        let chunks = png.chunks();
-        assert_eq!(chunks.len(), 3);
+impl IndexMut<usize> for Png {
+    /// Returns the chunk at `index` for editing, panicking if it is out of
+    /// bounds. See `Png::chunk_at_mut` for a non-panicking version.
+    fn index_mut(&mut self, index: usize) -> &mut Chunk {
+        let len = self.chunks.len();
+        match self.chunk_at_mut(index) {
+            Some(chunk) => chunk,
+            None => panic!("Png: Chunk index {} is out of bounds for {} chunks", index, len),
+        }
     }
+}
 
-    #[test]
-    fn test_chunk_by_type() {
-        let png = testing_png();
-        let chunk = png.chunk_by_type("FrSt").unwrap();
-        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
-        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+impl fmt::Display for Png {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Png {{",)?;
+        for chunk in &self.chunks {
+            if f.alternate() {
+                writeln!(f, "{:#}", chunk)?;
+            } else {
+                writeln!(f, "  {}", chunk)?;
+            }
+        }
+        writeln!(f, "}}")
     }
+}
 
-    #[test]
-    fn test_append_chunk() {
-        let mut png = testing_png();
-        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        let chunk = png.chunk_by_type("TeSt").unwrap();
-        assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
-        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+impl Png {
+    // Fill in this array with the correct values per the PNG spec
+    pub const STANDARD_HEADER: [u8; 8] = PNG_SIGNATURE;
+
+    /// Checks whether `bytes` start with the PNG signature, without looking at
+    /// any chunks. Useful to tell PNG files apart from other formats cheaply.
+    pub fn header_is_valid(bytes: &[u8]) -> bool {
+        bytes.starts_with(&PNG_SIGNATURE)
     }
 
-    #[test]
-    fn test_remove_chunk() {
-        let mut png = testing_png();
-        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
-        png.remove_chunk("TeSt").unwrap();
-        let chunk = png.chunk_by_type("TeSt");
-        assert!(chunk.is_none());
+    /// Creates a `Png` from a list of chunks using the correct header
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        Self {
+            chunks,
+            trailing_data: Vec::new(),
+        }
     }
 
-    #[test]
-    fn test_png_from_image_file() {
-        let png = Png::try_from(&PNG_FILE[..]);
-        assert!(png.is_ok());
+    /// Creates the smallest valid image of `width` by `height` pixels filled
+    /// with `color`: an 8-bit RGBA IHDR, a single IDAT and IEND. Fails if
+    /// either side is 0 or over the 2^31 - 1 pixels PNG allows, or if the image
+    /// would have more than `GENERATE_MAX_PIXELS` pixels.
+    pub fn generate(width: u32, height: u32, color: Rgba) -> Result<Png> {
+        const MAX_SIDE: u32 = i32::MAX as u32;
+        if !(1..=MAX_SIDE).contains(&width) || !(1..=MAX_SIDE).contains(&height) {
+            bail!("Png: Image sides must be between 1 and {}, got {}x{}", MAX_SIDE, width, height);
+        }
+        // Checked before the scanline is allocated, which is 4 bytes a pixel
+        if u64::from(width) * u64::from(height) > GENERATE_MAX_PIXELS {
+            bail!(
+                "Png: Can generate at most {} pixels, {}x{} is {}",
+                GENERATE_MAX_PIXELS,
+                width,
+                height,
+                u64::from(width) * u64::from(height)
+            );
+        }
+        // Every scanline uses the Sub filter, so after the first pixel each
+        // byte is the difference from the pixel to its left, which is 0
+        let mut scanline = vec![0; 1 + 4 * width as usize];
+        scanline[0] = 1;
+        scanline[1..5].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        for _ in 0..height {
+            encoder.write_all(&scanline)?;
+        }
+        let idat = Chunk::new(ChunkType::IDAT, encoder.finish()?);
+
+        let ihdr = IhdrData {
+            width,
+            height,
+            bit_depth: 8,
+            color_type: 6,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        };
+        PngBuilder::new().with_ihdr(ihdr).append_chunk(idat).build()
     }
 
-    #[test]
-    fn test_as_bytes() {
+    /// Creates a `Png` from a file path
+    pub fn from_file<P: AsRef<Path>>(path: P) -> PngResult<Self> {
+        Self::try_from(path.as_ref())
+    }
+
+    /// Parses a `Png` from `bytes` as `options` says, returning the errors
+    /// collected along the way with `OnError::Collect`
+    pub fn parse_with(bytes: &[u8], options: ParseOptions) -> PngResult<(Png, Vec<PngError>)> {
+        let (view, errors) = PngView::parse_with(bytes, options)?;
+        Ok((view.to_png(), errors))
+    }
+
+    /// Creates a `Png` by reading the header and then chunks from `r` until
+    /// `IEND` or the end of the input
+    pub fn from_reader<R: Read>(r: R) -> PngResult<Self> {
+        let (png, _) = Png::from_reader_with(r, ParseOptions::default())?;
+        Ok(png)
+    }
+
+    /// Like `from_reader`, but parses as `options` says, returning the errors
+    /// collected along the way with `OnError::Collect`
+    pub fn from_reader_with<R: Read>(
+        r: R,
+        options: ParseOptions,
+    ) -> PngResult<(Self, Vec<PngError>)> {
+        Png::from_reader_with_progress(r, options, |_| {})
+    }
+
+    /// Like `from_reader_with`, but calls `progress` with the number of bytes
+    /// read so far after each chunk, and once more at the end of the input.
+    /// Meant for driving a progress bar.
+    pub fn from_reader_with_progress<R: Read>(
+        mut r: R,
+        options: ParseOptions,
+        mut progress: impl FnMut(u64),
+    ) -> PngResult<(Self, Vec<PngError>)> {
+        read_signature(&mut r)?;
+
+        let mut chunk_offset = 8usize;
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut errors = Vec::new();
+        let mut reader = ChunkReader::unchecked(r);
+        let mut broken = false;
+        for result in &mut reader {
+            let invalid = |e| PngError::InvalidChunk {
+                index: chunks.len(),
+                offset: chunk_offset,
+                source: Box::new(e),
+            };
+            let chunk = match result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    handle_error(invalid(e), options.on_error, &mut errors)?;
+                    broken = true;
+                    break;
+                }
+            };
+            let keep = check_crc(|| chunk.validate_crc(), invalid, options, &mut errors)?;
+            chunk_offset += chunk.serialized_len();
+            progress(chunk_offset as u64);
+            if keep {
+                chunks.push(chunk);
+            }
+        }
+
+        // Only the part after IEND, usually empty, is buffered
+        let mut rest = Vec::new();
+        reader.into_inner().read_to_end(&mut rest)?;
+        progress((chunk_offset + rest.len()) as u64);
+        if broken {
+            // The broken chunk was partly consumed, keep whatever is left of the input
+            return Ok((
+                Png {
+                    chunks,
+                    trailing_data: rest,
+                },
+                errors,
+            ));
+        }
+        let mut views = Vec::new();
+        let trailing_data = views_after_iend(&rest, &mut views).to_vec();
+        chunks.extend(views.into_iter().map(ChunkView::to_owned));
+        Ok((
+            Png {
+                chunks,
+                trailing_data,
+            },
+            errors,
+        ))
+    }
+
+    /// Finds the first `Chunk` of type `chunk_type` in the PNG read from `r`,
+    /// seeking past the data of every other chunk instead of reading it. An
+    /// input that ends partway through a chunk before IEND is an error, not a
+    /// missing match.
+    pub fn find_chunk_in_reader<R: Read + Seek>(
+        r: R,
+        chunk_type: &ChunkType,
+    ) -> PngResult<Option<Chunk>> {
+        Png::find_chunk_in_reader_with(r, chunk_type, ParseOptions::default())
+    }
+
+    /// Like `find_chunk_in_reader`, but handles errors as `options` says. Only
+    /// the CRC of matching chunks is ever checked, and a match skipped for its
+    /// CRC is passed over in favour of the next one.
+    pub fn find_chunk_in_reader_with<R: Read + Seek>(
+        mut r: R,
+        chunk_type: &ChunkType,
+        options: ParseOptions,
+    ) -> PngResult<Option<Chunk>> {
+        // Seeking past the end succeeds, so skipped chunks are checked against
+        // the length of the input to catch truncation
+        let start = r.stream_position()?;
+        let len = r.seek(SeekFrom::End(0))? - start;
+        r.seek(SeekFrom::Start(start))?;
+        read_signature(&mut r)?;
+
+        let mut chunk_offset = 8usize;
+        let mut index = 0;
+        let mut seen_iend = false;
+        loop {
+            let invalid = |e| PngError::InvalidChunk {
+                index,
+                offset: chunk_offset,
+                source: Box::new(e),
+            };
+            let (length, found) = match Chunk::read_header(&mut r) {
+                Ok(Some(header)) => header,
+                Ok(None) => return Ok(None),
+                // Whatever follows IEND is not part of the image
+                Err(_) if seen_iend => return Ok(None),
+                Err(e) => {
+                    return handle_error(invalid(e), options.on_error, &mut Vec::new())
+                        .map(|()| None)
+                }
+            };
+            if found == *chunk_type {
+                let chunk = match Chunk::read_body_unchecked(length, found, &mut r) {
+                    Ok(chunk) => chunk,
+                    Err(_) if seen_iend => return Ok(None),
+                    Err(e) => {
+                        return handle_error(invalid(e), options.on_error, &mut Vec::new())
+                            .map(|()| None)
+                    }
+                };
+                if seen_iend {
+                    return Ok(chunk.verify_crc().then_some(chunk));
+                }
+                if check_crc(|| chunk.validate_crc(), invalid, options, &mut Vec::new())? {
+                    return Ok(Some(chunk));
+                }
+            } else {
+                let remaining = len.saturating_sub(chunk_offset as u64 + 8) as usize;
+                if remaining < length as usize + 4 {
+                    if seen_iend {
+                        return Ok(None);
+                    }
+                    let error = truncated_body(length as usize, remaining);
+                    return handle_error(invalid(error), options.on_error, &mut Vec::new())
+                        .map(|()| None);
+                }
+                r.seek(SeekFrom::Current(i64::from(length) + 4))?;
+            }
+            seen_iend |= found == "IEND";
+            chunk_offset += 12 + length as usize;
+            index += 1;
+        }
+    }
+
+    /// Inserts `chunks` right before the first IEND chunk of the PNG file at
+    /// `path`, copying the bytes before IEND as they are instead of parsing
+    /// them. The result is written next to `path` and renamed over it, so a
+    /// failed write leaves the file as it was. No CRCs are checked. Returns
+    /// `Ok(false)` without changing the file if it has no IEND or a chunk
+    /// header before it cannot be read, so that callers can fall back to
+    /// parsing the whole file.
+    pub fn insert_before_iend_in_file(path: &Path, chunks: &[Chunk]) -> PngResult<bool> {
+        let temp_path = temp_sibling(path)?;
+        if !Png::insert_before_iend_copying(path, &temp_path, chunks)? {
+            return Ok(false);
+        }
+        if let Err(e) = fs::rename(&temp_path, path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    /// Like `insert_before_iend_in_file`, but writes the result to `dest`
+    /// instead of replacing `src`. `dest` is not created when this returns
+    /// `Ok(false)`, and is removed again if writing it fails.
+    pub fn insert_before_iend_copying(
+        src: &Path,
+        dest: &Path,
+        chunks: &[Chunk],
+    ) -> PngResult<bool> {
+        let mut file = fs::File::open(src)?;
+        let Some(iend_offset) = find_iend_offset(io::BufReader::new(&mut file))? else {
+            return Ok(false);
+        };
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut write = || -> io::Result<()> {
+            let mut w = BufWriter::new(fs::File::create(dest)?);
+            io::copy(&mut (&mut file).take(iend_offset), &mut w)?;
+            for chunk in chunks {
+                chunk.write_to(&mut w)?;
+            }
+            // IEND and whatever follows it, usually just 12 bytes
+            io::copy(&mut file, &mut w)?;
+            w.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()
+        };
+        if let Err(e) = write() {
+            let _ = fs::remove_file(dest);
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    /// Appends a chunk to the end of this `Png` file's `Chunk` list.
+    pub fn append_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+
+    /// Appends every chunk in `chunks` to the end of this `Png` file's `Chunk`
+    /// list, in order.
+    pub fn append_chunks(&mut self, chunks: impl IntoIterator<Item = Chunk>) {
+        self.chunks.extend(chunks);
+    }
+
+    /// Inserts a chunk at position `index` in this `Png` file's `Chunk` list,
+    /// shifting all chunks after it to the right.
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> PngResult<()> {
+        if index > self.chunks.len() {
+            return Err(PngError::IndexOutOfBounds {
+                index,
+                len: self.chunks.len(),
+            });
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Replaces the first `Chunk` with the specified `target` type with `chunk`,
+    /// keeping its position, and returns the replaced `Chunk`.
+    pub fn replace_chunk(&mut self, target: &ChunkType, chunk: Chunk) -> PngResult<Chunk> {
+        match self.position_of(target) {
+            Some(idx) => Ok(std::mem::replace(&mut self.chunks[idx], chunk)),
+            None => Err(PngError::ChunkNotFound {
+                chunk_type: *target,
+            }),
+        }
+    }
+
+    /// Returns the index of the first `Chunk` with the specified `chunk_type`.
+    pub fn position_of(&self, chunk_type: &ChunkType) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type() == chunk_type)
+    }
+
+    /// Whether there is a chunk of type `chunk_type` holding exactly `data`.
+    /// Lengths and CRCs are compared first, so the data is only compared for
+    /// likely matches.
+    pub fn contains_identical(&self, chunk_type: &ChunkType, data: &[u8]) -> bool {
+        let crc = png_crc(chunk_type.as_bytes(), data);
+        self.chunks.iter().any(|chunk| {
+            chunk.chunk_type() == chunk_type
+                && chunk.data_len() == data.len()
+                && chunk.crc() == crc
+                && chunk.data() == data
+        })
+    }
+
+    /// Searches for a `Chunk` with the specified `chunk_type` and removes the first
+    /// matching `Chunk` from this `Png` list of chunks.
+    pub fn remove_chunk(&mut self, target: &ChunkType) -> PngResult<Chunk> {
+        self.remove_chunk_if_present(target)
+            .ok_or(PngError::ChunkNotFound {
+                chunk_type: *target,
+            })
+    }
+
+    /// Like `remove_chunk`, but returns `None` instead of an error when there
+    /// is no `Chunk` of type `target`
+    pub fn remove_chunk_if_present(&mut self, target: &ChunkType) -> Option<Chunk> {
+        let idx = self.position_of(target)?;
+        Some(self.chunks.remove(idx))
+    }
+
+    /// Returns the `Chunk` at position `index`, or `None` if it is out of
+    /// bounds. This is O(1).
+    pub fn chunk_at(&self, index: usize) -> Option<&Chunk> {
+        self.chunks.get(index)
+    }
+
+    /// Returns the `Chunk` at position `index` for in-place modification, or
+    /// `None` if it is out of bounds. This is O(1).
+    pub fn chunk_at_mut(&mut self, index: usize) -> Option<&mut Chunk> {
+        self.chunks.get_mut(index)
+    }
+
+    /// Removes and returns the `Chunk` at position `index`, or `None` if it is
+    /// out of bounds. This is O(n), as the chunks after it shift to the left.
+    pub fn remove_chunk_at(&mut self, index: usize) -> Option<Chunk> {
+        if index < self.chunks.len() {
+            Some(self.chunks.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Removes every `Chunk` with the specified `chunk_type`, returning how many
+    /// were removed. Unlike `remove_chunk`, finding none is not an error.
+    pub fn remove_all_of_type(&mut self, chunk_type: &ChunkType) -> usize {
+        self.retain_chunks_unchecked(|chunk| chunk.chunk_type() != chunk_type)
+            .len()
+    }
+
+    /// Returns a copy of this `Png` without any `Chunk` of the specified
+    /// `chunk_type`, leaving this one untouched.
+    pub fn clone_without_type(&self, chunk_type: &ChunkType) -> Png {
+        Png {
+            chunks: self
+                .chunks
+                .iter()
+                .filter(|chunk| chunk.chunk_type() != chunk_type)
+                .cloned()
+                .collect(),
+            trailing_data: self.trailing_data.clone(),
+        }
+    }
+
+    /// Removes every chunk with the same type and data as an earlier chunk,
+    /// returning how many were removed. Stored CRCs are not compared.
+    pub fn dedup_chunks(&mut self) -> usize {
+        let mut seen: HashSet<(ChunkType, Vec<u8>)> = HashSet::new();
+        self.retain_chunks_unchecked(|chunk| {
+            seen.insert((*chunk.chunk_type(), chunk.data().to_vec()))
+        })
+        .len()
+    }
+
+    /// Keeps only the first chunk of each type, returning how many were removed.
+    pub fn dedup_by_type(&mut self) -> usize {
+        let mut seen: HashSet<ChunkType> = HashSet::new();
+        self.retain_chunks_unchecked(|chunk| seen.insert(*chunk.chunk_type()))
+            .len()
+    }
+
+    /// Reorders the chunks of this `Png` into the canonical order recommended by
+    /// the PNG spec. Chunks of the same kind, such as consecutive `IDAT` chunks,
+    /// keep their original relative order.
+    pub fn sort_chunks_canonical(&mut self) {
+        self.chunks
+            .sort_by_key(|chunk| chunk.chunk_type().canonical_priority());
+    }
+
+    /// Moves chunks into an order the PNG spec allows: IHDR, chunks that must
+    /// precede PLTE, PLTE, the other chunks that must precede IDAT, the IDAT
+    /// chunks, then IEND. Other ancillary chunks stay on their side of PLTE and
+    /// the first IDAT, or move before IEND if they follow it. Chunks keep their
+    /// relative order within each group. Returns the chunks that moved back,
+    /// not the ones that only shifted to make room for them.
+    pub fn normalize_order(&mut self) -> Vec<Reordering> {
+        let first = |name: &[u8; 4]| {
+            self.chunks
+                .iter()
+                .position(|chunk| chunk.chunk_type().as_bytes() == name)
+        };
+        let (plte, idat) = (first(b"PLTE"), first(b"IDAT"));
+        let group = |index: usize, chunk: &Chunk| {
+            let name = chunk.chunk_type().as_bytes();
+            match name {
+                b"IHDR" => 0,
+                b"PLTE" => 2,
+                b"IDAT" => 4,
+                b"IEND" => 6,
+                _ if BEFORE_PLTE.contains(&name) => 1,
+                _ if AFTER_PLTE.contains(&name) || BEFORE_IDAT.contains(&name) => 3,
+                _ if idat.is_some_and(|idat| index > idat) => 5,
+                _ if plte.is_some_and(|plte| index > plte) => 3,
+                _ => 1,
+            }
+        };
+
+        let mut order: Vec<(u8, usize)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| (group(index, chunk), index))
+            .collect();
+        let mut moved_back = Vec::new();
+        let mut highest = 0;
+        for &(group, index) in &order {
+            if group < highest {
+                moved_back.push(index);
+            }
+            highest = highest.max(group);
+        }
+        order.sort_by_key(|&(group, _)| group);
+
+        let mut chunks: Vec<Option<Chunk>> = self.chunks.drain(..).map(Some).collect();
+        let mut reorderings = Vec::new();
+        for (to, &(_, from)) in order.iter().enumerate() {
+            let chunk = chunks[from].take().expect("each index appears once");
+            if moved_back.contains(&from) {
+                let chunk_type = *chunk.chunk_type();
+                reorderings.push(Reordering { chunk_type, from, to });
+            }
+            self.chunks.push(chunk);
+        }
+        reorderings.sort_by_key(|reordering| reordering.from);
+        reorderings
+    }
+
+    /// Checks that the chunks of this `Png` are laid out as the PNG spec requires,
+    /// returning every violated rule.
+    pub fn validate(&self) -> std::result::Result<(), Vec<StructureError>> {
+        let types: Vec<ChunkType> = self.chunks.iter().map(|chunk| *chunk.chunk_type()).collect();
+        validate_layout(&types)
+    }
+
+    /// Keeps only the chunks for which `f` returns `true` and returns the removed
+    /// chunks in their original order. `IHDR` and `IEND` are always kept, see
+    /// `retain_chunks_unchecked` to remove them too.
+    pub fn retain_chunks<F: FnMut(&Chunk) -> bool>(&mut self, mut f: F) -> Vec<Chunk> {
+        self.retain_chunks_unchecked(|chunk| {
+            chunk.is_ihdr() || chunk.is_iend() || f(chunk)
+        })
+    }
+
+    /// Like `retain_chunks`, but without protecting `IHDR` and `IEND`.
+    pub fn retain_chunks_unchecked<F: FnMut(&Chunk) -> bool>(&mut self, mut f: F) -> Vec<Chunk> {
+        let (kept, removed) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| f(chunk));
+        self.chunks = kept;
+        removed
+    }
+
+    /// The header of this PNG.
+    pub fn header(&self) -> &[u8; 8] {
+        &Self::STANDARD_HEADER
+    }
+
+    /// The signature this PNG starts with, always `PNG_SIGNATURE`
+    pub fn signature(&self) -> &[u8; 8] {
+        &PNG_SIGNATURE
+    }
+
+    /// Lists the `Chunk`s stored in this `Png`
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    /// Splits the `Chunk`s of this `Png` into `(critical, ancillary)` groups
+    pub fn partition_chunks(&self) -> (Vec<&Chunk>, Vec<&Chunk>) {
+        self.chunks
+            .iter()
+            .partition(|chunk| chunk.chunk_type().is_critical())
+    }
+
+    /// Consumes this `Png` and splits its `Chunk`s into `(critical, ancillary)` groups
+    pub fn into_partition(self) -> (Vec<Chunk>, Vec<Chunk>) {
+        self.chunks
+            .into_iter()
+            .partition(|chunk| chunk.chunk_type().is_critical())
+    }
+
+    /// Consumes this `Png` and splits its `Chunk`s into `(pre_idat, idat, post_idat)`,
+    /// where `idat` is the first run of consecutive `IDAT` chunks. Without any
+    /// `IDAT` chunk, every chunk ends up in `pre_idat`. Trailing data is dropped.
+    pub fn split_at_idat(self) -> (Vec<Chunk>, Vec<Chunk>, Vec<Chunk>) {
+        let mut pre = self.chunks;
+        let Some(start) = pre.iter().position(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+        else {
+            return (pre, Vec::new(), Vec::new());
+        };
+        let mut idat = pre.split_off(start);
+        let end = idat
+            .iter()
+            .position(|chunk| *chunk.chunk_type() != ChunkType::IDAT)
+            .unwrap_or(idat.len());
+        let post = idat.split_off(end);
+        (pre, idat, post)
+    }
+
+    /// Puts back together the parts returned by `split_at_idat`
+    pub fn reassemble_from_parts(pre: Vec<Chunk>, idat: Vec<Chunk>, post: Vec<Chunk>) -> Png {
+        let mut chunks = pre;
+        chunks.extend(idat);
+        chunks.extend(post);
+        Png::from_chunks(chunks)
+    }
+
+    /// Lists the critical `Chunk`s stored in this `Png`
+    pub fn critical_chunks(&self) -> Vec<&Chunk> {
+        self.iter_critical_chunks().collect()
+    }
+
+    /// Lists the ancillary `Chunk`s stored in this `Png`
+    pub fn ancillary_chunks(&self) -> Vec<&Chunk> {
+        self.iter_ancillary_chunks().collect()
+    }
+
+    /// Iterates over the critical `Chunk`s stored in this `Png` without
+    /// collecting them
+    pub fn iter_critical_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type().is_critical())
+    }
+
+    /// Iterates over the ancillary `Chunk`s stored in this `Png` without
+    /// collecting them
+    pub fn iter_ancillary_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| !chunk.chunk_type().is_critical())
+    }
+
+    /// Whether this `Png` has a critical `Chunk` of type `chunk_type`
+    pub fn has_critical_chunk(&self, chunk_type: &ChunkType) -> bool {
+        self.iter_critical_chunks()
+            .any(|chunk| chunk.chunk_type() == chunk_type)
+    }
+
+    /// Whether this `Png` has an ancillary `Chunk` of type `chunk_type`
+    pub fn has_ancillary_chunk(&self, chunk_type: &ChunkType) -> bool {
+        self.iter_ancillary_chunks()
+            .any(|chunk| chunk.chunk_type() == chunk_type)
+    }
+
+    /// The bytes found after `IEND` that are not chunks, written back out
+    /// after the last chunk
+    pub fn trailing_data(&self) -> &[u8] {
+        &self.trailing_data
+    }
+
+    /// Removes and returns the bytes found after `IEND`
+    pub fn take_trailing_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.trailing_data)
+    }
+
+    /// Replaces the bytes written after the last chunk
+    pub fn set_trailing_data(&mut self, trailing_data: Vec<u8>) {
+        self.trailing_data = trailing_data;
+    }
+
+    /// Lists the `Chunk`s stored in this `Png` for in-place modification
+    pub fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self.chunks
+    }
+
+    /// The total data length of the ancillary chunks whose type the PNG spec
+    /// does not define, such as the ones `pngme encode` adds
+    pub fn custom_data_len(&self) -> usize {
+        self.iter_ancillary_chunks()
+            .filter(|chunk| !chunk.chunk_type().is_known_standard())
+            .map(Chunk::data_len)
+            .sum()
+    }
+
+    /// How many bytes of data fit in new chunks of at most `chunk_size` data
+    /// bytes each, when those chunks may take up `budget` bytes in total.
+    /// Each chunk costs 12 bytes on top of its data.
+    pub fn chunked_capacity(budget: usize, chunk_size: usize) -> usize {
+        const OVERHEAD: usize = 12;
+        let chunk_size = chunk_size.clamp(1, crate::chunk::MAX_LENGTH as usize);
+        let full = budget / (chunk_size + OVERHEAD);
+        let rest = budget % (chunk_size + OVERHEAD);
+        full * chunk_size + rest.saturating_sub(OVERHEAD)
+    }
+
+    /// The size in bytes of this `Png` once written out, header and trailing
+    /// data included
+    pub fn serialized_size(&self) -> usize {
+        self.header().len()
+            + self
+                .chunks
+                .iter()
+                .map(Chunk::serialized_len)
+                .sum::<usize>()
+            + self.trailing_data.len()
+    }
+
+    /// Lists where each `Chunk` lives in the bytes `as_bytes` would return, in
+    /// the same order as `chunks`. For a `Png` parsed from a file and not
+    /// edited since, these are the offsets in that file. Computed on each call
+    /// so they always match the current chunk list.
+    pub fn chunk_spans(&self) -> Vec<ChunkSpan> {
+        let mut offset = self.header().len() as u64;
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                let span = ChunkSpan {
+                    offset,
+                    total_len: chunk.serialized_len() as u64,
+                };
+                offset += span.total_len;
+                span
+            })
+            .collect()
+    }
+
+    /// The number of `Chunk`s stored in this `Png`
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Whether this `Png` has no `Chunk`s at all
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Searches for a `Chunk` with the specified `chunk_type` and returns the first
+    /// matching `Chunk` from this `Png`.
+    pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        self.chunks
+            .iter()
+            .find(|chunk| *chunk.chunk_type() == chunk_type)
+    }
+
+    /// Returns the `n`th (0-indexed) `Chunk` of type `chunk_type`, or `None` if
+    /// this `Png` has `n` or fewer of them
+    pub fn nth_chunk_of_type(&self, chunk_type: &ChunkType, n: usize) -> Option<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == chunk_type)
+            .nth(n)
+    }
+
+    /// Returns every `Chunk` for which `predicate` returns `true`, in order
+    pub fn find_chunks_where<F>(&self, predicate: F) -> Vec<&Chunk>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.chunks.iter().filter(|chunk| predicate(chunk)).collect()
+    }
+
+    /// Returns the first `Chunk` for which `predicate` returns `true`
+    pub fn find_chunk_where<F>(&self, predicate: F) -> Option<&Chunk>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.chunks.iter().find(|chunk| predicate(chunk))
+    }
+
+    /// Same as `find_chunks_where`, for in-place modification
+    pub fn find_chunks_where_mut<F>(&mut self, predicate: F) -> Vec<&mut Chunk>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.chunks.iter_mut().filter(|chunk| predicate(chunk)).collect()
+    }
+
+    /// Same as `find_chunk_where`, for in-place modification
+    pub fn find_chunk_where_mut<F>(&mut self, predicate: F) -> Option<&mut Chunk>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.chunks.iter_mut().find(|chunk| predicate(chunk))
+    }
+
+    /// Returns this `Png` as a byte sequence.
+    /// These bytes will contain the header followed by the bytes of all of the chunks.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.write_to(&mut bytes)
+            .expect("Png: Writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Like `as_bytes`, but consumes this `Png`. Each chunk is dropped as
+    /// soon as it has been copied, so at most one chunk's data is held twice.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        bytes.extend_from_slice(self.header());
+        for chunk in self.chunks {
+            chunk
+                .write_to(&mut bytes)
+                .expect("Png: Writing to a Vec cannot fail");
+        }
+        bytes.extend_from_slice(&self.trailing_data);
+        bytes
+    }
+
+    /// Writes this `Png` to the file at `path`, replacing any existing file
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let file = fs::File::create(path)
+            .with_context(|| format!("Png: Failed to create file {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("Png: Failed to write file {}", path.display()))
+    }
+
+    /// Like `write_to_file`, but writes to a temporary file next to `path` first
+    /// and then renames it, so `path` never holds a partially written PNG.
+    pub fn write_to_file_atomic(&self, path: &Path) -> Result<()> {
+        let temp_path = temp_sibling(path)?;
+        if let Err(e) = self.write_to_file(&temp_path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Png: Failed to replace file {}", path.display()))
+    }
+
+    /// Writes the header followed by every chunk and the trailing data of this
+    /// `Png` to `w`, returning the number of bytes written.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<usize> {
+        w.write_all(self.header())?;
+        let mut written = self.header().len();
+        for chunk in &self.chunks {
+            written += chunk.write_to(w)?;
+        }
+        w.write_all(&self.trailing_data)?;
+        Ok(written + self.trailing_data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::convert::TryFrom;
+
+    fn testing_chunks() -> Vec<Chunk> {
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
+    }
+
+    fn testing_png() -> Png {
+        let chunks = testing_chunks();
+        Png::from_chunks(chunks)
+    }
+
+    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let data: Vec<u8> = data.bytes().collect();
+
+        Ok(Chunk::new(chunk_type, data))
+    }
+
+    #[test]
+    fn test_from_chunks() {
+        let chunks = testing_chunks();
+        let png = Png::from_chunks(chunks);
+
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_valid_from_bytes() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_slice());
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_slice());
+
+        assert!(matches!(
+            png,
+            Err(PngError::InvalidSignature {
+                found: [13, 80, 78, 71, 13, 10, 26, 10],
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_header_is_valid() {
+        assert!(Png::header_is_valid(&PNG_SIGNATURE));
+        assert!(Png::header_is_valid(&testing_png().as_bytes()));
+        assert_eq!(testing_png().signature(), &PNG_SIGNATURE);
+
+        assert!(!Png::header_is_valid(&[]));
+        assert!(!Png::header_is_valid(&PNG_SIGNATURE[..7]));
+        for idx in 0..PNG_SIGNATURE.len() {
+            let mut bytes = PNG_SIGNATURE;
+            bytes[idx] ^= 0x20;
+            assert!(!Png::header_is_valid(&bytes), "accepted a change to byte {}", idx);
+        }
+        assert!(!Png::header_is_valid(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F']));
+    }
+
+    #[test]
+    fn test_truncated_signature() {
+        let png = Png::try_from(&Png::STANDARD_HEADER[..5]);
+
+        assert!(matches!(png, Err(PngError::TruncatedSignature { found: 5 })));
+    }
+
+    #[test]
+    fn test_jpeg_signature() {
+        let bytes = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00];
+
+        let error = Png::try_from(&bytes[..]).unwrap_err();
+
+        assert!(matches!(error, PngError::InvalidSignature { guess: Some("JPEG"), .. }));
+        assert_eq!(
+            error.to_string(),
+            "Png: Invalid signature, expected 89 50 4E 47 0D 0A 1A 0A but found \
+             FF D8 FF E0 00 10 4A 46 (this looks like a JPEG file)"
+        );
+    }
+
+    #[test]
+    fn test_guess_format() {
+        assert_eq!(guess_format(b"GIF89a\x01\x00"), Some("GIF"));
+        assert_eq!(guess_format(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("WebP"));
+        assert_eq!(guess_format(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(guess_format(b"Hello, world!\n"), Some("plain text"));
+        assert_eq!(guess_format(&[0, 1, 2, 3, 4, 5, 6, 7]), None);
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let error = Png::try_from(&[][..]).unwrap_err();
+
+        assert!(matches!(error, PngError::EmptyInput));
+        assert_eq!(error.to_string(), "Png: Input is empty, expected a PNG signature");
+    }
+
+    #[test]
+    fn test_four_byte_input() {
+        let error = Png::try_from(&Png::STANDARD_HEADER[..4]).unwrap_err();
+
+        assert!(matches!(error, PngError::TruncatedSignature { found: 4 }));
+        assert_eq!(
+            error.to_string(),
+            "Png: Input is only 4 bytes long, too short for the 8 byte PNG signature"
+        );
+    }
+
+    #[test]
+    fn test_invalid_chunk() {
+        let mut chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        #[rustfmt::skip]
+        let mut bad_chunk = vec![
+            0, 0, 0, 5,         // length
+            32, 117, 83, 116,   // Chunk Type (bad)
+            65, 64, 65, 66, 67, // Data
+            1, 2, 3, 4, 5       // CRC (bad)
+        ];
+
+        chunk_bytes.append(&mut bad_chunk);
+
+        let png = Png::try_from(chunk_bytes.as_slice());
+
+        assert!(png.is_err());
+    }
+
+    #[test]
+    fn test_invalid_chunk_after_valid_chunks() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(&[0, 0, 0, 5, 32, 117, 83, 116, 65, 64, 65, 66, 67, 1, 2, 3, 4]);
+
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_crc_mismatch_error_message() {
+        let mut bytes = testing_png().as_bytes();
+        // Flip a bit in the CRC of the second chunk
+        let crc_offset = 8 + 32 + 30 - 1;
+        bytes[crc_offset] ^= 1;
+
+        let error = Png::try_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(error.to_string(), "Png: Invalid chunk 1 at offset 40");
+
+        let chunk = &testing_chunks()[1];
+        match error {
+            PngError::InvalidChunk { source, .. } => match *source {
+                PngError::CrcMismatch {
+                    chunk_type,
+                    expected,
+                    actual,
+                } => {
+                    assert_eq!(chunk_type, "miDl");
+                    assert_eq!(expected, chunk.crc());
+                    assert_eq!(actual, chunk.crc() ^ 1);
+                }
+                other => panic!("expected a CRC mismatch, got {:?}", other),
+            },
+            other => panic!("expected an invalid chunk, got {:?}", other),
+        }
+    }
+
+    /// The bytes of `testing_png` with a bad CRC on its middle chunk
+    fn bad_crc_png_bytes() -> Vec<u8> {
+        let mut bytes = testing_png().as_bytes();
+        bytes[8 + 32 + 30 - 1] ^= 1;
+        bytes
+    }
+
+    #[test]
+    fn test_parse_with_on_error() {
+        let bytes = bad_crc_png_bytes();
+        let with = |on_error| ParseOptions {
+            verify_crc: true,
+            on_error,
+        };
+
+        let error = Png::parse_with(&bytes, with(OnError::Fail)).unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunk { index: 1, .. }));
+
+        let (png, errors) = Png::parse_with(&bytes, with(OnError::Skip)).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "LASt"]);
+        assert!(errors.is_empty());
+
+        let (png, errors) = Png::parse_with(&bytes, with(OnError::Collect)).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+        assert!(!png.chunks()[1].verify_crc());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], PngError::InvalidChunk { index: 1, offset: 40, .. }));
+
+        let reader_result = Png::from_reader_with(bytes.as_slice(), with(OnError::Collect));
+        let (png, errors) = reader_result.unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+        assert!(matches!(errors[0], PngError::InvalidChunk { index: 1, offset: 40, .. }));
+    }
+
+    #[test]
+    fn test_parse_without_verifying_crc() {
+        let bytes = bad_crc_png_bytes();
+        let options = ParseOptions {
+            verify_crc: false,
+            ..ParseOptions::default()
+        };
+
+        let (png, errors) = Png::parse_with(&bytes, options).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+        assert!(errors.is_empty());
+        let (png, _) = Png::from_reader_with(bytes.as_slice(), options).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+
+        let midl = ChunkType::from_str("miDl").unwrap();
+        let found = Png::find_chunk_in_reader_with(io::Cursor::new(&bytes), &midl, options);
+        assert_eq!(found.unwrap().unwrap().data(), b"I am another chunk");
+        assert!(Png::find_chunk_in_reader(io::Cursor::new(&bytes), &midl).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_stops_at_unreadable_chunk() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(&[0, 0, 0, 50, b'b', b'r', b'K', b'n', 1, 2]);
+        let options = ParseOptions {
+            verify_crc: true,
+            on_error: OnError::Collect,
+        };
+
+        let (png, errors) = Png::parse_with(&bytes, options).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+        assert_eq!(png.trailing_data().len(), 10);
+        assert!(matches!(errors[0], PngError::InvalidChunk { index: 3, .. }));
+
+        let (png, errors) = Png::from_reader_with(bytes.as_slice(), options).unwrap();
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_chunk_claiming_huge_length() {
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(&[0x7F, 0xFF, 0xFF, 0xFF])
+            .chain(b"IDAT")
+            .chain(&[0, 0, 0, 0])
+            .copied()
+            .collect();
+
+        let error = Png::try_from(bytes.as_slice()).unwrap_err();
+
+        match error {
+            PngError::InvalidChunk { index: 0, offset: 8, source } => assert!(matches!(
+                *source,
+                PngError::TruncatedChunk {
+                    offset: 8,
+                    field: "data",
+                    expected: 2147483647,
+                    found: 4
+                }
+            )),
+            other => panic!("expected an invalid chunk, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_chunks_after_iend() {
+        let mut png = testing_png();
+        png.insert_chunk(2, chunk_from_strings("IEND", "").unwrap())
+            .unwrap();
+
+        let mut bytes = png.as_bytes();
+        let parsed = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(chunk_types(&parsed), ["FrSt", "miDl", "IEND", "LASt"]);
+
+        bytes.extend_from_slice(b"not a chunk");
+        let parsed = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed.trailing_data(), b"not a chunk");
+    }
+
+    #[test]
+    fn test_trailing_data_round_trip() {
+        let mut bytes = PNG_FILE.to_vec();
+        let garbage: Vec<u8> = (0..1024).map(|i| (i * 7 % 251) as u8).collect();
+        bytes.extend_from_slice(&garbage);
+
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(png.trailing_data(), garbage.as_slice());
+        assert_eq!(png.serialized_size(), bytes.len());
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_take_trailing_data() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes.extend_from_slice(&[0xAB; 1024]);
+        let mut png = Png::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(png.take_trailing_data(), vec![0xAB; 1024]);
+        assert!(png.trailing_data().is_empty());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_list_chunks() {
+        let png = testing_png();
+        let chunks = png.chunks();
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_chunk_by_type() {
+        let png = testing_png();
+        let chunk = png.chunk_by_type("FrSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "FrSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
+    }
+
+    #[test]
+    fn test_nth_chunk_of_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("FrSt", "I am the second FrSt").unwrap());
+        let frst = ChunkType::from_str("FrSt").unwrap();
+
+        let first = png.nth_chunk_of_type(&frst, 0).unwrap();
+        assert_eq!(&first.data_as_string().unwrap(), "I am the first chunk");
+        let second = png.nth_chunk_of_type(&frst, 1).unwrap();
+        assert_eq!(&second.data_as_string().unwrap(), "I am the second FrSt");
+        assert!(png.nth_chunk_of_type(&frst, 2).is_none());
+
+        let view_bytes = png.as_bytes();
+        let view = PngView::parse(&view_bytes).unwrap();
+        let second = view.nth_chunk_of_type(&frst, 1).unwrap();
+        assert_eq!(second.data(), b"I am the second FrSt");
+        assert!(view.nth_chunk_of_type(&frst, 2).is_none());
+    }
+
+    #[test]
+    fn test_find_chunks_where() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("teXt", "Author: Ferris").unwrap());
+        png.append_chunk(chunk_from_strings("teXt", "Title: Dice").unwrap());
+        png.append_chunk(chunk_from_strings("zeXt", "Author: Corro").unwrap());
+        let by_author = |chunk: &Chunk| chunk.data().starts_with(b"Author:");
+
+        let found = png.find_chunks_where(by_author);
+        assert_eq!(found.len(), 2);
+        assert_eq!(&found[0].data_as_string().unwrap(), "Author: Ferris");
+        assert_eq!(&found[1].data_as_string().unwrap(), "Author: Corro");
+        let first = png.find_chunk_where(|chunk| chunk.data_as_string_lossy().contains("Dice"));
+        assert_eq!(first.unwrap().chunk_type(), "teXt");
+        assert!(png.find_chunk_where(|chunk| chunk.data().is_empty()).is_none());
+        assert!(png.find_chunks_where(|chunk| chunk.data().is_empty()).is_empty());
+
+        for chunk in png.find_chunks_where_mut(by_author) {
+            chunk.map_data(|data| data.to_ascii_uppercase());
+        }
+        png.find_chunk_where_mut(|chunk| chunk.chunk_type() == "LASt")
+            .unwrap()
+            .set_data(b"edited".to_vec());
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt", "teXt", "teXt", "zeXt"]);
+        assert_eq!(png[2].data(), b"edited");
+        assert_eq!(png[3].data(), b"AUTHOR: FERRIS");
+        assert_eq!(png[4].data(), b"Title: Dice");
+        assert_eq!(png[5].data(), b"AUTHOR: CORRO");
+        assert!(png.chunks().iter().all(Chunk::verify_crc));
+    }
+
+    #[test]
+    fn test_append_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let chunk = png.chunk_by_type("TeSt").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "TeSt");
+        assert_eq!(&chunk.data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    fn test_append_chunks() {
+        let mut png = testing_png();
+        png.append_chunks(vec![
+            chunk_from_strings("TeSt", "one").unwrap(),
+            chunk_from_strings("ruSt", "two").unwrap(),
+        ]);
+        assert_eq!(png.len(), 5);
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt", "TeSt", "ruSt"]);
+
+        let other = Png::from_chunks(vec![
+            chunk_from_strings("saFe", "kept").unwrap(),
+            chunk_from_strings("unSF", "dropped").unwrap(),
+            chunk_from_strings("alSo", "kept too").unwrap(),
+        ]);
+        png.extend(other.into_iter().filter(|chunk| chunk.chunk_type().is_safe_to_copy()));
+        assert_eq!(png.len(), 7);
+        assert_eq!(&chunk_types(&png)[5..], ["saFe", "alSo"]);
+        assert_eq!(&png[6].data_as_string().unwrap(), "kept too");
+    }
+
+    #[test]
+    fn test_remove_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        let removed = png
+            .remove_chunk(&ChunkType::from_str("TeSt").unwrap())
+            .unwrap();
+        assert_eq!(&removed.data_as_string().unwrap(), "Message");
+        let chunk = png.chunk_by_type("TeSt");
+        assert!(chunk.is_none());
+    }
+
+    #[test]
+    fn test_remove_missing_chunk() {
+        let mut png = testing_png();
+        let result = png.remove_chunk(&ChunkType::from_str("TeSt").unwrap());
+        assert!(matches!(result, Err(PngError::ChunkNotFound { chunk_type }) if chunk_type == "TeSt"));
+        assert_eq!(png.len(), 3);
+    }
+
+    fn reparse(png: &Png) -> Png {
+        Png::try_from(png.as_bytes().as_slice()).unwrap()
+    }
+
+    fn chunk_types(png: &Png) -> Vec<String> {
+        png.chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+
+        let png = reparse(&png);
+        assert_eq!(chunk_types(&png), ["FrSt", "TeSt", "miDl", "LASt"]);
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    fn test_insert_chunk_at_end() {
+        let mut png = testing_png();
+        png.insert_chunk(3, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+
+        let png = reparse(&png);
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt", "TeSt"]);
+    }
+
+    #[test]
+    fn test_insert_chunk_out_of_bounds() {
+        let mut png = testing_png();
+        let result = png.insert_chunk(4, chunk_from_strings("TeSt", "Message").unwrap());
+
+        assert!(matches!(result, Err(PngError::IndexOutOfBounds { index: 4, len: 3 })));
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_replace_chunk() {
+        let mut png = testing_png();
+        let target = ChunkType::from_str("miDl").unwrap();
+        let old = png
+            .replace_chunk(&target, chunk_from_strings("TeSt", "Message").unwrap())
+            .unwrap();
+
+        assert_eq!(&old.data_as_string().unwrap(), "I am another chunk");
+        let png = reparse(&png);
+        assert_eq!(chunk_types(&png), ["FrSt", "TeSt", "LASt"]);
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "Message");
+    }
+
+    #[test]
+    fn test_replace_missing_chunk() {
+        let mut png = testing_png();
+        let target = ChunkType::from_str("NoPe").unwrap();
+        let result = png.replace_chunk(&target, chunk_from_strings("TeSt", "Message").unwrap());
+
+        assert!(matches!(result, Err(PngError::ChunkNotFound { .. })));
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+    }
+
+    #[test]
+    fn test_position_of() {
+        let png = testing_png();
+
+        assert_eq!(png.position_of(&ChunkType::from_str("LASt").unwrap()), Some(2));
+        assert_eq!(png.position_of(&ChunkType::from_str("NoPe").unwrap()), None);
+    }
+
+    #[test]
+    fn test_contains_identical() {
+        let png = testing_png();
+        let last = ChunkType::from_str("LASt").unwrap();
+        let data = png.chunk_by_type("LASt").unwrap().data().to_vec();
+
+        assert!(png.contains_identical(&last, &data));
+        assert!(!png.contains_identical(&last, &data[1..]));
+        assert!(!png.contains_identical(&ChunkType::from_str("laSt").unwrap(), &data));
+        let mut changed = data.clone();
+        changed[0] ^= 1;
+        assert!(!png.contains_identical(&last, &changed));
+    }
+
+    #[test]
+    fn test_len() {
+        let png = testing_png();
+        assert_eq!(png.len(), 3);
+        assert!(!png.is_empty());
+        assert!(Png::from_chunks(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let png = testing_png();
+
+        let borrowed: Vec<String> = (&png)
+            .into_iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(borrowed, ["FrSt", "miDl", "LASt"]);
+
+        let owned: Vec<Chunk> = png.into_iter().collect();
+        assert_eq!(owned.len(), 3);
+        assert_eq!(&owned[2].data_as_string().unwrap(), "I am the last chunk");
+    }
+
+    #[test]
+    fn test_chunks_mut() {
+        let mut png = testing_png();
+        let original_crc = png.chunks()[1].crc();
+
+        png.chunks_mut()[1].set_data(b"I have been changed".to_vec());
+
+        let png = reparse(&png);
+        assert_eq!(png.len(), 3);
+        assert_ne!(png.chunks()[1].crc(), original_crc);
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "I have been changed");
+    }
+
+    #[test]
+    fn test_sort_chunks_canonical() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IDAT", "first").unwrap(),
+            chunk_from_strings("tEXt", "text").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+            chunk_from_strings("ruSt", "custom").unwrap(),
+            chunk_from_strings("PLTE", "palette").unwrap(),
+            chunk_from_strings("IDAT", "second").unwrap(),
+            chunk_from_strings("tRNS", "transparency").unwrap(),
+            chunk_from_strings("gAMA", "gamma").unwrap(),
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "third").unwrap(),
+        ]);
+
+        png.sort_chunks_canonical();
+
+        assert_eq!(
+            chunk_types(&png),
+            ["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "IDAT", "tEXt", "ruSt", "IEND"]
+        );
+        let idat_data: Vec<String> = png
+            .chunks()
+            .iter()
+            .filter(|chunk| *chunk.chunk_type() == "IDAT")
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+        assert_eq!(idat_data, ["first", "second", "third"]);
+    }
+
+    fn png_of(chunks: &[(&str, &str)]) -> Png {
+        let chunks = chunks
+            .iter()
+            .map(|&(chunk_type, data)| chunk_from_strings(chunk_type, data).unwrap())
+            .collect();
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_normalize_order() {
+        let mut png = png_of(&[
+            ("IHDR", "header"),
+            ("tEXt", "before"),
+            ("PLTE", "palette"),
+            ("IDAT", "first"),
+            ("ruSt", "between"),
+            ("IDAT", "second"),
+            ("gAMA", "gamma"),
+            ("IEND", ""),
+            ("tRNS", "transparency"),
+            ("tEXt", "appended"),
+        ]);
+
+        let reorderings = png.normalize_order();
+
+        assert_eq!(
+            chunk_types(&png),
+            ["IHDR", "tEXt", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "ruSt", "tEXt", "IEND"]
+        );
+        let data: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+        assert_eq!(data[1], "before");
+        assert_eq!(data[5..7], ["first", "second"]);
+        assert_eq!(data[7..9], ["between", "appended"]);
+        let moved: Vec<(String, usize, usize)> = reorderings
+            .iter()
+            .map(|r| (r.chunk_type.to_string(), r.from, r.to))
+            .collect();
+        assert_eq!(
+            moved,
+            [
+                ("IDAT".to_string(), 5, 6),
+                ("gAMA".to_string(), 6, 2),
+                ("tRNS".to_string(), 8, 4),
+                ("tEXt".to_string(), 9, 8),
+            ]
+        );
+
+        assert!(png.validate().is_ok());
+        let reparsed = reparse(&png);
+        assert_eq!(chunk_types(&reparsed), chunk_types(&png));
+        assert_eq!(reparsed.as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_normalize_order_without_plte() {
+        let mut png = png_of(&[
+            ("IDAT", "first"),
+            ("IHDR", "header"),
+            ("pHYs", "pixels"),
+            ("IDAT", "second"),
+            ("sRGB", "srgb"),
+            ("IEND", ""),
+        ]);
+
+        let reorderings = png.normalize_order();
+
+        assert_eq!(chunk_types(&png), ["IHDR", "sRGB", "pHYs", "IDAT", "IDAT", "IEND"]);
+        assert_eq!(reorderings.len(), 3);
+        assert_eq!(reorderings[0].to_string(), "moved IHDR from chunk 1 to chunk 0");
+        assert!(reparse(&png).validate().is_ok());
+    }
+
+    #[test]
+    fn test_normalize_order_leaves_valid_order_alone() {
+        let mut png = testing_png();
+        png.insert_chunk(0, chunk_from_strings("IHDR", "header").unwrap()).unwrap();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        let before = png.as_bytes();
+
+        assert!(png.normalize_order().is_empty());
+        assert_eq!(png.as_bytes(), before);
+    }
+
+    #[test]
+    fn test_retain_chunks() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("tEXt", "first").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("ruSt", "second").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+
+        let removed = png.retain_chunks(|chunk| chunk.chunk_type().is_critical());
+
+        assert_eq!(chunk_types(&png), ["IHDR", "IDAT", "IEND"]);
+        let removed: Vec<String> = removed
+            .iter()
+            .map(|chunk| chunk.data_as_string().unwrap())
+            .collect();
+        assert_eq!(removed, ["first", "second"]);
+    }
+
+    #[test]
+    fn test_retain_chunks_protects_ihdr_and_iend() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+
+        let removed = png.retain_chunks(|_| false);
+
+        assert_eq!(chunk_types(&png), ["IHDR", "IEND"]);
+        assert_eq!(removed.len(), 1);
+    }
+
+    #[test]
+    fn test_retain_chunks_unchecked() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+
+        let removed = png.retain_chunks_unchecked(|_| false);
+
+        assert!(png.is_empty());
+        let removed: Vec<String> = removed
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(removed, ["IHDR", "IDAT", "IEND"]);
+    }
+
+    #[test]
+    fn test_dedup_chunks_without_duplicates() {
+        let mut png = testing_png();
+        assert_eq!(png.dedup_chunks(), 0);
+        assert_eq!(png.len(), 3);
+    }
+
+    #[test]
+    fn test_dedup_chunks() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("ruSt", "one").unwrap(),
+            chunk_from_strings("ruSt", "two").unwrap(),
+            chunk_from_strings("ruSt", "one").unwrap(),
+            chunk_from_strings("teSt", "one").unwrap(),
+            chunk_from_strings("ruSt", "two").unwrap(),
+            chunk_from_strings("ruSt", "one").unwrap(),
+        ]);
+
+        assert_eq!(png.dedup_chunks(), 3);
+
+        let remaining: Vec<(String, String)> = png
+            .chunks()
+            .iter()
+            .map(|chunk| (chunk.chunk_type().to_string(), chunk.data_as_string().unwrap()))
+            .collect();
+        assert_eq!(
+            remaining,
+            [
+                ("ruSt".to_string(), "one".to_string()),
+                ("ruSt".to_string(), "two".to_string()),
+                ("teSt".to_string(), "one".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedup_by_type() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("ruSt", "one").unwrap(),
+            chunk_from_strings("teSt", "one").unwrap(),
+            chunk_from_strings("ruSt", "two").unwrap(),
+            chunk_from_strings("teSt", "three").unwrap(),
+        ]);
+
+        assert_eq!(png.dedup_by_type(), 2);
+        assert_eq!(chunk_types(&png), ["ruSt", "teSt"]);
+        assert_eq!(&png.chunks()[0].data_as_string().unwrap(), "one");
+    }
+
+    #[test]
+    fn test_chunk_at() {
+        let mut png = testing_png();
+
+        assert_eq!(png.chunk_at(1).unwrap().chunk_type(), "miDl");
+        assert!(png.chunk_at(3).is_none());
+        assert_eq!(png[2].chunk_type(), "LASt");
+
+        png.chunk_at_mut(0).unwrap().set_data(b"changed".to_vec());
+        assert_eq!(png[0].data(), b"changed");
+        assert!(png.chunk_at_mut(3).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Png: Chunk index 3 is out of bounds for 3 chunks")]
+    fn test_index_out_of_bounds() {
+        let png = testing_png();
+        let _ = &png[3];
+    }
+
+    #[test]
+    fn test_index_standard_png() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png[0].chunk_type(), "IHDR");
+
+        let last = png.len() - 1;
+        png[last].set_data(b"changed".to_vec());
+        assert_eq!(png[last].data(), b"changed");
+        assert!(png[last].verify_crc());
+    }
+
+    #[test]
+    fn test_index_mut_out_of_bounds_is_caught() {
+        let result = std::panic::catch_unwind(|| {
+            let mut png = testing_png();
+            png[10].set_data(Vec::new());
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_remove_chunk_if_present() {
+        let mut png = testing_png();
+        let missing = ChunkType::from_str("TeSt").unwrap();
+        assert!(png.remove_chunk_if_present(&missing).is_none());
+        assert_eq!(png.len(), 3);
+
+        let removed = png.remove_chunk_if_present(&ChunkType::from_str("miDl").unwrap());
+        assert_eq!(removed.unwrap().chunk_type(), "miDl");
+        assert_eq!(chunk_types(&png), ["FrSt", "LASt"]);
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+
+        let removed = png.remove_chunk_at(1).unwrap();
+        assert_eq!(removed.chunk_type(), "miDl");
+        assert_eq!(chunk_types(&png), ["FrSt", "LASt"]);
+        assert!(png.remove_chunk_at(2).is_none());
+        assert_eq!(png.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_all_of_type() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("ruSt", "one").unwrap(),
+            chunk_from_strings("teSt", "one").unwrap(),
+            chunk_from_strings("ruSt", "two").unwrap(),
+        ]);
+
+        assert_eq!(png.remove_all_of_type(&ChunkType::from_str("ruSt").unwrap()), 2);
+        assert_eq!(chunk_types(&png), ["teSt"]);
+        assert_eq!(png.remove_all_of_type(&ChunkType::from_str("ruSt").unwrap()), 0);
+        assert_eq!(chunk_types(&png), ["teSt"]);
+    }
+
+    #[test]
+    fn test_clone_without_type() {
+        let png = testing_png();
+
+        let stripped = png.clone_without_type(&ChunkType::from_str("miDl").unwrap());
+        assert_eq!(chunk_types(&stripped), ["FrSt", "LASt"]);
+        assert_eq!(chunk_types(&png), ["FrSt", "miDl", "LASt"]);
+
+        let unchanged = png.clone_without_type(&ChunkType::from_str("NoPe").unwrap());
+        assert_eq!(unchanged.as_bytes(), png.as_bytes());
+    }
+
+    fn mixed_png() -> Png {
+        Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("tEXt", "text").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ])
+    }
+
+    fn types_of(chunks: &[&Chunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_partition_chunks() {
+        let png = mixed_png();
+        let (critical, ancillary) = png.partition_chunks();
+
+        assert_eq!(types_of(&critical), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(types_of(&ancillary), ["tEXt"]);
+        assert_eq!(types_of(&png.critical_chunks()), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(types_of(&png.ancillary_chunks()), ["tEXt"]);
+    }
+
+    #[test]
+    fn test_iter_critical_and_ancillary_chunks() {
+        let png = mixed_png();
+        let critical: Vec<&Chunk> = png.iter_critical_chunks().collect();
+        let ancillary: Vec<&Chunk> = png.iter_ancillary_chunks().collect();
+
+        assert_eq!(types_of(&critical), ["IHDR", "IDAT", "IEND"]);
+        assert_eq!(types_of(&ancillary), ["tEXt"]);
+        assert_eq!(png.iter_critical_chunks().count() + png.iter_ancillary_chunks().count(), 4);
+    }
+
+    #[test]
+    fn test_has_critical_and_ancillary_chunk() {
+        let png = mixed_png();
+        let idat = ChunkType::from_str("IDAT").unwrap();
+        let text = ChunkType::from_str("tEXt").unwrap();
+        let time = ChunkType::from_str("tIME").unwrap();
+
+        assert!(png.has_critical_chunk(&idat));
+        assert!(!png.has_ancillary_chunk(&idat));
+        assert!(png.has_ancillary_chunk(&text));
+        assert!(!png.has_critical_chunk(&text));
+        assert!(!png.has_ancillary_chunk(&time));
+    }
+
+    #[test]
+    fn test_into_partition() {
+        let (critical, ancillary) = mixed_png().into_partition();
+
+        assert_eq!(critical.len(), 3);
+        assert_eq!(ancillary.len(), 1);
+        assert_eq!(&ancillary[0].data_as_string().unwrap(), "text");
+    }
+
+    #[test]
+    fn test_split_at_idat() {
+        let png = png_of(&[
+            ("IHDR", "header"),
+            ("tEXt", "before"),
+            ("IDAT", "first"),
+            ("IDAT", "second"),
+            ("tEXt", "after"),
+            ("IDAT", "stray"),
+            ("IEND", ""),
+        ]);
+        let bytes = png.as_bytes();
+
+        let (pre, idat, post) = png.split_at_idat();
+
+        let types = |chunks: &[Chunk]| -> Vec<String> {
+            chunks.iter().map(|chunk| chunk.chunk_type().to_string()).collect()
+        };
+        assert_eq!(types(&pre), ["IHDR", "tEXt"]);
+        assert_eq!(types(&idat), ["IDAT", "IDAT"]);
+        assert_eq!(types(&post), ["tEXt", "IDAT", "IEND"]);
+        assert_eq!(Png::reassemble_from_parts(pre, idat, post).as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_split_at_idat_without_idat() {
+        let png = testing_png();
+        let len = png.len();
+
+        let (pre, idat, post) = png.split_at_idat();
+
+        assert_eq!(pre.len(), len);
+        assert!(idat.is_empty() && post.is_empty());
+        let png = Png::reassemble_from_parts(pre, idat, post);
+        assert_eq!(png.as_bytes(), testing_png().as_bytes());
+    }
+
+    fn structure_rules(chunk_types: &[&str]) -> Vec<(Option<usize>, StructureRule)> {
+        let chunks = chunk_types
+            .iter()
+            .map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap())
+            .collect();
+        match Png::from_chunks(chunks).validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| (e.index, e.rule)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_valid_structure() {
+        assert!(Png::try_from(&PNG_FILE[..]).unwrap().validate().is_ok());
+        assert!(structure_rules(&["IHDR", "PLTE", "IDAT", "IDAT", "tEXt", "IEND"]).is_empty());
+    }
+
+    #[test]
+    fn test_validate_ihdr_rules() {
+        assert_eq!(
+            structure_rules(&["IDAT", "IEND"]),
+            [(None, StructureRule::MissingIhdr)]
+        );
+        assert_eq!(
+            structure_rules(&["tEXt", "IHDR", "IDAT", "IEND"]),
+            [(Some(1), StructureRule::IhdrNotFirst)]
+        );
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT", "IHDR", "IEND"]),
+            [(Some(2), StructureRule::DuplicateIhdr)]
+        );
+    }
+
+    #[test]
+    fn test_validate_iend_rules() {
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT"]),
+            [(None, StructureRule::MissingIend)]
+        );
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT", "IEND", "ruSt"]),
+            [(Some(3), StructureRule::ChunkAfterIend)]
+        );
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT", "IEND", "IEND"]),
+            [
+                (Some(3), StructureRule::ChunkAfterIend),
+                (Some(3), StructureRule::DuplicateIend)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_idat_rules() {
+        assert_eq!(
+            structure_rules(&["IHDR", "IEND"]),
+            [(None, StructureRule::MissingIdat)]
+        );
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT", "tEXt", "IDAT", "IEND"]),
+            [(Some(2), StructureRule::IdatNotConsecutive)]
+        );
+    }
+
+    #[test]
+    fn test_validate_plte_rules() {
+        assert_eq!(
+            structure_rules(&["IHDR", "IDAT", "PLTE", "IEND"]),
+            [(Some(2), StructureRule::PlteAfterIdat)]
+        );
+    }
+
+    #[test]
+    fn test_structure_error_display() {
+        let error = StructureError {
+            index: Some(2),
+            rule: StructureRule::PlteAfterIdat,
+        };
+        assert_eq!(
+            error.to_string(),
+            "chunk 2: PLTE must precede the first IDAT chunk"
+        );
+    }
+
+    #[test]
+    fn test_png_from_image_file() {
+        let png = Png::try_from(&PNG_FILE[..]);
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    fn test_as_bytes() {
         let png = Png::try_from(&PNG_FILE[..]).unwrap();
         let actual = png.as_bytes();
-        let expected: Vec<u8> = PNG_FILE.iter().copied().collect();
+        let expected: Vec<u8> = PNG_FILE.to_vec();
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_as_bytes_exact_capacity() {
+        let mut png = many_idat_png();
+        png.trailing_data = b"trailing".to_vec();
+        let bytes = png.as_bytes();
+        assert_eq!(bytes.capacity(), bytes.len());
+
+        // The field-by-field layout as_bytes used to build with iterator chains
+        let expected: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(png.chunks().iter().flat_map(|chunk| {
+                chunk
+                    .length()
+                    .to_be_bytes()
+                    .into_iter()
+                    .chain(chunk.chunk_type().bytes())
+                    .chain(chunk.data().iter().copied())
+                    .chain(chunk.crc().to_be_bytes())
+                    .collect::<Vec<u8>>()
+            }))
+            .chain(png.trailing_data().iter().copied())
+            .collect();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_into_bytes() {
+        let mut png = many_idat_png();
+        png.trailing_data = b"trailing".to_vec();
+        let expected = png.as_bytes();
+        let bytes = png.into_bytes();
+        assert_eq!(bytes, expected);
+        assert_eq!(bytes.capacity(), bytes.len());
+    }
+
+    #[test]
+    fn test_custom_data_len() {
+        let png = png_of(&[
+            ("IHDR", "header"),
+            ("tEXt", "standard"),
+            ("ruSt", "custom"),
+            ("IDAT", "data"),
+            ("raIn", "more"),
+            ("IEND", ""),
+        ]);
+        assert_eq!(png.custom_data_len(), 10);
+        let png = png_of(&[("IHDR", "header"), ("IDAT", "data"), ("IEND", "")]);
+        assert_eq!(png.custom_data_len(), 0);
+    }
+
+    #[test]
+    fn test_chunked_capacity() {
+        assert_eq!(Png::chunked_capacity(0, 100), 0);
+        assert_eq!(Png::chunked_capacity(12, 100), 0);
+        assert_eq!(Png::chunked_capacity(112, 100), 100);
+        assert_eq!(Png::chunked_capacity(250, 100), 200 + 14);
+        assert_eq!(Png::chunked_capacity(1 << 20, 1 << 16), (1 << 20) - 16 * 12);
+    }
+
+    #[test]
+    fn test_default() {
+        let png = Png::default();
+        assert!(png.is_empty());
+        assert_eq!(png.as_bytes(), PNG_SIGNATURE);
+        assert!(png.validate().is_err());
+    }
+
+    #[test]
+    fn test_generate() {
+        let color = Rgba::from_str("#FF800040").unwrap();
+        assert_eq!(color, Rgba { r: 0xFF, g: 0x80, b: 0, a: 0x40 });
+        assert_eq!(color.to_string(), "FF800040");
+
+        let png = Png::generate(3, 2, color).unwrap();
+        assert!(png.validate().is_ok());
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+        let ihdr = crate::standard_chunks::parse_ihdr(&png.chunks()[0]).unwrap();
+        assert_eq!((ihdr.width, ihdr.height, ihdr.bit_depth, ihdr.color_type), (3, 2, 8, 6));
+
+        let mut scanlines = Vec::new();
+        flate2::read::ZlibDecoder::new(png.chunks()[1].data())
+            .read_to_end(&mut scanlines)
+            .unwrap();
+        let row = [1, 0xFF, 0x80, 0, 0x40, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(scanlines, [row, row].concat());
+
+        // Survives a round trip through bytes
+        assert_eq!(Png::try_from(png.as_bytes().as_slice()).unwrap().as_bytes(), png.as_bytes());
+    }
+
+    #[test]
+    fn test_generate_invalid() {
+        let white = Rgba::from_str("FFFFFFFF").unwrap();
+        assert!(Png::generate(0, 1, white).is_err());
+        assert!(Png::generate(1, 1 << 31, white).is_err());
+        let error = Png::generate(i32::MAX as u32, 1, white).unwrap_err();
+        assert!(error.to_string().contains("at most 67108864 pixels"), "{}", error);
+        assert!(Png::generate(8192, 8193, white).is_err());
+        assert!(Rgba::from_str("FFFFFF").is_err());
+        assert!(Rgba::from_str("+FFFFFFF").is_err());
+        assert!(Rgba::from_str("GGGGGGGG").is_err());
+    }
+
+    fn builder_ihdr() -> IhdrData {
+        IhdrData {
+            width: 1,
+            height: 1,
+            bit_depth: 8,
+            color_type: 0,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: 0,
+        }
+    }
+
+    #[test]
+    fn test_png_builder() {
+        let idat = Chunk::new(ChunkType::IDAT, vec![0x78, 0x01, 0x63, 0x60, 0, 0, 0, 2, 0, 1]);
+        let png = PngBuilder::new()
+            .append_chunk(chunk_from_strings("tEXt", "Comment\0built").unwrap())
+            .append_chunk(idat)
+            .with_ihdr(builder_ihdr())
+            .build()
+            .unwrap();
+
+        assert_eq!(chunk_types(&png), ["IHDR", "tEXt", "IDAT", "IEND"]);
+        let reparsed = reparse(&png);
+        assert_eq!(reparsed.as_bytes(), png.as_bytes());
+        assert!(reparsed.validate().is_ok());
+    }
+
+    #[test]
+    fn test_png_builder_rejects_invalid_png() {
+        let error = PngBuilder::new().with_ihdr(builder_ihdr()).build().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Png: Built PNG is invalid: at least one IDAT chunk is required"
+        );
+        assert!(PngBuilder::new().build().is_err());
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let png = many_idat_png();
+        let expected = png.as_bytes();
+        assert_eq!(Vec::<u8>::from(png), expected);
+    }
+
+    #[test]
+    fn test_chunk_spans() {
+        let mut png = testing_png();
+        let spans = png.chunk_spans();
+        // FrSt holds 20 bytes of data and miDl 18, plus 12 bytes of fields each
+        assert_eq!(
+            spans,
+            [
+                ChunkSpan { offset: 8, total_len: 32 },
+                ChunkSpan { offset: 40, total_len: 30 },
+                ChunkSpan { offset: 70, total_len: 31 },
+            ]
+        );
+
+        png.remove_chunk_at(0);
+        assert_eq!(png.chunk_spans()[0], ChunkSpan { offset: 8, total_len: 30 });
+    }
+
+    #[test]
+    fn test_chunk_spans_cover_file() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let spans = png.chunk_spans();
+        assert_eq!(spans.len(), png.len());
+
+        let last = spans.last().unwrap();
+        assert_eq!(last.offset + last.total_len, PNG_FILE.len() as u64);
+        for (span, chunk) in spans.iter().zip(png.chunks()) {
+            let start = span.offset as usize;
+            let end = start + span.total_len as usize;
+            assert_eq!(&PNG_FILE[start..end], chunk.as_bytes().as_slice());
+        }
+    }
+
+    #[test]
+    fn test_serialized_size() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.serialized_size(), PNG_FILE.len());
+        assert_eq!(Png::from_chunks(vec![]).serialized_size(), 8);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = testing_png();
+        let mut bytes = Vec::new();
+
+        let written = png.write_to(&mut bytes).unwrap();
+
+        assert_eq!(written, bytes.len());
+        assert_eq!(bytes, png.as_bytes());
+        assert_eq!(reparse(&png).len(), 3);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let png = Png::from_reader(io::Cursor::new(&PNG_FILE[..])).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    /// A PNG with many IDAT chunks, a message after them and one after IEND
+    fn many_idat_png() -> Png {
+        let mut chunks = vec![chunk_from_strings("IHDR", "header").unwrap()];
+        for idx in 0..1000 {
+            let data = vec![idx as u8; 1024];
+            chunks.push(Chunk::new(ChunkType::from_str("IDAT").unwrap(), data));
+        }
+        chunks.push(chunk_from_strings("ruSt", "hidden").unwrap());
+        chunks.push(chunk_from_strings("IEND", "").unwrap());
+        chunks.push(chunk_from_strings("laTe", "appended").unwrap());
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_find_chunk_in_reader() {
+        let bytes = many_idat_png().as_bytes();
+        let find = |chunk_type: &str| {
+            let chunk_type = ChunkType::from_str(chunk_type).unwrap();
+            Png::find_chunk_in_reader(io::Cursor::new(&bytes), &chunk_type).unwrap()
+        };
+
+        assert_eq!(find("ruSt").unwrap().data(), b"hidden");
+        assert_eq!(find("laTe").unwrap().data(), b"appended");
+        assert_eq!(find("IDAT").unwrap().data(), &[0; 1024][..]);
+        assert!(find("NoPe").is_none());
+    }
+
+    #[test]
+    fn test_find_chunk_in_reader_errors() {
+        let mut bytes = many_idat_png().as_bytes();
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+
+        // Garbage after IEND just ends the search
+        bytes.extend_from_slice(b"garbage");
+        let found = Png::find_chunk_in_reader(io::Cursor::new(&bytes), &chunk_type).unwrap();
+        assert!(found.is_some());
+
+        // A bad CRC in the matching chunk is an error
+        let png = many_idat_png();
+        let offset = 8 + png.chunks()[..1001].iter().map(Chunk::serialized_len).sum::<usize>();
+        bytes[offset + png.chunks()[1001].serialized_len() - 1] ^= 1;
+        let error = Png::find_chunk_in_reader(io::Cursor::new(&bytes), &chunk_type).unwrap_err();
+        assert!(matches!(error, PngError::InvalidChunk { index: 1001, offset: o, .. } if o == offset));
+
+        // So is a file cut off in a chunk that is skipped over
+        let bytes = many_idat_png().as_bytes();
+        let truncated = &bytes[..8 + png.chunks()[0].serialized_len() + 100];
+        let error = Png::find_chunk_in_reader(io::Cursor::new(truncated), &chunk_type).unwrap_err();
+        let PngError::InvalidChunk { index: 1, source, .. } = error else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert!(matches!(
+            *source,
+            PngError::TruncatedChunk { field: "data", expected: 1024, found: 92, .. }
+        ));
+        let truncated = &bytes[..offset - 2];
+        let error = Png::find_chunk_in_reader(io::Cursor::new(truncated), &chunk_type).unwrap_err();
+        let PngError::InvalidChunk { index: 1000, source, .. } = error else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert!(matches!(*source, PngError::TruncatedChunk { field: "CRC", found: 2, .. }));
+    }
+
+    #[test]
+    fn test_from_reader_matches_try_from() {
+        let mut bytes = many_idat_png().as_bytes();
+        bytes.extend_from_slice(b"garbage");
+
+        let streamed = Png::from_reader(bytes.as_slice()).unwrap();
+        let buffered = Png::try_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(streamed.len(), 1004);
+        assert_eq!(streamed.trailing_data(), b"garbage");
+        assert_eq!(streamed.as_bytes(), buffered.as_bytes());
+    }
+
+    #[test]
+    fn test_from_reader_with_progress() {
+        let png = many_idat_png();
+        let mut bytes = png.as_bytes();
+        bytes.extend_from_slice(b"garbage");
+
+        let mut reported = Vec::new();
+        let options = ParseOptions::default();
+        Png::from_reader_with_progress(bytes.as_slice(), options, |read| reported.push(read))
+            .unwrap();
+
+        let ends: Vec<u64> = png.chunk_spans().iter().map(|s| s.offset + s.total_len).collect();
+        // Reading stops at IEND, and the chunk after it comes with the rest
+        assert_eq!(reported.len(), 1004);
+        assert_eq!(reported[..1003], ends[..1003]);
+        assert_eq!(reported[1003], bytes.len() as u64);
+    }
+
+    #[test]
+    fn test_from_reader_invalid_header() {
+        let mut bytes = PNG_FILE.to_vec();
+        bytes[0] = 13;
+        assert!(matches!(
+            Png::from_reader(bytes.as_slice()),
+            Err(PngError::InvalidSignature { .. })
+        ));
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("pngme-{}-{}.png", std::process::id(), name))
+    }
+
+    /// Checks that inserting `chunks` in place gives the same bytes as parsing
+    /// `bytes`, inserting them before IEND and writing everything back out
+    fn assert_insert_in_file_matches_full_rewrite(name: &str, bytes: &[u8], chunks: &[Chunk]) {
+        let mut png = Png::try_from(bytes).unwrap();
+        let iend = png.position_of(&ChunkType::from_str("IEND").unwrap()).unwrap();
+        for (idx, chunk) in chunks.iter().enumerate() {
+            png.insert_chunk(iend + idx, chunk.clone()).unwrap();
+        }
+
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        assert!(Png::insert_before_iend_in_file(&path, chunks).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_before_iend_in_file() {
+        let secret = chunk_from_strings("ruSt", "hidden").unwrap();
+        let other = chunk_from_strings("teXt", "more").unwrap();
+        let chunks = [secret, other];
+        assert_insert_in_file_matches_full_rewrite("in_file_sample", &PNG_FILE, &chunks[..1]);
+
+        let many = many_idat_png().as_bytes();
+        assert_insert_in_file_matches_full_rewrite("in_file_many", &many, &chunks);
+
+        let mut trailing = PNG_FILE.to_vec();
+        trailing.extend_from_slice(b"not a chunk");
+        assert_insert_in_file_matches_full_rewrite("in_file_trailing", &trailing, &chunks);
+    }
+
+    #[test]
+    fn test_insert_before_iend_in_file_without_iend() {
+        let bytes = testing_png().as_bytes();
+        let path = temp_path("in_file_without_iend");
+        fs::write(&path, &bytes).unwrap();
+
+        let secret = chunk_from_strings("ruSt", "hidden").unwrap();
+        assert!(!Png::insert_before_iend_in_file(&path, &[secret]).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        assert!(!temp_sibling(&path).unwrap().exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_insert_before_iend_copying() {
+        let src = temp_path("copying_src");
+        let dest = temp_path("copying_dest");
+        fs::write(&src, PNG_FILE).unwrap();
+
+        let secret = chunk_from_strings("ruSt", "hidden").unwrap();
+        let chunks = std::slice::from_ref(&secret);
+        assert!(Png::insert_before_iend_copying(&src, &dest, chunks).unwrap());
+        assert_eq!(fs::read(&src).unwrap(), PNG_FILE);
+        let copy = Png::try_from(fs::read(&dest).unwrap().as_slice()).unwrap();
+        let iend = copy.position_of(&ChunkType::IEND).unwrap();
+        assert_eq!(copy.chunks()[iend - 1].data(), secret.data());
+
+        // Nothing is left behind when the destination cannot be written
+        let missing_dir = temp_path("copying_missing").join("dest.png");
+        assert!(Png::insert_before_iend_copying(&src, &missing_dir, &[secret]).is_err());
+        assert!(!missing_dir.exists());
+        fs::remove_file(&src).unwrap();
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[test]
+    fn test_write_to_file() {
+        let png = testing_png();
+        let path = temp_path("write_to_file");
+
+        png.write_to_file(&path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_to_file_atomic() {
+        let png = testing_png();
+        let path = temp_path("write_to_file_atomic");
+        fs::write(&path, b"previous contents").unwrap();
+
+        png.write_to_file_atomic(&path).unwrap();
+
+        let leftover: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with("write_to_file_atomic.png.tmp"))
+            .collect();
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        assert!(leftover.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_path() {
+        let path = temp_path("try_from_path");
+        fs::write(&path, PNG_FILE).unwrap();
+
+        let from_path = Png::try_from(path.as_path()).unwrap();
+        let from_path_buf = Png::try_from(&path).unwrap();
+
+        assert_eq!(from_path.as_bytes(), PNG_FILE.to_vec());
+        assert_eq!(from_path_buf.as_bytes(), PNG_FILE.to_vec());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_try_from_missing_path() {
+        let path = temp_path("try_from_missing_path");
+
+        let error = Png::try_from(&path).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!("Png: Failed to load PNG from {}", path.display())
+        );
+        match error {
+            PngError::Load { source, .. } => assert!(matches!(*source, PngError::Io(_))),
+            other => panic!("expected a load error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -273,7 +2967,7 @@ mod tests {
             .copied()
             .collect();
 
-        let png: Png = TryFrom::try_from(bytes.as_ref()).unwrap();
+        let png: Png = TryFrom::try_from(bytes.as_slice()).unwrap();
 
         let _png_string = format!("{}", png);
     }
@@ -0,0 +1,25 @@
+//! Progress bars for commands that read large files. Bars go to stderr and
+//! are only drawn when it is a terminal.
+
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// Files smaller than this are read quickly enough to need no progress bar
+const PROGRESS_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Adds a bar for reading the `len` bytes of `path` to `bars`. The bar is
+/// hidden when `quiet` is set, the file is small or stderr is not a terminal.
+pub fn file_bar(bars: &MultiProgress, path: &Path, len: u64, quiet: bool) -> ProgressBar {
+    if quiet || len < PROGRESS_THRESHOLD || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{msg} [{wide_bar}] {bytes}/{total_bytes}")
+        .expect("Progress: The bar template is valid")
+        .progress_chars("=> ");
+    let bar = ProgressBar::new(len)
+        .with_style(style)
+        .with_message(path.display().to_string());
+    bars.add(bar)
+}
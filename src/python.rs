@@ -0,0 +1,96 @@
+//! Python bindings for `Png`, built with `maturin build`. Errors are raised as
+//! `ValueError`s carrying the same messages as on the Rust side.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::{Png, Rgba};
+
+/// A chunk as Python sees it: `(type, data, crc)`
+type PyChunk<'py> = (String, Bound<'py, PyBytes>, u32);
+
+/// A PNG file, exposed to Python as `pngme.Png`
+#[pyclass(name = "Png")]
+pub struct PyPng {
+    png: Png,
+}
+
+#[pymethods]
+impl PyPng {
+    /// Parses a PNG file from its bytes
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<PyPng> {
+        let png = Png::try_from(bytes).map_err(value_error)?;
+        Ok(PyPng { png })
+    }
+
+    /// Creates an image of `width` by `height` pixels filled with `color`,
+    /// given as `RRGGBBAA` hex digits, same as `Png::generate`
+    #[staticmethod]
+    fn generate(width: u32, height: u32, color: &str) -> PyResult<PyPng> {
+        let png = Rgba::from_str(color)
+            .and_then(|color| Png::generate(width, height, color))
+            .map_err(|error| PyValueError::new_err(format!("{:#}", error)))?;
+        Ok(PyPng { png })
+    }
+
+    /// The PNG file as bytes
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.png.as_bytes())
+    }
+
+    /// Every chunk in file order, as `(type, data, crc)` tuples
+    fn chunks<'py>(&self, py: Python<'py>) -> Vec<PyChunk<'py>> {
+        self.png.chunks().iter().map(|chunk| to_py_chunk(py, chunk)).collect()
+    }
+
+    /// Appends a chunk to the end of the chunk list, same as `Png::append_chunk`
+    fn append_chunk(&mut self, chunk_type: &str, data: &[u8]) -> PyResult<()> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(value_error)?;
+        self.png.append_chunk(Chunk::new(chunk_type, data.to_vec()));
+        Ok(())
+    }
+
+    /// Removes the first chunk of type `chunk_type` and returns it
+    fn remove_chunk<'py>(
+        &mut self,
+        py: Python<'py>,
+        chunk_type: &str,
+    ) -> PyResult<PyChunk<'py>> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(value_error)?;
+        let chunk = self.png.remove_chunk(&chunk_type).map_err(value_error)?;
+        Ok(to_py_chunk(py, &chunk))
+    }
+
+    /// The first chunk of type `chunk_type`, or `None`
+    fn find<'py>(&self, py: Python<'py>, chunk_type: &str) -> PyResult<Option<PyChunk<'py>>> {
+        let chunk_type = ChunkType::from_str(chunk_type).map_err(value_error)?;
+        let chunk = self.png.chunks().iter().find(|chunk| chunk.chunk_type() == &chunk_type);
+        Ok(chunk.map(|chunk| to_py_chunk(py, chunk)))
+    }
+
+    fn __len__(&self) -> usize {
+        self.png.len()
+    }
+}
+
+fn to_py_chunk<'py>(py: Python<'py>, chunk: &Chunk) -> PyChunk<'py> {
+    (chunk.chunk_type().to_string(), PyBytes::new(py, chunk.data()), chunk.crc())
+}
+
+/// Raises `error` as a `ValueError`, keeping the messages of its sources
+fn value_error(error: crate::error::PngError) -> PyErr {
+    PyValueError::new_err(format!("{:#}", crate::Error::from(error)))
+}
+
+/// The `pngme` Python module
+#[pymodule]
+fn pngme(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyPng>()
+}
@@ -0,0 +1,87 @@
+//! Colors for the `human` format of `print`. The JSON and CSV formats are
+//! never colored.
+
+use std::io::{self, IsTerminal};
+
+use owo_colors::{OwoColorize, Style};
+
+use pngme::chunk_type::ChunkType;
+
+use crate::args::ColorWhen;
+
+/// Picks the colors of chunk listings, or leaves them plain
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    /// With `auto`, colors are used when stdout is a terminal and `NO_COLOR`
+    /// is unset or empty
+    pub fn new(when: ColorWhen) -> Palette {
+        let enabled = match when {
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+            ColorWhen::Auto => {
+                io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        };
+        Palette { enabled }
+    }
+
+    /// Colors `entry`, the text printed for one chunk. The chunk type is colored
+    /// by kind, or the whole entry is red when the CRC is wrong.
+    pub fn paint_chunk(&self, entry: &str, chunk_type: &ChunkType, crc_ok: bool) -> String {
+        if !self.enabled {
+            return entry.to_string();
+        }
+        if !crc_ok {
+            return entry.style(Style::new().red().bold()).to_string();
+        }
+        // Both summary forms name the type before showing any data, so the
+        // first match is the type itself
+        let name = chunk_type.to_string();
+        entry.replacen(&name, &name.style(type_style(chunk_type)).to_string(), 1)
+    }
+}
+
+/// Critical types in blue, standard ancillary types in green, and anything
+/// else in bold yellow since that is where hidden messages live
+fn type_style(chunk_type: &ChunkType) -> Style {
+    if chunk_type.is_critical() {
+        Style::new().blue()
+    } else if chunk_type.is_known_standard() {
+        Style::new().green()
+    } else {
+        Style::new().yellow().bold()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn chunk_type(name: &str) -> ChunkType {
+        ChunkType::from_str(name).unwrap()
+    }
+
+    #[test]
+    fn test_never_is_plain() {
+        let palette = Palette::new(ColorWhen::Never);
+        let entry = "ruSt  5 bytes  crc=0x00000000";
+        assert_eq!(palette.paint_chunk(entry, &chunk_type("ruSt"), false), entry);
+    }
+
+    #[test]
+    fn test_always_colors_by_kind() {
+        let palette = Palette::new(ColorWhen::Always);
+        let paint = |name: &str, crc_ok| palette.paint_chunk(name, &chunk_type(name), crc_ok);
+
+        assert_eq!(paint("IDAT", true), "\x1b[34mIDAT\x1b[0m");
+        assert_eq!(paint("tEXt", true), "\x1b[32mtEXt\x1b[0m");
+        assert_eq!(paint("ruSt", true), "\x1b[33;1mruSt\x1b[0m");
+        assert!(paint("IDAT", false).starts_with("\x1b[31;1m"));
+    }
+}
@@ -0,0 +1,493 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::Result;
+
+/// The contents of a `tIME` chunk: the UTC time of the last image modification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeData {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl TimeData {
+    /// Converts a `SystemTime` to its UTC calendar date and time
+    pub fn from_system_time(datetime: &SystemTime) -> Result<TimeData> {
+        let secs = datetime
+            .duration_since(UNIX_EPOCH)
+            .context("TimeData: Time is before the Unix epoch")?
+            .as_secs();
+        let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+        // Days since the epoch to a proleptic Gregorian date, as described in
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        Ok(TimeData {
+            year: u16::try_from(year).context("TimeData: Year does not fit in a tIME chunk")?,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day % 3600 / 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        })
+    }
+}
+
+impl fmt::Display for TimeData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Creates a `tIME` chunk recording `datetime` as the last modification time
+pub fn new_time_chunk(datetime: &SystemTime) -> Result<Chunk> {
+    let time = TimeData::from_system_time(datetime)?;
+    let mut data = time.year.to_be_bytes().to_vec();
+    data.extend_from_slice(&[time.month, time.day, time.hour, time.minute, time.second]);
+    Ok(Chunk::new(ChunkType::from_str("tIME")?, data))
+}
+
+/// Reads the modification time stored in a `tIME` chunk
+pub fn parse_time_chunk(chunk: &Chunk) -> Result<TimeData> {
+    if chunk.chunk_type().bytes() != *b"tIME" {
+        bail!("StandardChunks: Expected a tIME chunk, found {}", chunk.chunk_type());
+    }
+    let data: [u8; 7] = chunk
+        .data()
+        .try_into()
+        .context("StandardChunks: tIME chunk data must be 7 bytes long")?;
+
+    Ok(TimeData {
+        year: u16::from_be_bytes([data[0], data[1]]),
+        month: data[2],
+        day: data[3],
+        hour: data[4],
+        minute: data[5],
+        second: data[6],
+    })
+}
+
+/// The contents of an `IHDR` chunk: the image dimensions and how its pixels
+/// are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IhdrData {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+impl IhdrData {
+    /// Returns the name of the color type, or `None` if it is not one the
+    /// PNG spec defines
+    pub fn color_type_name(&self) -> Option<&'static str> {
+        match self.color_type {
+            0 => Some("Grayscale"),
+            2 => Some("Truecolor"),
+            3 => Some("Indexed-color"),
+            4 => Some("Grayscale with alpha"),
+            6 => Some("Truecolor with alpha"),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of samples per pixel, or `None` for a color type
+    /// the PNG spec does not define. Palette indexes count as one sample.
+    pub fn channels(&self) -> Option<u8> {
+        match self.color_type {
+            0 | 3 => Some(1),
+            2 => Some(3),
+            4 => Some(2),
+            6 => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of samples in the image, or `None` for a color
+    /// type the PNG spec does not define
+    pub fn sample_count(&self) -> Option<u64> {
+        let channels = u64::from(self.channels()?);
+        Some(u64::from(self.width) * u64::from(self.height) * channels)
+    }
+
+    /// Returns the name of the interlace method, or `None` if it is not one
+    /// the PNG spec defines
+    pub fn interlace_method_name(&self) -> Option<&'static str> {
+        match self.interlace_method {
+            0 => Some("None"),
+            1 => Some("Adam7"),
+            _ => None,
+        }
+    }
+
+    /// Formats the fields as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"width\":{},\"height\":{},\"bit_depth\":{},\"color_type\":{},\"compression_method\":{},\"filter_method\":{},\"interlace_method\":{}}}",
+            self.width,
+            self.height,
+            self.bit_depth,
+            self.color_type,
+            self.compression_method,
+            self.filter_method,
+            self.interlace_method
+        )
+    }
+}
+
+impl fmt::Display for IhdrData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Width: {}", self.width)?;
+        writeln!(f, "Height: {}", self.height)?;
+        writeln!(f, "Bit depth: {}", self.bit_depth)?;
+        match self.color_type_name() {
+            Some(name) => writeln!(f, "Color type: {} ({})", self.color_type, name)?,
+            None => writeln!(f, "Color type: {} (unknown)", self.color_type)?,
+        }
+        writeln!(f, "Compression method: {}", self.compression_method)?;
+        writeln!(f, "Filter method: {}", self.filter_method)?;
+        match self.interlace_method_name() {
+            Some(name) => write!(f, "Interlace method: {} ({})", self.interlace_method, name),
+            None => write!(f, "Interlace method: {} (unknown)", self.interlace_method),
+        }
+    }
+}
+
+/// Reads the image header stored in an `IHDR` chunk
+pub fn parse_ihdr(chunk: &Chunk) -> Result<IhdrData> {
+    if chunk.chunk_type().bytes() != *b"IHDR" {
+        bail!("StandardChunks: Expected an IHDR chunk, found {}", chunk.chunk_type());
+    }
+    let data: [u8; 13] = chunk
+        .data()
+        .try_into()
+        .context("StandardChunks: IHDR chunk data must be 13 bytes long")?;
+
+    Ok(IhdrData {
+        width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+        height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        bit_depth: data[8],
+        color_type: data[9],
+        compression_method: data[10],
+        filter_method: data[11],
+        interlace_method: data[12],
+    })
+}
+
+/// Creates the `IHDR` chunk holding `ihdr`
+pub fn new_ihdr_chunk(ihdr: &IhdrData) -> Chunk {
+    let mut data = Vec::with_capacity(13);
+    data.extend_from_slice(&ihdr.width.to_be_bytes());
+    data.extend_from_slice(&ihdr.height.to_be_bytes());
+    data.extend_from_slice(&[
+        ihdr.bit_depth,
+        ihdr.color_type,
+        ihdr.compression_method,
+        ihdr.filter_method,
+        ihdr.interlace_method,
+    ]);
+    Chunk::new(ChunkType::IHDR, data)
+}
+
+/// Checks that `keyword` follows the rules shared by the `tEXt`, `zTXt` and
+/// `iTXt` chunks and returns it as Latin-1 bytes
+pub(crate) fn encode_keyword(keyword: &str) -> Result<Vec<u8>> {
+    let bytes = encode_latin1(keyword).context("StandardChunks: Invalid keyword")?;
+    if bytes.is_empty() || bytes.len() > 79 {
+        bail!("StandardChunks: Keyword must be 1 to 79 bytes long");
+    }
+    if bytes.iter().any(|&b| !(32..=126).contains(&b) && b < 161) {
+        bail!("StandardChunks: Keyword must only contain printable Latin-1 characters");
+    }
+    if bytes[0] == b' ' || bytes[bytes.len() - 1] == b' ' || keyword.contains("  ") {
+        bail!("StandardChunks: Keyword must not have leading, trailing or consecutive spaces");
+    }
+    Ok(bytes)
+}
+
+fn encode_latin1(text: &str) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| match u8::try_from(u32::from(c)) {
+            Ok(byte) => Ok(byte),
+            Err(_) => bail!("StandardChunks: {:?} is not a Latin-1 character", c),
+        })
+        .collect()
+}
+
+pub(crate) fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+/// The longest text `parse_compressed_text` inflates a `zTXt` chunk to, so
+/// that a small chunk cannot expand to fill memory
+pub const MAX_COMPRESSED_TEXT_LEN: usize = 8 << 20;
+
+/// Creates a `zTXt` chunk holding `value` deflate-compressed under `keyword`.
+/// Both are Latin-1 text, as the PNG spec requires.
+pub fn new_compressed_text(keyword: &str, value: &str) -> Result<Chunk> {
+    let mut data = encode_keyword(keyword)?;
+    // Null separator followed by compression method 0 (deflate)
+    data.extend_from_slice(&[0, 0]);
+
+    let mut encoder = ZlibEncoder::new(data, Compression::default());
+    encoder.write_all(&encode_latin1(value)?)?;
+    let data = encoder.finish()?;
+
+    Ok(Chunk::new(ChunkType::from_str("zTXt")?, data))
+}
+
+/// Reads the keyword and decompressed text of a `zTXt` chunk
+pub fn parse_compressed_text(chunk: &Chunk) -> Result<(String, String)> {
+    if chunk.chunk_type().bytes() != *b"zTXt" {
+        bail!("StandardChunks: Expected a zTXt chunk, found {}", chunk.chunk_type());
+    }
+    let data = chunk.data();
+    let separator = data
+        .iter()
+        .position(|&b| b == 0)
+        .context("StandardChunks: zTXt chunk has no keyword separator")?;
+    let keyword = decode_latin1(&data[..separator]);
+    encode_keyword(&keyword)?;
+
+    match data.get(separator + 1) {
+        Some(0) => {}
+        Some(method) => bail!("StandardChunks: Unknown zTXt compression method {}", method),
+        None => bail!("StandardChunks: zTXt chunk has no compression method"),
+    }
+
+    let mut text = Vec::new();
+    ZlibDecoder::new(&data[separator + 2..])
+        .take(MAX_COMPRESSED_TEXT_LEN as u64 + 1)
+        .read_to_end(&mut text)
+        .context("StandardChunks: Could not decompress zTXt text")?;
+    if text.len() > MAX_COMPRESSED_TEXT_LEN {
+        bail!(
+            "StandardChunks: zTXt text decompresses to more than {} bytes",
+            MAX_COMPRESSED_TEXT_LEN
+        );
+    }
+
+    Ok((keyword, decode_latin1(&text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn system_time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_new_time_chunk() {
+        // 2022-07-25 12:34:56 UTC
+        let chunk = new_time_chunk(&system_time(1_658_752_496)).unwrap();
+
+        assert_eq!(chunk.chunk_type().to_string(), "tIME");
+        assert_eq!(chunk.data(), &[0x07, 0xE6, 7, 25, 12, 34, 56]);
+    }
+
+    #[test]
+    fn test_time_chunk_round_trip() {
+        // 2000-02-29 23:59:59 UTC
+        let chunk = new_time_chunk(&system_time(951_868_799)).unwrap();
+        let chunk = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+
+        let time = parse_time_chunk(&chunk).unwrap();
+
+        assert_eq!(
+            time,
+            TimeData {
+                year: 2000,
+                month: 2,
+                day: 29,
+                hour: 23,
+                minute: 59,
+                second: 59,
+            }
+        );
+        assert_eq!(time.to_string(), "2000-02-29 23:59:59 UTC");
+    }
+
+    #[test]
+    fn test_time_at_epoch() {
+        let time = TimeData::from_system_time(&UNIX_EPOCH).unwrap();
+        assert_eq!(time.to_string(), "1970-01-01 00:00:00 UTC");
+    }
+
+    #[test]
+    fn test_compressed_text_round_trip() {
+        let pairs = [
+            ("Title", "Dice"),
+            ("Comment", ""),
+            ("Description", "Ceci est une déclaration très longue, répétée. ".repeat(20).as_str()),
+            ("Author ÿ", "Zoë Ångström, £5 ±1°"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        for (keyword, value) in pairs {
+            let chunk = new_compressed_text(&keyword, &value).unwrap();
+            let chunk = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+
+            assert_eq!(chunk.chunk_type().to_string(), "zTXt");
+            assert_eq!(parse_compressed_text(&chunk).unwrap(), (keyword, value));
+        }
+    }
+
+    #[test]
+    fn test_compressed_text_header() {
+        let chunk = new_compressed_text("Title", "Dice").unwrap();
+        assert_eq!(&chunk.data()[..7], b"Title\0\0");
+    }
+
+    #[test]
+    fn test_compressed_text_is_latin1() {
+        let chunk = new_compressed_text("Author", "Zoë").unwrap();
+        let mut text = Vec::new();
+        ZlibDecoder::new(&chunk.data()[8..]).read_to_end(&mut text).unwrap();
+        assert_eq!(text, [b'Z', b'o', 0xEB]);
+    }
+
+    #[test]
+    fn test_compressed_text_invalid_keyword() {
+        assert!(new_compressed_text("", "value").is_err());
+        assert!(new_compressed_text(&"k".repeat(80), "value").is_err());
+        assert!(new_compressed_text(" Title", "value").is_err());
+        assert!(new_compressed_text("Ti  tle", "value").is_err());
+        assert!(new_compressed_text("Ti\ntle", "value").is_err());
+        assert!(new_compressed_text("Title", "not Latin-1: €").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_compressed_text() {
+        let wrong_type = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Title\0\0".to_vec());
+        assert!(parse_compressed_text(&wrong_type).is_err());
+
+        let wrong_method = Chunk::new(ChunkType::from_str("zTXt").unwrap(), b"Title\0\x01".to_vec());
+        assert!(parse_compressed_text(&wrong_method).is_err());
+    }
+
+    #[test]
+    fn test_parse_compressed_text_is_capped() {
+        let mut encoder = ZlibEncoder::new(b"Title\0\0".to_vec(), Compression::best());
+        encoder.write_all(&vec![b'a'; MAX_COMPRESSED_TEXT_LEN + 1]).unwrap();
+        let bomb = Chunk::new(ChunkType::from_str("zTXt").unwrap(), encoder.finish().unwrap());
+        assert!(bomb.data().len() < 64 * 1024);
+
+        let error = parse_compressed_text(&bomb).unwrap_err();
+        assert!(error.to_string().contains("decompresses to more than"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse_invalid_time_chunk() {
+        let wrong_type = Chunk::new(ChunkType::from_str("tEXt").unwrap(), vec![0; 7]);
+        assert!(parse_time_chunk(&wrong_type).is_err());
+
+        let wrong_length = Chunk::new(ChunkType::from_str("tIME").unwrap(), vec![0; 6]);
+        assert!(parse_time_chunk(&wrong_length).is_err());
+    }
+
+    fn ihdr_chunk() -> Chunk {
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 0x01, 0x90,   // width: 400
+            0, 0, 0x00, 0xC8,   // height: 200
+            8,                  // bit depth
+            6,                  // color type: truecolor with alpha
+            0, 0,               // compression and filter methods
+            1,                  // interlace method: Adam7
+        ];
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data)
+    }
+
+    #[test]
+    fn test_parse_ihdr() {
+        let ihdr = parse_ihdr(&ihdr_chunk()).unwrap();
+
+        assert_eq!(
+            ihdr,
+            IhdrData {
+                width: 400,
+                height: 200,
+                bit_depth: 8,
+                color_type: 6,
+                compression_method: 0,
+                filter_method: 0,
+                interlace_method: 1,
+            }
+        );
+        assert_eq!(ihdr.color_type_name(), Some("Truecolor with alpha"));
+        assert_eq!(ihdr.interlace_method_name(), Some("Adam7"));
+    }
+
+    #[test]
+    fn test_new_ihdr_chunk() {
+        let chunk = ihdr_chunk();
+        let ihdr = parse_ihdr(&chunk).unwrap();
+        assert_eq!(new_ihdr_chunk(&ihdr).as_bytes(), chunk.as_bytes());
+    }
+
+    #[test]
+    fn test_ihdr_sample_count() {
+        let mut ihdr = parse_ihdr(&ihdr_chunk()).unwrap();
+        assert_eq!(ihdr.channels(), Some(4));
+        assert_eq!(ihdr.sample_count(), Some(400 * 200 * 4));
+
+        ihdr.color_type = 3;
+        assert_eq!(ihdr.sample_count(), Some(400 * 200));
+        ihdr.color_type = 5;
+        assert_eq!(ihdr.channels(), None);
+        assert_eq!(ihdr.sample_count(), None);
+    }
+
+    #[test]
+    fn test_ihdr_to_json() {
+        let ihdr = parse_ihdr(&ihdr_chunk()).unwrap();
+
+        assert_eq!(
+            ihdr.to_json(),
+            r#"{"width":400,"height":200,"bit_depth":8,"color_type":6,"compression_method":0,"filter_method":0,"interlace_method":1}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_ihdr() {
+        let wrong_type = Chunk::new(ChunkType::from_str("tEXt").unwrap(), vec![0; 13]);
+        assert!(parse_ihdr(&wrong_type).is_err());
+
+        let wrong_length = Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 12]);
+        assert!(parse_ihdr(&wrong_length).is_err());
+    }
+}
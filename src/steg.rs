@@ -0,0 +1,354 @@
+//! Hides a message in the least significant bits of the image samples, where
+//! chunk inspectors do not see it. The message is stored after a 4 byte
+//! big-endian length, one bit per sample, in scanline order. Only 8-bit,
+//! non-interlaced grayscale and truecolor images, with or without alpha, are
+//! supported.
+
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::png::Png;
+use crate::standard_chunks::{parse_ihdr, IhdrData};
+use crate::Result;
+
+/// Size of the length that precedes the message
+const LENGTH_SIZE: usize = 4;
+
+/// The decoded samples of an image, along with the filter type each scanline
+/// was stored with so that it can be stored the same way again
+struct Samples {
+    /// Bytes per pixel, which is also the number of samples per pixel
+    bpp: usize,
+    stride: usize,
+    filters: Vec<u8>,
+    data: Vec<u8>,
+}
+
+/// How many message bytes `png` can hide, going by its IHDR chunk
+pub fn capacity(png: &Png) -> Result<usize> {
+    let ihdr = png.chunk_by_type("IHDR").context("Steg: Image has no IHDR chunk")?;
+    capacity_for(&parse_ihdr(ihdr)?)
+}
+
+/// How many message bytes an image with this header can hide
+pub fn capacity_for(ihdr: &IhdrData) -> Result<usize> {
+    bytes_per_pixel(ihdr)?;
+    let samples = ihdr.sample_count().context("Steg: Unknown color type")?;
+    let capacity = (samples / 8).saturating_sub(LENGTH_SIZE as u64);
+    Ok(usize::try_from(capacity).unwrap_or(usize::MAX))
+}
+
+/// Hides `message` in the image samples of `png` and rebuilds its `IDAT`
+/// chunks. `png` is left untouched if the message does not fit.
+pub fn embed(png: &mut Png, message: &[u8]) -> Result<()> {
+    let mut samples = decode(png)?;
+    let capacity = capacity_of(&samples);
+    if message.len() > capacity {
+        bail!(
+            "Steg: Message is {} bytes long, but the image can only hide {} bytes",
+            message.len(),
+            capacity
+        );
+    }
+
+    let length = (message.len() as u32).to_be_bytes();
+    let bits = length
+        .iter()
+        .chain(message)
+        .flat_map(|&byte| (0..8).rev().map(move |i| byte >> i & 1));
+    for (sample, bit) in samples.data.iter_mut().zip(bits) {
+        *sample = *sample & !1 | bit;
+    }
+
+    let data = encode(&samples)?;
+    replace_idat(png, data)
+}
+
+/// Reads a message hidden by `embed`
+pub fn extract(png: &Png) -> Result<Vec<u8>> {
+    let samples = decode(png)?;
+    let mut bytes = samples
+        .data
+        .chunks_exact(8)
+        .map(|bits| bits.iter().fold(0, |byte, sample| byte << 1 | sample & 1));
+
+    let length: Vec<u8> = bytes.by_ref().take(LENGTH_SIZE).collect();
+    let length = match <[u8; LENGTH_SIZE]>::try_from(length) {
+        Ok(length) => u32::from_be_bytes(length) as usize,
+        Err(_) => bail!("Steg: Image is too small to hold a message"),
+    };
+    if length > capacity_of(&samples) {
+        bail!("Steg: No hidden message found");
+    }
+    Ok(bytes.take(length).collect())
+}
+
+fn capacity_of(samples: &Samples) -> usize {
+    (samples.data.len() / 8).saturating_sub(LENGTH_SIZE)
+}
+
+/// Checks that the image is one `steg` supports, returning its bytes per pixel
+fn bytes_per_pixel(ihdr: &IhdrData) -> Result<usize> {
+    if ihdr.interlace_method != 0 {
+        bail!("Steg: Interlaced images are not supported");
+    }
+    if ihdr.bit_depth != 8 {
+        bail!("Steg: Only 8-bit images are supported, this one is {}-bit", ihdr.bit_depth);
+    }
+    if ihdr.color_type == 3 {
+        bail!("Steg: Palette images are not supported");
+    }
+    match ihdr.channels() {
+        Some(channels) => Ok(usize::from(channels)),
+        None => bail!("Steg: Unknown color type {}", ihdr.color_type),
+    }
+}
+
+/// Inflates the `IDAT` data of `png` and undoes the scanline filters
+fn decode(png: &Png) -> Result<Samples> {
+    let ihdr = png.chunk_by_type("IHDR").context("Steg: Image has no IHDR chunk")?;
+    let ihdr = parse_ihdr(ihdr)?;
+    let bpp = bytes_per_pixel(&ihdr)?;
+    let stride = ihdr.width as usize * bpp;
+    let height = ihdr.height as usize;
+
+    let compressed: Vec<u8> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| *chunk.chunk_type() == ChunkType::IDAT)
+        .flat_map(|chunk| chunk.data().iter().copied())
+        .collect();
+    let expected = (height as u64) * (stride as u64 + 1);
+    let mut filtered = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(expected + 1)
+        .read_to_end(&mut filtered)
+        .context("Steg: Could not decompress the image data")?;
+    if filtered.len() as u64 > expected {
+        bail!(
+            "Steg: Image data inflates to more than the {} bytes a {}x{} image needs",
+            expected,
+            ihdr.width,
+            ihdr.height
+        );
+    }
+    if filtered.len() as u64 != expected {
+        bail!(
+            "Steg: Image data is {} bytes long, expected {} for a {}x{} image",
+            filtered.len(),
+            expected,
+            ihdr.width,
+            ihdr.height
+        );
+    }
+
+    let mut filters = Vec::with_capacity(height);
+    let mut data = vec![0; height * stride];
+    for (y, line) in filtered.chunks_exact(stride + 1).enumerate() {
+        let (before, rest) = data.split_at_mut(y * stride);
+        let previous = (y > 0).then(|| &before[(y - 1) * stride..]);
+        let current = &mut rest[..stride];
+        current.copy_from_slice(&line[1..]);
+        let filter = line[0];
+        if filter > 4 {
+            bail!("Steg: Unknown filter type {} on scanline {}", filter, y);
+        }
+        for x in 0..stride {
+            let predictor = predict(filter, current, previous, x, bpp);
+            current[x] = current[x].wrapping_add(predictor);
+        }
+        filters.push(filter);
+    }
+
+    Ok(Samples {
+        bpp,
+        stride,
+        filters,
+        data,
+    })
+}
+
+/// Filters each scanline with the filter type it had before and deflates the
+/// result
+fn encode(samples: &Samples) -> Result<Vec<u8>> {
+    let (bpp, stride) = (samples.bpp, samples.stride);
+    let mut filtered = Vec::with_capacity(samples.filters.len() * (stride + 1));
+    for (y, &filter) in samples.filters.iter().enumerate() {
+        let current = &samples.data[y * stride..(y + 1) * stride];
+        let previous = (y > 0).then(|| &samples.data[(y - 1) * stride..y * stride]);
+        filtered.push(filter);
+        for x in 0..stride {
+            let predictor = predict(filter, current, previous, x, bpp);
+            filtered.push(current[x].wrapping_sub(predictor));
+        }
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&filtered)?;
+    Ok(encoder.finish()?)
+}
+
+/// The value `filter` predicts for sample `x` of the scanline `current` from
+/// the samples before it, in unfiltered form
+fn predict(filter: u8, current: &[u8], previous: Option<&[u8]>, x: usize, bpp: usize) -> u8 {
+    let left = if x >= bpp { current[x - bpp] } else { 0 };
+    let up = previous.map_or(0, |previous| previous[x]);
+    let up_left = previous.filter(|_| x >= bpp).map_or(0, |previous| previous[x - bpp]);
+    match filter {
+        1 => left,
+        2 => up,
+        3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+        4 => paeth(left, up, up_left),
+        _ => 0,
+    }
+}
+
+/// The Paeth predictor from the PNG spec
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let (a, b, c) = (i16::from(left), i16::from(up), i16::from(up_left));
+    let p = a + b - c;
+    let (pa, pb, pc) = ((p - a).abs(), (p - b).abs(), (p - c).abs());
+    if pa <= pb && pa <= pc {
+        left
+    } else if pb <= pc {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Replaces the `IDAT` chunks of `png` with `data`, split into chunks as
+/// large as the first of the old ones
+fn replace_idat(png: &mut Png, data: Vec<u8>) -> Result<()> {
+    let first = png
+        .position_of(&ChunkType::IDAT)
+        .context("Steg: Image has no IDAT chunk")?;
+    let size = png.chunks()[first].data().len().max(1);
+    png.retain_chunks_unchecked(|chunk| *chunk.chunk_type() != ChunkType::IDAT);
+    for (i, part) in data.chunks(size).enumerate() {
+        png.insert_chunk(first + i, Chunk::new(ChunkType::IDAT, part.to_vec()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` x `height` image with `bpp` samples per pixel, whose scanlines
+    /// cycle through every filter type
+    fn image(color_type: u8, bpp: usize, width: u32, height: u32) -> Png {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+        let stride = width as usize * bpp;
+        let samples = Samples {
+            bpp,
+            stride,
+            filters: (0..height).map(|y| (y % 5) as u8).collect(),
+            data: (0..height as usize * stride).map(|i| (i * 7 % 251) as u8).collect(),
+        };
+        let data = encode(&samples).unwrap();
+        let idat = data.chunks(64).map(|part| Chunk::new(ChunkType::IDAT, part.to_vec()));
+
+        let mut chunks = vec![Chunk::new(ChunkType::IHDR, ihdr)];
+        chunks.extend(idat);
+        chunks.push(Chunk::new_iend());
+        Png::from_chunks(chunks)
+    }
+
+    fn supported_images() -> Vec<Png> {
+        vec![image(0, 1, 40, 30), image(2, 3, 40, 30), image(4, 2, 40, 30), image(6, 4, 40, 30)]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for mut png in supported_images() {
+            let original = decode(&png).unwrap().data;
+            let message = b"This is where your secret message will be!";
+
+            embed(&mut png, message).unwrap();
+
+            let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+            assert!(png.validate().is_ok());
+            assert_eq!(extract(&png).unwrap(), message);
+            let changed = decode(&png).unwrap().data;
+            assert_eq!(changed.len(), original.len());
+            assert!(changed.iter().zip(&original).all(|(a, b)| a | 1 == b | 1));
+        }
+    }
+
+    #[test]
+    fn test_round_trip_fills_capacity() {
+        let mut png = image(0, 1, 8, 8);
+        assert_eq!(capacity(&png).unwrap(), 4);
+        assert_eq!(capacity(&image(6, 4, 40, 30)).unwrap(), 596);
+
+        embed(&mut png, b"full").unwrap();
+        assert_eq!(extract(&png).unwrap(), b"full");
+        embed(&mut png, b"").unwrap();
+        assert_eq!(extract(&png).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_embed_too_long_leaves_png_untouched() {
+        let mut png = image(2, 3, 4, 4);
+        let before = png.as_bytes();
+
+        let error = embed(&mut png, &[0; 3]).unwrap_err();
+
+        assert!(error.to_string().contains("can only hide 2 bytes"), "{}", error);
+        assert_eq!(png.as_bytes(), before);
+    }
+
+    #[test]
+    fn test_unsupported_images() {
+        let cases = [
+            (image(3, 1, 4, 4), "Palette images are not supported"),
+            (image(2, 3, 4, 4), "Interlaced images are not supported"),
+            (image(2, 3, 4, 4), "Only 8-bit images are supported, this one is 16-bit"),
+        ];
+        for (i, (mut png, expected)) in cases.into_iter().enumerate() {
+            let mut ihdr = png.chunks()[0].data().to_vec();
+            match i {
+                1 => ihdr[12] = 1,
+                2 => ihdr[8] = 16,
+                _ => {}
+            }
+            png.replace_chunk(&ChunkType::IHDR, Chunk::new(ChunkType::IHDR, ihdr))
+                .unwrap();
+
+            let error = embed(&mut png, b"hi").unwrap_err();
+            assert_eq!(error.to_string(), format!("Steg: {}", expected));
+        }
+    }
+
+    #[test]
+    fn test_decode_stops_at_declared_size() {
+        let mut png = image(0, 1, 8, 8);
+        let mut ihdr = png.chunks()[0].data().to_vec();
+        ihdr[4..8].copy_from_slice(&4u32.to_be_bytes());
+        png.replace_chunk(&ChunkType::IHDR, Chunk::new(ChunkType::IHDR, ihdr))
+            .unwrap();
+
+        let error = decode(&png).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Steg: Image data inflates to more than the 36 bytes a 8x4 image needs"
+        );
+    }
+
+    #[test]
+    fn test_extract_without_message() {
+        let png = image(6, 4, 4, 4);
+        // The first 32 samples are not a length that fits the 12 byte capacity
+        assert_eq!(extract(&png).unwrap_err().to_string(), "Steg: No hidden message found");
+    }
+}
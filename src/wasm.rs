@@ -0,0 +1,37 @@
+//! JavaScript bindings for parsing and editing PNGs in the browser. Build them
+//! with `wasm-pack build -- --no-default-features --features wasm`.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::export::PngDocument;
+use crate::png::Png;
+
+/// Parses a PNG file and returns its chunk list as a JSON string, in the same
+/// format as the `export` command writes
+#[wasm_bindgen]
+pub fn parse_png(bytes: &[u8]) -> Result<JsValue, JsError> {
+    let png = Png::try_from(bytes)?;
+    let json = PngDocument::from_png(&png).to_json().map_err(js_error)?;
+    Ok(JsValue::from_str(&json))
+}
+
+/// Adds a chunk of type `chunk_type` holding `data` right before the IEND
+/// chunk of a PNG file and returns the new file
+#[wasm_bindgen]
+pub fn add_chunk(bytes: &[u8], chunk_type: &str, data: &[u8]) -> Result<Vec<u8>, JsError> {
+    let mut png = Png::try_from(bytes)?;
+    let chunk = Chunk::new(ChunkType::from_str(chunk_type)?, data.to_vec());
+    let position = png.position_of(&ChunkType::IEND).unwrap_or(png.len());
+    png.insert_chunk(position, chunk)?;
+    Ok(png.into_bytes())
+}
+
+/// Keeps the whole context chain of an `anyhow` error in the message
+fn js_error(error: crate::Error) -> JsError {
+    JsError::new(&format!("{:#}", error))
+}
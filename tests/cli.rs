@@ -0,0 +1,1304 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::Png;
+use pngme::steg;
+
+fn pngme(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(args)
+        .output()
+        .expect("failed to run pngme")
+}
+
+#[test]
+fn test_remove_validates_chunk_type_before_reading_file() {
+    let output = pngme(&["remove", "does-not-exist.png", "ru1t"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ChunkType"), "unexpected stderr: {}", stderr);
+    assert!(!stderr.contains("Failed to read file"), "unexpected stderr: {}", stderr);
+}
+
+/// Generates a solid color image with `pngme generate` for a test to modify
+fn generated_png(name: &str, width: u32, height: u32) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pngme-cli-{}-{}.png", std::process::id(), name));
+    let output = pngme(&[
+        "generate",
+        &width.to_string(),
+        &height.to_string(),
+        path.to_str().unwrap(),
+        "--color",
+        "336699FF",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    path
+}
+
+#[test]
+fn test_merge() {
+    let src = generated_png("merge_src", 4, 4);
+    let src_arg = src.to_str().unwrap();
+    // Rendering intent 1, relative colorimetric
+    for (chunk_type, message) in [("sRGB", "\u{1}"), ("zzZz", "late")] {
+        let output = pngme(&["encode", src_arg, chunk_type, message, "-q"]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+    let dst = generated_png("merge_dst", 8, 8);
+    let original = fs::read(&dst).unwrap();
+    let merged = dst.with_extension("merged.png");
+    let merged_arg = merged.to_str().unwrap();
+
+    let output = pngme(&["merge", src_arg, dst.to_str().unwrap(), merged_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["verify", merged_arg]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let png = Png::try_from(merged.as_path()).unwrap();
+    let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+    let position = |name: &str| types.iter().position(|t| t == name).unwrap();
+    // sRGB must come before IDAT, other ancillary chunks go before IEND
+    assert!(position("sRGB") < position("IDAT"), "{:?}", types);
+    assert_eq!(position("zzZz"), types.len() - 2, "{:?}", types);
+    assert_eq!(fs::read(&dst).unwrap(), original);
+    for path in [src, dst, merged] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_generate() {
+    let path = generated_png("generate", 16, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["verify", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["info", path_arg]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Width: 16\nHeight: 8\nBit depth: 8\n"), "{}", stdout);
+
+    let output = pngme(&["encode", path_arg, "ruSt", "on a generated image"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["decode", path_arg, "ruSt"]);
+    assert_eq!(output.stdout, b"on a generated image");
+
+    let output = pngme(&["generate", "0", "8", path_arg]);
+    assert!(!output.status.success());
+    let output = pngme(&["generate", "1", "1", path_arg, "--color", "blue"]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_refuses_critical_chunk_type() {
+    let path = generated_png("encode_refuses_critical", 8, 8);
+    let original = fs::read(&path).unwrap();
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "IDAT", "oops"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--force-critical"), "unexpected stderr: {}", stderr);
+    assert_eq!(fs::read(&path).unwrap(), original);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_critical_chunk_type_with_force() {
+    let path = generated_png("encode_critical_with_force", 8, 8);
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "PaST", "forced", "--force-critical"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = pngme(&["decode", path.to_str().unwrap(), "PaST"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "forced");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_remove_refuses_critical_chunk_type() {
+    let path = generated_png("remove_refuses_critical", 8, 8);
+    let original = fs::read(&path).unwrap();
+
+    let output = pngme(&["remove", path.to_str().unwrap(), "IHDR"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--force-critical"), "unexpected stderr: {}", stderr);
+    assert_eq!(fs::read(&path).unwrap(), original);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_remove_ihdr_with_force_warns() {
+    let path = generated_png("remove_ihdr_with_force", 8, 8);
+
+    let output = pngme(&["remove", path.to_str().unwrap(), "IHDR", "--force-critical"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("won't be a valid PNG"), "unexpected stderr: {}", stderr);
+    let output = pngme(&["info", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_and_remove_summary() {
+    let path = generated_png("encode_and_remove_summary", 8, 8);
+    let output_path = path.with_extension("out.png");
+    let path_arg = path.to_str().unwrap();
+    let output_arg = output_path.to_str().unwrap();
+    let iend = Png::try_from(path.as_path()).unwrap().len() - 1;
+    let size = fs::metadata(&path).unwrap().len();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "hello", output_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!(
+        "added ruSt (5 bytes) at index {}; file {} bytes -> {} bytes; wrote {}",
+        iend,
+        size,
+        size + 17,
+        output_arg
+    );
+    assert_eq!(stderr.trim(), expected);
+
+    let output = pngme(&["remove", output_arg, "ruSt"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!(
+        "removed 1 ruSt chunk (5 bytes) at index {}, saving 17 bytes; \
+         file {} bytes -> {} bytes; wrote {}",
+        iend,
+        size + 17,
+        size,
+        output_arg
+    );
+    assert_eq!(stderr.trim(), expected);
+
+    let output = pngme(&["encode", path_arg, "ruSt", "hello", "--quiet"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    let output = pngme(&["remove", path_arg, "ruSt", "-q"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&output_path).unwrap();
+}
+
+#[test]
+fn test_ancillary_chunk_types_need_no_force() {
+    let path = generated_png("ancillary_need_no_force", 8, 8);
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "hello"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["remove", path.to_str().unwrap(), "ruSt"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Warning"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_batch_file() {
+    let path = generated_png("encode_batch_file", 8, 8);
+    let batch_path = path.with_extension("csv");
+    fs::write(&batch_path, "ruSt,first\nraIn,second\nrOSt,third\n").unwrap();
+
+    let path_arg = path.to_str().unwrap();
+    let batch_arg = batch_path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "--batch-file", batch_arg]);
+    assert!(output.status.success(), "{:?}", output);
+
+    for (chunk_type, message) in [("ruSt", "first"), ("raIn", "second"), ("rOSt", "third")] {
+        let output = pngme(&["decode", path_arg, chunk_type]);
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), message);
+    }
+    let output = pngme(&["encode", path_arg, "ruSt", "msg", "--batch-file", batch_arg]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&batch_path).unwrap();
+}
+
+#[test]
+fn test_encode_pairs() {
+    let path = generated_png("encode_pairs", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let original = fs::read(&path).unwrap();
+
+    let output = pngme(&[
+        "encode", path_arg, "--pair", "ruSt=first", "--pair", "raIn=a=b", "--pair", "rOSt=third",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let png = Png::try_from(path.as_path()).unwrap();
+    let added: Vec<(String, &[u8])> = png.chunks()[png.len() - 4..png.len() - 1]
+        .iter()
+        .map(|chunk| (chunk.chunk_type().to_string(), chunk.data()))
+        .collect();
+    assert_eq!(
+        added,
+        [
+            ("ruSt".to_string(), &b"first"[..]),
+            ("raIn".to_string(), &b"a=b"[..]),
+            ("rOSt".to_string(), &b"third"[..]),
+        ]
+    );
+
+    fs::write(&path, &original).unwrap();
+    let output = pngme(&[
+        "encode", path_arg, "--pair", "ruSt=first", "--pair", "raIn=second", "--pair", "r0St=bad",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Pair 3"), "{:?}", output);
+    assert_eq!(fs::read(&path).unwrap(), original);
+    fs::remove_file(&path).unwrap();
+}
+
+/// Generates an image with 1 KiB of garbage appended after IEND
+fn png_with_trailing_data(name: &str) -> (PathBuf, Vec<u8>) {
+    let path = generated_png(name, 8, 8);
+    let mut bytes = fs::read(&path).unwrap();
+    bytes.extend((0..1024).map(|i| (i % 251) as u8));
+    fs::write(&path, &bytes).unwrap();
+    (path, bytes)
+}
+
+#[test]
+fn test_encode_keeps_trailing_data() {
+    let (path, original) = png_with_trailing_data("encode_keeps_trailing");
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "hello"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["remove", path.to_str().unwrap(), "ruSt"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(fs::read(&path).unwrap(), original);
+    let output = pngme(&["print", path.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1024 bytes of trailing data"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_strip_trailing_data() {
+    let (path, original) = png_with_trailing_data("encode_strip_trailing");
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "hi", "--strip-trailing"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stripped = fs::read(&path).unwrap();
+    assert_eq!(stripped.len(), original.len() - 1024 + 12 + 2);
+    let output = pngme(&["verify", path.to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("trailing data"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_decode_binary_message() {
+    let path = generated_png("decode_binary_message", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    // The CLI only encodes text, so build the chunk directly
+    let mut png = Png::try_from(&path).unwrap();
+    let chunk_type = ChunkType::from_str("ruSt").unwrap();
+    let iend = png.len() - 1;
+    png.insert_chunk(iend, Chunk::new(chunk_type, b"caf\xC3!".to_vec())).unwrap();
+    png.write_to_file(&path).unwrap();
+
+    // Piped output gets the bytes as they are, with no newline added
+    for args in [&["decode", path_arg, "ruSt"][..], &["decode", path_arg, "ruSt", "--raw"]] {
+        let output = pngme(args);
+        assert!(output.status.success(), "{:?}", output);
+        assert_eq!(output.stdout, b"caf\xC3!");
+        assert!(output.stderr.is_empty(), "{:?}", output);
+    }
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--text"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "caf\u{FFFD}!\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("replaced 1 invalid sequence"), "unexpected stderr: {}", stderr);
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--strict"]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_decode_nth_and_all() {
+    let path = generated_png("decode_nth", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    for message in ["first", "second"] {
+        let output = pngme(&["encode", path_arg, "ruSt", message]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--nth", "0"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "first");
+    let output = pngme(&["decode", path_arg, "ruSt", "--nth", "1"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "second");
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--nth", "5"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only 2 found"), "unexpected stderr: {}", stderr);
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--all"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "first\nsecond\n");
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--all", "--nth", "1"]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+/// Encodes `message` into the file at `path` with and without `--fast`, writing
+/// to separate output files, and checks that both outputs are identical
+fn assert_fast_encode_matches(name: &str, path: &Path) {
+    let slow = path.with_extension("slow.png");
+    let fast = path.with_extension("fast.png");
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "hello", slow.to_str().unwrap()]);
+    assert!(output.status.success(), "{}: {:?}", name, output);
+    let fast_arg = fast.to_str().unwrap();
+    let output = pngme(&["encode", path_arg, "ruSt", "hello", fast_arg, "--fast"]);
+    assert!(output.status.success(), "{}: {:?}", name, output);
+
+    assert_eq!(fs::read(&slow).unwrap(), fs::read(&fast).unwrap(), "{}", name);
+    fs::remove_file(&slow).unwrap();
+    fs::remove_file(&fast).unwrap();
+}
+
+#[test]
+fn test_fast_encode_matches_full_rewrite() {
+    let path = generated_png("fast_encode_sample", 8, 8);
+    assert_fast_encode_matches("sample", &path);
+
+    // Chunks appended after IEND by older versions are kept in place
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "first"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_fast_encode_matches("encoded", &path);
+    fs::remove_file(&path).unwrap();
+
+    let (path, _) = png_with_trailing_data("fast_encode_trailing");
+    assert_fast_encode_matches("trailing data", &path);
+    fs::remove_file(&path).unwrap();
+
+    // Without IEND the fast path falls back to the full rewrite
+    let path = generated_png("fast_encode_no_iend", 8, 8);
+    let mut png = Png::try_from(&path).unwrap();
+    png.remove_chunk(&ChunkType::from_str("IEND").unwrap()).unwrap();
+    png.write_to_file(&path).unwrap();
+    assert_fast_encode_matches("no IEND", &path);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_fast_encode_in_place() {
+    let path = generated_png("fast_encode_in_place", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "quick", "--fast"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["decode", path_arg, "ruSt"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "quick");
+
+    let output = pngme(&["encode", path_arg, "ruSt", "quick", "--fast", "--strict"]);
+    assert!(!output.status.success());
+
+    let output = pngme(&["encode", path_arg, "ruSt", "checked", "--fast", "--verify"]);
+    assert!(output.status.success(), "{:?}", output);
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    assert!(!path.with_file_name(format!(".{}.tmp", file_name)).exists());
+    fs::remove_file(&path).unwrap();
+}
+
+/// Generates an image with one data byte of its first IDAT chunk changed, so
+/// that the CRC of that chunk no longer matches
+fn png_with_corrupt_idat(name: &str) -> PathBuf {
+    let path = generated_png(name, 8, 8);
+    let mut bytes = fs::read(&path).unwrap();
+    let idat = bytes.windows(4).position(|window| window == b"IDAT").unwrap();
+    bytes[idat + 4] ^= 0xFF;
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn test_print_corrupt_idat_with_no_verify() {
+    let path = png_with_corrupt_idat("print_corrupt_idat");
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["print", path_arg]);
+    assert!(!output.status.success());
+
+    let output = pngme(&["print", path_arg, "--no-verify"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[1].starts_with("IDAT") && lines[1].ends_with("  crc=BAD"), "{}", stdout);
+    assert!(lines[2].starts_with("IEND") && !lines[2].contains("BAD"), "{}", stdout);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_verify_reports_corrupt_idat() {
+    let path = png_with_corrupt_idat("verify_corrupt_idat");
+
+    let output = pngme(&["verify", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("IDAT  CRC mismatch"), "unexpected stdout: {}", stdout);
+    assert!(stdout.contains("IEND  ok"), "unexpected stdout: {}", stdout);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_verify_jobs_give_identical_results() {
+    let dir = std::env::temp_dir().join(format!("pngme-cli-{}-verify-jobs", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let generated = generated_png("verify_jobs", 8, 8);
+    let sample = fs::read(&generated).unwrap();
+    fs::remove_file(&generated).unwrap();
+    let paths: Vec<String> = (0..100)
+        .map(|idx| {
+            let mut bytes = sample.clone();
+            if idx % 7 == 0 {
+                // Corrupt the last byte of the IEND CRC
+                *bytes.last_mut().unwrap() ^= 0xFF;
+            }
+            let path = dir.join(format!("{:03}.png", idx));
+            fs::write(&path, bytes).unwrap();
+            path.to_str().unwrap().to_string()
+        })
+        .collect();
+
+    let run = |jobs: &str| {
+        let mut args = vec!["verify", "--jobs", jobs];
+        args.extend(paths.iter().map(String::as_str));
+        pngme(&args)
+    };
+    let serial = run("1");
+    let parallel = run("8");
+
+    assert!(!serial.status.success());
+    assert_eq!(serial.status.code(), parallel.status.code());
+    assert_eq!(serial.stdout, parallel.stdout);
+    assert_eq!(serial.stderr, parallel.stderr);
+    let stderr = String::from_utf8_lossy(&serial.stderr);
+    assert!(stderr.contains("in 15 of 100 files"), "unexpected stderr: {}", stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_encode_random_type() {
+    let path = generated_png("encode_random_type", 8, 8);
+    let output_path = path.with_extension("out.png");
+
+    let output = pngme(&[
+        "encode",
+        path.to_str().unwrap(),
+        "random",
+        "stashed",
+        output_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let chunk_type = stdout.trim().strip_prefix("Encoded into chunk type ").unwrap();
+    let parsed = ChunkType::from_str(chunk_type).unwrap();
+    assert!(parsed.is_valid() && !parsed.is_critical() && !parsed.is_public());
+    assert!(parsed.is_safe_to_copy());
+
+    let output = pngme(&["decode", output_path.to_str().unwrap(), chunk_type]);
+    assert_eq!(output.stdout, b"stashed");
+    let original = Png::try_from(path.as_path()).unwrap();
+    assert!(original.position_of(&parsed).is_none());
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "random", "quick", "--fast"]);
+    assert!(!output.status.success());
+    assert_eq!(Png::try_from(path.as_path()).unwrap().as_bytes(), original.as_bytes());
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&output_path).unwrap();
+}
+
+/// Encodes into a freshly generated image with `--deterministic` and
+/// `args`, and returns the bytes written
+fn encode_deterministic(name: &str, args: &[&str]) -> Vec<u8> {
+    let path = generated_png(name, 8, 8);
+    let output = Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(["encode", path.to_str().unwrap(), "--deterministic", "--timestamp"])
+        .args(args)
+        .env("SOURCE_DATE_EPOCH", "1700000000")
+        .output()
+        .expect("failed to run pngme");
+    assert!(output.status.success(), "{:?}", output);
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    bytes
+}
+
+#[test]
+fn test_encode_deterministic() {
+    let args = ["random", "stashed", "--seed", "7"];
+    let first = encode_deterministic("deterministic_1", &args);
+    assert_eq!(encode_deterministic("deterministic_2", &args), first);
+
+    let other_seed = encode_deterministic("deterministic_3", &["random", "stashed"]);
+    assert_ne!(other_seed, first);
+
+    // 2023-11-14 22:13:20 UTC, from SOURCE_DATE_EPOCH
+    let time = [0x07, 0xE7, 11, 14, 22, 13, 20];
+    assert!(first.windows(11).any(|window| window[..4] == *b"tIME" && window[4..] == time));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encode_deterministic_with_password() {
+    let args = ["ruSt", "top secret", "--password", "hunter2"];
+    let first = encode_deterministic("deterministic_password_1", &args);
+    assert_eq!(encode_deterministic("deterministic_password_2", &args), first);
+}
+
+#[test]
+fn test_encode_idempotent() {
+    let path = generated_png("encode_idempotent", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let original = fs::read(&path).unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "tagged", "--idempotent"]);
+    assert!(output.status.success(), "{:?}", output);
+    let encoded = fs::read(&path).unwrap();
+    assert_ne!(encoded, original);
+
+    // An old modification time, so that any write would show
+    let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "tagged", "--idempotent"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already present, nothing to do"), "unexpected stderr: {}", stderr);
+    assert_eq!(fs::read(&path).unwrap(), encoded);
+    assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+
+    // An output file gets a byte for byte copy of the input
+    let copy = path.with_extension("copy.png");
+    let copy_arg = copy.to_str().unwrap();
+    let output = pngme(&["encode", path_arg, "ruSt", "tagged", copy_arg, "--idempotent"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read(&copy).unwrap(), encoded);
+    fs::remove_file(&copy).unwrap();
+
+    // Only the pairs not already present are added
+    let pairs = ["--pair", "ruSt=tagged", "--pair", "ruSt=retagged", "--idempotent"];
+    let output = pngme(&[&["encode", path_arg][..], &pairs].concat());
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ruSt already present, skipped"), "unexpected stderr: {}", stderr);
+    let output = pngme(&["decode", path_arg, "ruSt", "--all"]);
+    assert_eq!(output.stdout, b"tagged\nretagged\n");
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encode_idempotent_conflicts_with_password() {
+    let args = ["encode", "x.png", "ruSt", "secret", "--idempotent", "--password", "hunter2"];
+    let output = pngme(&args);
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+}
+
+#[test]
+fn test_decode_count_and_quiet() {
+    let path = generated_png("decode_count", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--count"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, b"0\n");
+    let output = pngme(&["decode", path_arg, "ruSt", "--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty(), "{:?}", output);
+
+    for message in ["first", "second"] {
+        let output = pngme(&["encode", path_arg, "ruSt", message]);
+        assert!(output.status.success(), "{:?}", output);
+    }
+    let output = pngme(&["decode", path_arg, "ruSt", "--count"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(output.stdout, b"2\n");
+
+    for args in [&["--quiet"][..], &["-q", "--nth", "1"], &["-q", "--all"]] {
+        let output = pngme(&[&["decode", path_arg, "ruSt"][..], args].concat());
+        assert!(output.status.success(), "{:?}", output);
+        assert!(output.stdout.is_empty(), "{:?}", output);
+    }
+    let output = pngme(&["decode", path_arg, "ruSt", "--quiet", "--nth", "2"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty(), "{:?}", output);
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--count", "--quiet"]);
+    assert!(!output.status.success());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_no_progress_bar_when_not_a_terminal() {
+    // Big enough for a progress bar, if stderr were a terminal
+    let path = generated_png("no_progress_bar", 8, 8);
+    let mut png = Png::try_from(path.as_path()).unwrap();
+    let iend = png.position_of(&ChunkType::IEND).unwrap();
+    let padding = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 17 * 1024 * 1024]);
+    png.insert_chunk(iend, padding).unwrap();
+    png.write_to_file(&path).unwrap();
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["verify", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+
+    let output = pngme(&["encode", path_arg, "raIn", "hello"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("added raIn") && stderr.lines().count() == 1, "{}", stderr);
+    assert!(!stderr.contains(['\r', '\x1b']), "unexpected stderr: {:?}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_doctor() {
+    let path = generated_png("doctor", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["doctor", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "No problems found\n");
+
+    // Warnings and info only
+    let mut png = Png::try_from(path.as_path()).unwrap();
+    let iend = png.position_of(&ChunkType::IEND).unwrap();
+    png.insert_chunk(iend, Chunk::new(ChunkType::from_str("pHYs").unwrap(), vec![0; 9]))
+        .unwrap();
+    png.insert_chunk(1, Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec()))
+        .unwrap();
+    png.write_to_file(&path).unwrap();
+
+    let output = pngme(&["doctor", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!(
+        "\
+warning (1):
+  [chunk-order] chunk {}: pHYs comes after IDAT, it must come before it
+info (1):
+  [private-chunk] chunk 1: private chunk type ruSt
+",
+        iend + 1
+    );
+    assert_eq!(stdout, expected);
+    let output = pngme(&["doctor", path_arg, "--strict"]);
+    assert!(!output.status.success());
+
+    png.insert_chunk(1, Chunk::new(ChunkType::from_str("RUST").unwrap(), vec![]))
+        .unwrap();
+    png.write_to_file(&path).unwrap();
+    let output = pngme(&["doctor", path_arg]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("error (1):\n  [unknown-critical] chunk 1"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Found 1 error(s) and 1 warning(s)"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_color_never() {
+    let path = generated_png("print_color_never", 8, 8);
+    let output = pngme(&["print", path.to_str().unwrap(), "--color", "never"]);
+    assert!(output.status.success(), "{:?}", output);
+    let expected: String = Png::try_from(path.as_path())
+        .unwrap()
+        .chunks()
+        .iter()
+        .map(|chunk| {
+            format!("{}  {} bytes  crc=0x{:08x}\n", chunk.chunk_type(), chunk.length(), chunk.crc())
+        })
+        .collect();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_summary() {
+    // Two more IDAT chunks, so the image data is still the largest total
+    let path = generated_png("print_summary", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let mut png = Png::try_from(path.as_path()).unwrap();
+    let idat = png.chunks()[1].clone();
+    png.insert_chunk(2, idat.clone()).unwrap();
+    png.insert_chunk(2, idat.clone()).unwrap();
+    let text = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Title\0Generated".to_vec());
+    png.insert_chunk(4, text).unwrap();
+    png.write_to_file(&path).unwrap();
+
+    let output = pngme(&["print", path_arg, "--summary"]);
+    assert!(output.status.success(), "{:?}", output);
+    let idat_bytes = 3 * idat.data().len();
+    let total = idat_bytes + 15 + 13;
+    let row = |name: &str, count: usize, bytes: usize| {
+        let percent = bytes as f64 * 100.0 / total as f64;
+        format!("{:<6} {:>6} {:>12} {:>9.1}%\n", name, count, bytes, percent)
+    };
+    let expected = [
+        "TYPE    COUNT  TOTAL_BYTES  %_OF_DATA\n".to_string(),
+        row("IDAT", 3, idat_bytes),
+        row("tEXt", 1, 15),
+        row("IHDR", 1, 13),
+        row("IEND", 1, 0),
+        format!("{}\n", "-".repeat(37)),
+        row("total", 6, total),
+    ]
+    .concat();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = pngme(&["print", path_arg, "--summary", "--format", "csv"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("file,type,count,total_bytes\n{},IDAT,3,{}\n", path_arg, idat_bytes);
+    assert!(stdout.starts_with(&expected), "{}", stdout);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_color_always() {
+    let path = png_with_corrupt_idat("print_color_always");
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["print", path_arg, "--no-verify", "--color", "always"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[34mIHDR\x1b[0m"), "unexpected stdout: {}", stdout);
+    assert!(stdout.contains("\x1b[31;1mIDAT"), "unexpected stdout: {}", stdout);
+
+    // Not a terminal, so `auto` leaves the output plain
+    let output = pngme(&["print", path_arg, "--no-verify"]);
+    assert!(!output.stdout.contains(&0x1b), "{:?}", output);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_data() {
+    let path = generated_png("print_data", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let output = pngme(&["encode", path_arg, "ruSt", "hi there", "-q"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = pngme(&["print", path_arg]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Data ("));
+
+    let output = pngme(&["print", path_arg, "--data"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("ruSt  8 bytes"), "unexpected stdout: {}", stdout);
+    assert!(stdout.contains("\n  Data (text): hi there\n"), "unexpected stdout: {}", stdout);
+
+    for args in [&["--data-hex"][..], &["--data", "--data-hex"]] {
+        let output = pngme(&[&["print", path_arg], args].concat());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let expected = "\n  Data (hex): 68 69 20 74 68 65 72 65\n";
+        assert!(stdout.contains(expected), "unexpected stdout: {}", stdout);
+        assert!(!stdout.contains("Data (text)"), "unexpected stdout: {}", stdout);
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_csv() {
+    // A comma and quotes in the file name exercise the CSV quoting
+    let source = generated_png("print_csv", 8, 8);
+    let path = source.with_file_name(format!("pngme-cli-{}-print,\"csv\".png", std::process::id()));
+    fs::rename(&source, &path).unwrap();
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "a, \"quoted\" message"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = pngme(&["print", path.to_str().unwrap(), "--format", "csv"]);
+    assert!(output.status.success(), "{:?}", output);
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    assert_eq!(
+        reader.headers().unwrap(),
+        vec![
+            "file",
+            "index",
+            "offset",
+            "type",
+            "length",
+            "crc_hex",
+            "critical",
+            "public",
+            "safe_to_copy"
+        ]
+    );
+    let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap()).collect();
+    let png = Png::try_from(path.as_path()).unwrap();
+    assert_eq!(rows.len(), png.len());
+    assert_eq!(&rows[0][0], path.to_str().unwrap());
+    assert_eq!(
+        rows[0].iter().skip(1).collect::<Vec<_>>(),
+        ["0", "8", "IHDR", "13", &format!("{:08x}", png[0].crc()), "true", "true", "false"]
+    );
+
+    let rust = png.position_of(&ChunkType::from_str("ruSt").unwrap()).unwrap();
+    let spans = png.chunk_spans();
+    let row = &rows[rust];
+    assert_eq!(&row[1], rust.to_string());
+    assert_eq!(&row[2], spans[rust].offset.to_string());
+    assert_eq!(&row[3], "ruSt");
+    assert_eq!(&row[4], "19");
+    assert_eq!(&row[5], format!("{:08x}", png[rust].crc()));
+    assert_eq!([&row[6], &row[7], &row[8]], ["false", "false", "true"]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_print_json() {
+    // Quotes and a backslash in the file name exercise the JSON escaping
+    let source = generated_png("print_json", 8, 8);
+    let name = format!("pngme-cli-{}-print\"json\\\".png", std::process::id());
+    let path = source.with_file_name(name);
+    fs::rename(&source, &path).unwrap();
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["print", path_arg, "--format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let expected = serde_json::json!({
+        "file": path_arg,
+        "index": 2,
+        "offset": Png::try_from(path.as_path()).unwrap().chunk_spans()[2].offset,
+        "type": "IEND",
+        "length": 0,
+        "crc_hex": "ae426082",
+        "critical": true,
+        "public": true,
+        "safe_to_copy": false,
+    });
+    assert_eq!(rows[2], expected);
+
+    let output = pngme(&["print", path_arg, "--summary", "--format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let rows: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let expected =
+        serde_json::json!({"file": path_arg, "type": "IEND", "count": 1, "total_bytes": 0});
+    assert_eq!(rows[2], expected);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_info_csv() {
+    let path = generated_png("info_csv", 12, 10);
+    let output = pngme(&["info", path.to_str().unwrap(), "--format", "csv"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap()).collect();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0].iter().skip(1).collect::<Vec<_>>(),
+        ["12", "10", "8", "6", "0", "0", "0"]
+    );
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_decode_json() {
+    let path = generated_png("decode_json", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let message = r#"{"name":"pngme","tags":["a","b"],"nested":{"n":1}}"#;
+    let output = pngme(&["encode", path_arg, "ruSt", message]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\n  \"name\": \"pngme\""), "not pretty-printed: {}", stdout);
+    let decoded: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(decoded, serde_json::from_str::<serde_json::Value>(message).unwrap());
+
+    let output = pngme(&["encode", path_arg, "teXt", "plain words"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["decode", path_arg, "teXt", "--json"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not valid JSON"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_export_import_round_trip() {
+    let path = generated_png("export_import", 8, 8);
+    let json_path = path.with_extension("json");
+    let rebuilt_path = path.with_extension("rebuilt.png");
+
+    let output = pngme(&["export", path.to_str().unwrap(), json_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["import", json_path.to_str().unwrap(), rebuilt_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read(&rebuilt_path).unwrap(), fs::read(&path).unwrap());
+
+    let json = fs::read_to_string(&json_path).unwrap();
+    fs::write(&json_path, json.replacen("\"IDAT\"", "\"ID4T\"", 1)).unwrap();
+    let output = pngme(&["import", json_path.to_str().unwrap(), rebuilt_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ChunkType"), "unexpected stderr: {}", stderr);
+
+    for path in [path, json_path, rebuilt_path] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_encode_compressed() {
+    let path = generated_png("encode_compressed", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let message = "compress me ".repeat(50);
+
+    let output = pngme(&["encode", path_arg, "ruSt", &message, "--compress", "zlib:9"]);
+    assert!(output.status.success(), "{:?}", output);
+    let png = Png::try_from(path.as_path()).unwrap();
+    let data = png.chunk_by_type("ruSt").unwrap().data();
+    assert!(data.starts_with(b"PMZ\0") && data.len() < 100, "{:?}", data);
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--decompress", "--text"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{}\n", message));
+
+    let output = pngme(&["encode", path_arg, "raIn", "hi", "--compress", "lzma"]);
+    assert!(!output.status.success());
+    #[cfg(feature = "zstd")]
+    {
+        let output = pngme(&["encode", path_arg, "rOSt", &message, "--compress", "zstd:3"]);
+        assert!(output.status.success(), "{:?}", output);
+        let output = pngme(&["decode", path_arg, "rOSt", "--decompress", "--text"]);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{}\n", message));
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn test_encode_with_password() {
+    let path = generated_png("encode_with_password", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "top secret", "--password", "hunter2"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stored = Png::try_from(path.as_path()).unwrap();
+    let data = stored.chunk_by_type("ruSt").unwrap().data();
+    assert!(!data.windows(10).any(|window| window == b"top secret"));
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--decrypt", "--password", "hunter2"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "top secret");
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--decrypt", "--password", "hunter3"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Wrong password"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "ecc")]
+#[test]
+fn test_encode_with_ecc() {
+    let path = generated_png("encode_with_ecc", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "survives damage", "--ecc", "--parity", "30"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // Flip bytes in the chunk data, past the length and type fields
+    let png = Png::try_from(path.as_path()).unwrap();
+    let index = png.position_of(&ChunkType::from_str("ruSt").unwrap()).unwrap();
+    let start = png.chunk_spans()[index].offset as usize + 8;
+    let mut bytes = fs::read(&path).unwrap();
+    for offset in [0, 3, 15, 20] {
+        bytes[start + offset] ^= 0xFF;
+    }
+    fs::write(&path, &bytes).unwrap();
+
+    let output = pngme(&["decode", path_arg, "ruSt", "--ecc", "--text"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "survives damage\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Repaired 4 damaged byte(s)\n");
+
+    for offset in 12..70 {
+        bytes[start + offset] ^= 0xFF;
+    }
+    fs::write(&path, &bytes).unwrap();
+    let output = pngme(&["decode", path_arg, "ruSt", "--ecc"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 1 block(s) are damaged"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+/// Serves `body` at every path except `/missing.png` from a local HTTP server
+/// on a background thread, returning the server's base URL
+#[cfg(feature = "http")]
+fn serve_png(body: Vec<u8>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", server.server_addr());
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/missing.png" {
+                tiny_http::Response::from_data(Vec::new()).with_status_code(404)
+            } else {
+                tiny_http::Response::from_data(body.clone())
+            };
+            request.respond(response).unwrap();
+        }
+    });
+    base_url
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_read_png_from_url() {
+    let path = generated_png("read_png_from_url", 8, 8);
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "served over http"]);
+    assert!(output.status.success(), "{:?}", output);
+    let base_url = serve_png(fs::read(&path).unwrap());
+    let url = format!("{}/dice.png", base_url);
+
+    let output = pngme(&["decode", &url, "ruSt"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "served over http");
+
+    let output = pngme(&["print", &url]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("ruSt"));
+
+    let output = pngme(&["verify", &url]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let missing = format!("{}/missing.png", base_url);
+    let output = pngme(&["decode", &missing, "ruSt"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&missing), "unexpected stderr: {}", stderr);
+    assert!(stderr.contains("404"), "unexpected stderr: {}", stderr);
+
+    let output = pngme(&["decode", &url, "ruSt", "--max-download-size", "64"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("download limit"), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_url_input_needs_somewhere_to_write() {
+    let source = generated_png("url_write", 8, 8);
+    let url = format!("{}/dice.png", serve_png(fs::read(&source).unwrap()));
+    fs::remove_file(&source).unwrap();
+
+    let output = pngme(&["encode", &url, "ruSt", "nowhere to go"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("give an output file"), "unexpected stderr: {}", stderr);
+
+    let output = pngme(&["remove", &url, "ruSt"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only local files"), "unexpected stderr: {}", stderr);
+
+    let output_path = generated_png("url_write_output", 8, 8);
+    let output = pngme(&["encode", &url, "ruSt", "saved locally", output_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["decode", output_path.to_str().unwrap(), "ruSt"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "saved locally");
+    fs::remove_file(&output_path).unwrap();
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_matches_streaming() {
+    let path = generated_png("through_mmap", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    // Make the file large enough to be memory-mapped
+    let mut png = Png::try_from(&path).unwrap();
+    let iend = png.len() - 1;
+    let idat = Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![0; 17 * 1024 * 1024]);
+    png.insert_chunk(iend, idat).unwrap();
+    png.insert_chunk(iend + 1, Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"big".to_vec()))
+        .unwrap();
+    png.write_to_file(&path).unwrap();
+    let commands = [
+        vec!["decode", path_arg, "ruSt"],
+        vec!["decode", path_arg, "ruSt", "--all"],
+        vec!["print", path_arg],
+        vec!["verify", path_arg],
+    ];
+    let compare = |expect_success: [bool; 4]| {
+        for (command, success) in commands.iter().zip(expect_success) {
+            let mapped = pngme(command);
+            let streamed = pngme(&[command.as_slice(), &["--no-mmap"]].concat());
+            assert_eq!(mapped.status.success(), success, "{:?}: {:?}", command, mapped);
+            assert_eq!(mapped.status.code(), streamed.status.code(), "{:?}", command);
+            assert_eq!(mapped.stdout, streamed.stdout, "{:?}", command);
+        }
+    };
+    compare([true; 4]);
+
+    // A bad CRC in a chunk that decode skips over only fails the full parses
+    let mut bytes = fs::read(&path).unwrap();
+    let offset = png.chunk_spans()[iend].offset as usize + 8;
+    bytes[offset] ^= 0xFF;
+    fs::write(&path, bytes).unwrap();
+    compare([true, false, false, false]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_completions() {
+    for shell in ["bash", "zsh", "fish"] {
+        let output = pngme(&["completions", shell]);
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("encode") && stdout.contains("doctor"), "{}", stdout);
+    }
+    assert!(!pngme(&["completions", "tcsh"]).status.success());
+}
+
+#[test]
+fn test_reorder() {
+    let path = generated_png("reorder", 8, 8);
+    let path_arg = path.to_str().unwrap();
+    let mut png = Png::try_from(path.as_path()).unwrap();
+    let len = png.len();
+    png.append_chunk(Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"late".to_vec()));
+    png.write_to_file(&path).unwrap();
+
+    let output = pngme(&["reorder", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    let expected = format!("moved ruSt from chunk {} to chunk {}\n", len, len - 1);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    let png = Png::try_from(path.as_path()).unwrap();
+    assert!(png.validate().is_ok());
+    assert_eq!(png.chunks()[len - 1].chunk_type().to_string(), "ruSt");
+
+    let output_path = path.with_extension("ordered.png");
+    let output = pngme(&["reorder", path_arg, output_path.to_str().unwrap()]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Chunks are already in order\n");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read(&output_path).unwrap(), fs::read(&path).unwrap());
+    fs::remove_file(&output_path).unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_steg() {
+    let path = generated_png("steg", 32, 24);
+    let path_arg = path.to_str().unwrap();
+    let png = Png::try_from(path.as_path()).unwrap();
+    let capacity = steg::capacity(&png).unwrap();
+    // IDAT is rebuilt in chunks as large as the first one, the rest stays put
+    let other_types = |png: &Png| -> Vec<String> {
+        let types = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string());
+        types.filter(|chunk_type| chunk_type != "IDAT").collect()
+    };
+    let types_before = other_types(&png);
+
+    let output = pngme(&["steg", "encode", path_arg, "hidden in plain sight"]);
+    assert!(output.status.success(), "{:?}", output);
+    let png = Png::try_from(path.as_path()).unwrap();
+    assert!(png.validate().is_ok());
+    assert_eq!(other_types(&png), types_before);
+
+    let output = pngme(&["steg", "decode", path_arg]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hidden in plain sight\n");
+
+    let message = "x".repeat(capacity + 1);
+    let output = pngme(&["steg", "encode", path_arg, &message]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!("can only hide {} bytes", capacity);
+    assert!(stderr.contains(&expected), "unexpected stderr: {}", stderr);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_capacity() {
+    let path = generated_png("capacity", 64, 48);
+    let path_arg = path.to_str().unwrap();
+    let mut png = Png::try_from(path.as_path()).unwrap();
+    let lsb = steg::capacity(&png).unwrap();
+    let iend = png.position_of(&ChunkType::IEND).unwrap();
+    png.insert_chunk(iend, Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 100]))
+        .unwrap();
+    png.write_to_file(&path).unwrap();
+
+    let output = pngme(&["capacity", path_arg, "--chunk-size", "100", "--budget", "250"]);
+    assert!(output.status.success(), "{:?}", output);
+    let expected = format!(
+        "\
+Single chunk: 2147483647 bytes
+Chunks of 100 bytes: 214 bytes in a budget of 250 bytes
+Already in custom chunks: 100 bytes
+Sample LSBs: {} bytes
+",
+        lsb
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = pngme(&["capacity", path_arg, "--format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let capacity: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(capacity["file"], path_arg);
+    assert_eq!((&capacity["custom"], &capacity["lsb"]), (&100.into(), &lsb.into()));
+
+    // Palette images have no sample capacity
+    let palette = generated_png("capacity_palette", 4, 4);
+    let mut png = Png::try_from(palette.as_path()).unwrap();
+    let mut ihdr = png.chunks()[0].data().to_vec();
+    ihdr[9] = 3;
+    png.chunks_mut()[0] = Chunk::new(ChunkType::from_str("IHDR").unwrap(), ihdr);
+    png.write_to_file(&palette).unwrap();
+    let output = pngme(&["capacity", palette.to_str().unwrap(), "--format", "json"]);
+    assert!(output.status.success(), "{:?}", output);
+    let capacity: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(capacity["lsb"].is_null(), "{}", capacity);
+    fs::remove_file(&palette).unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_encode_verify() {
+    let path = generated_png("encode_verify", 8, 8);
+    let path_arg = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path_arg, "ruSt", "checked", "--verify"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output =
+        pngme(&["encode", path_arg, "--pair", "raIn=one", "--pair", "rOSt=two", "--verify"]);
+    assert!(output.status.success(), "{:?}", output);
+    let output = pngme(&["encode", path_arg, "ruSt", "fast", "--fast", "--verify"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let png = Png::try_from(path.as_path()).unwrap();
+    assert_eq!(png.chunk_by_type("rOSt").unwrap().data(), b"two");
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,50 @@
+"""Tests for the Python bindings. Build and install them with
+`maturin develop`, then run `pytest tests/python`."""
+
+import pytest
+
+import pngme
+
+
+def generated_bytes():
+    return pngme.Png.generate(8, 8, "336699FF").to_bytes()
+
+
+def test_round_trip():
+    original = generated_bytes()
+    png = pngme.Png.from_bytes(original)
+    assert png.to_bytes() == original
+
+    chunk_types = [chunk_type for chunk_type, _, _ in png.chunks()]
+    assert chunk_types[0] == "IHDR"
+    assert chunk_types[-1] == "IEND"
+    assert len(png) == len(chunk_types)
+
+
+def test_edit_chunks():
+    png = pngme.Png.from_bytes(generated_bytes())
+    assert png.find("ruSt") is None
+
+    png.append_chunk("ruSt", b"hello from python")
+    chunk_type, data, crc = png.find("ruSt")
+    assert (chunk_type, data) == ("ruSt", b"hello from python")
+    assert png.chunks()[-1] == (chunk_type, data, crc)
+
+    reparsed = pngme.Png.from_bytes(png.to_bytes())
+    assert reparsed.find("ruSt") == (chunk_type, data, crc)
+    assert reparsed.remove_chunk("ruSt") == (chunk_type, data, crc)
+    assert reparsed.find("ruSt") is None
+
+    with pytest.raises(ValueError, match="No chunk found with chunk type ruSt"):
+        reparsed.remove_chunk("ruSt")
+    with pytest.raises(ValueError, match="ChunkType"):
+        png.append_chunk("ru1t", b"")
+
+
+def test_invalid_signature():
+    with pytest.raises(ValueError) as error:
+        pngme.Png.from_bytes(b"GIF89a\x01\x00\x01\x00")
+    assert str(error.value) == (
+        "Png: Invalid signature, expected 89 50 4E 47 0D 0A 1A 0A "
+        "but found 47 49 46 38 39 61 01 00 (this looks like a GIF file)"
+    )
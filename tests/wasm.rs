@@ -0,0 +1,39 @@
+//! Run with `wasm-pack test --node -- --no-default-features --features wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use std::convert::TryFrom;
+
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::export::PngDocument;
+use pngme::png::Png;
+use pngme::wasm::{add_chunk, parse_png};
+
+fn testing_png() -> Vec<u8> {
+    Png::from_chunks(vec![Chunk::new(ChunkType::IHDR, vec![0; 13]), Chunk::new_iend()]).as_bytes()
+}
+
+#[wasm_bindgen_test]
+fn test_add_chunk_round_trip() {
+    let bytes = add_chunk(&testing_png(), "ruSt", b"from the browser").unwrap();
+
+    let png = Png::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(png.len(), 3);
+    assert_eq!(png[1].chunk_type(), "ruSt");
+    assert_eq!(png[1].data(), b"from the browser");
+
+    let json = parse_png(&bytes).unwrap().as_string().unwrap();
+    let document = PngDocument::from_json(&json).unwrap();
+    assert_eq!(document.to_png(false).unwrap().as_bytes(), bytes);
+    let types: Vec<&str> = document.chunks.iter().map(|c| c.chunk_type.as_str()).collect();
+    assert_eq!(types, ["IHDR", "ruSt", "IEND"]);
+}
+
+#[wasm_bindgen_test]
+fn test_add_chunk_rejects_bad_input() {
+    assert!(add_chunk(b"not a png", "ruSt", b"").is_err());
+    assert!(add_chunk(&testing_png(), "ru1t", b"").is_err());
+    assert!(parse_png(b"not a png").is_err());
+}